@@ -170,6 +170,14 @@ function getAvailableThemes() {
     return [...AVAILABLE_THEMES];
 }
 
+// Toggle via a delegated listener instead of inline onclick handlers,
+// which the Content-Security-Policy does not allow
+document.addEventListener('click', function(event) {
+    if (event.target instanceof Element && event.target.matches('input.theme-controller')) {
+        toggleTheme();
+    }
+});
+
 // Auto-initialize when DOM is ready
 if (document.readyState === 'loading') {
     document.addEventListener('DOMContentLoaded', function() {
@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use url::Url;
+
+use crate::view::about;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of checking one external URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Answered with a success or redirect status.
+    Ok(u16),
+    /// Answered with a client or server error status.
+    Broken(u16),
+    /// Could not be reached at all.
+    Failed(String),
+    /// Not checked, e.g. offline and not in the cache.
+    Unknown,
+}
+
+impl Status {
+    fn is_broken(&self) -> bool {
+        matches!(self, Status::Broken(_) | Status::Failed(_))
+    }
+}
+
+/// Decides whether a URL is reachable. The network is the default; offline
+/// builds answer from a results file and tests can pass a plain closure.
+pub trait LinkChecker {
+    fn check(&self, url: &str) -> Status;
+}
+
+impl<F: Fn(&str) -> Status> LinkChecker for F {
+    fn check(&self, url: &str) -> Status {
+        self(url)
+    }
+}
+
+pub struct HttpChecker {
+    agent: ureq::Agent,
+}
+
+impl HttpChecker {
+    pub fn new() -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .http_status_as_error(false)
+            .user_agent("focus-link-checker")
+            .build()
+            .into();
+        HttpChecker { agent }
+    }
+}
+
+impl LinkChecker for HttpChecker {
+    fn check(&self, url: &str) -> Status {
+        // Some servers refuse HEAD, ask again with GET before calling it broken
+        let response = match self.agent.head(url).call() {
+            Ok(response) if response.status().is_client_error() => self.agent.get(url).call(),
+            other => other,
+        };
+
+        match response {
+            Ok(response) if response.status().as_u16() < 400 => {
+                Status::Ok(response.status().as_u16())
+            }
+            Ok(response) => Status::Broken(response.status().as_u16()),
+            Err(err) => Status::Failed(err.to_string()),
+        }
+    }
+}
+
+/// Answers from a results file first and falls back to another checker,
+/// if any, for URLs it has not seen. Only working URLs are trusted from the
+/// file; broken ones are checked again while online, as they may be back.
+pub struct CachedChecker<C> {
+    results: BTreeMap<String, Status>,
+    fallback: Option<C>,
+}
+
+impl<C: LinkChecker> LinkChecker for CachedChecker<C> {
+    fn check(&self, url: &str) -> Status {
+        match (self.results.get(url), &self.fallback) {
+            (Some(status @ Status::Ok(_)), _) | (Some(status), None) => status.clone(),
+            (_, Some(fallback)) => fallback.check(url),
+            (None, None) => Status::Unknown,
+        }
+    }
+}
+
+/// Reads a results file of `status url` lines, status being the HTTP code
+/// or `error`. A missing file is an empty cache.
+pub fn load_cache(path: &Path) -> io::Result<BTreeMap<String, Status>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(err),
+    };
+
+    let results = content
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .map(|(status, url)| {
+            let status = match status.parse::<u16>() {
+                Ok(code) if code < 400 => Status::Ok(code),
+                Ok(code) => Status::Broken(code),
+                Err(_) => Status::Failed("failed when cached".to_string()),
+            };
+            (url.trim().to_string(), status)
+        })
+        .collect();
+    Ok(results)
+}
+
+pub fn save_cache(path: &Path, report: &[Entry]) -> io::Result<()> {
+    let lines: String = report
+        .iter()
+        .filter_map(|entry| {
+            let status = match &entry.status {
+                Status::Ok(code) | Status::Broken(code) => code.to_string(),
+                Status::Failed(_) => "error".to_string(),
+                Status::Unknown => return None,
+            };
+            Some(format!("{status} {}\n", entry.url))
+        })
+        .collect();
+    fs::write(path, lines)
+}
+
+/// Every http(s) URL linked from a post, with the `file:line` places it is used.
+pub fn collect() -> BTreeMap<String, Vec<String>> {
+    let mut links: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (file, first_line, content) in about::post_sources() {
+        for link in super::links(content) {
+            let external =
+                Url::parse(&link.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if external {
+                let line = first_line + link.line - 1;
+                links
+                    .entry(link.url)
+                    .or_default()
+                    .push(format!("{file}:{line}"));
+            }
+        }
+    }
+
+    links
+}
+
+pub struct Entry {
+    pub url: String,
+    pub status: Status,
+    pub locations: Vec<String>,
+}
+
+pub fn check_all(links: BTreeMap<String, Vec<String>>, checker: &impl LinkChecker) -> Vec<Entry> {
+    links
+        .into_iter()
+        .map(|(url, locations)| Entry {
+            status: checker.check(&url),
+            url,
+            locations,
+        })
+        .collect()
+}
+
+fn print(report: &[Entry]) {
+    for entry in report {
+        let status = match &entry.status {
+            Status::Ok(code) => format!("ok      {code}"),
+            Status::Broken(code) => format!("broken  {code}"),
+            Status::Failed(reason) => format!("broken  ({reason})"),
+            Status::Unknown => "unknown (not checked)".to_string(),
+        };
+        println!("{status}  {}", entry.url);
+        if entry.status.is_broken() {
+            for location in &entry.locations {
+                println!("        {location}");
+            }
+        }
+    }
+}
+
+/// `focus check --links [--offline] [--cache <file>]`. Returns whether any
+/// link is broken.
+pub fn run(args: &[String]) -> io::Result<bool> {
+    let offline = args.iter().any(|arg| arg == "--offline");
+    let cache: Option<PathBuf> = args
+        .iter()
+        .position(|arg| arg == "--cache")
+        .map(|index| {
+            args.get(index + 1).map(PathBuf::from).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "--cache needs a file path")
+            })
+        })
+        .transpose()?;
+
+    let results = match &cache {
+        Some(path) => load_cache(path)?,
+        None => BTreeMap::new(),
+    };
+    if offline && cache.is_none() {
+        eprintln!("warning: --offline without --cache, no link can be checked");
+    }
+
+    let checker = CachedChecker {
+        results,
+        fallback: (!offline).then(HttpChecker::new),
+    };
+    let report = check_all(collect(), &checker);
+    print(&report);
+
+    let broken = report
+        .iter()
+        .filter(|entry| entry.status.is_broken())
+        .count();
+    let unknown = report
+        .iter()
+        .filter(|entry| entry.status == Status::Unknown)
+        .count();
+    println!(
+        "Checked {} external link(s): {} ok, {broken} broken, {unknown} unknown",
+        report.len(),
+        report.len() - broken - unknown
+    );
+
+    if let Some(path) = &cache {
+        save_cache(path, &report)?;
+    }
+
+    Ok(broken > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    fn cached(results: &[(&str, Status)]) -> BTreeMap<String, Status> {
+        results
+            .iter()
+            .map(|(url, status)| (url.to_string(), status.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn trusts_only_working_cached_urls_online() {
+        let asked = RefCell::new(Vec::new());
+        let fallback = |url: &str| {
+            asked.borrow_mut().push(url.to_string());
+            Status::Ok(200)
+        };
+        let checker = CachedChecker {
+            results: cached(&[
+                ("https://ok.example/", Status::Ok(301)),
+                ("https://gone.example/", Status::Broken(404)),
+                (
+                    "https://down.example/",
+                    Status::Failed("timeout".to_string()),
+                ),
+            ]),
+            fallback: Some(fallback),
+        };
+
+        assert_eq!(checker.check("https://ok.example/"), Status::Ok(301));
+        assert_eq!(checker.check("https://gone.example/"), Status::Ok(200));
+        assert_eq!(checker.check("https://down.example/"), Status::Ok(200));
+        assert_eq!(checker.check("https://new.example/"), Status::Ok(200));
+        assert_eq!(
+            *asked.borrow(),
+            [
+                "https://gone.example/",
+                "https://down.example/",
+                "https://new.example/"
+            ]
+        );
+    }
+
+    #[test]
+    fn offline_answers_from_the_cache() {
+        let checker: CachedChecker<fn(&str) -> Status> = CachedChecker {
+            results: cached(&[("https://gone.example/", Status::Broken(410))]),
+            fallback: None,
+        };
+        assert_eq!(checker.check("https://gone.example/"), Status::Broken(410));
+        assert_eq!(checker.check("https://new.example/"), Status::Unknown);
+    }
+
+    #[test]
+    fn check_all_keeps_locations() {
+        let links = BTreeMap::from([
+            (
+                "https://a.example/".to_string(),
+                vec!["posts/a.md:3".to_string()],
+            ),
+            (
+                "https://b.example/".to_string(),
+                vec!["posts/b.md:1".to_string()],
+            ),
+        ]);
+        let checker = |url: &str| {
+            if url.contains("b.") {
+                Status::Broken(500)
+            } else {
+                Status::Ok(200)
+            }
+        };
+
+        let report = check_all(links, &checker);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].status, Status::Ok(200));
+        assert_eq!(report[1].status, Status::Broken(500));
+        assert_eq!(report[1].locations, ["posts/b.md:1"]);
+        assert!(report[1].status.is_broken());
+    }
+
+    #[test]
+    fn cache_round_trips() {
+        let path = std::env::temp_dir().join(format!("focus-links-{}", std::process::id()));
+        let report = [
+            ("https://a.example/", Status::Ok(200)),
+            ("https://b.example/", Status::Broken(404)),
+            ("https://c.example/", Status::Failed("dns".to_string())),
+            ("https://d.example/", Status::Unknown),
+        ]
+        .map(|(url, status)| Entry {
+            url: url.to_string(),
+            status,
+            locations: Vec::new(),
+        });
+
+        save_cache(&path, &report).unwrap();
+        let results = load_cache(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(results["https://a.example/"], Status::Ok(200));
+        assert_eq!(results["https://b.example/"], Status::Broken(404));
+        assert!(matches!(results["https://c.example/"], Status::Failed(_)));
+        assert!(!results.contains_key("https://d.example/"));
+        assert!(load_cache(&path).unwrap().is_empty());
+    }
+}
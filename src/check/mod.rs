@@ -0,0 +1,426 @@
+use std::fmt;
+use std::path::Path;
+
+use markdown::ParseOptions;
+use markdown::mdast::Node;
+
+use crate::math;
+use crate::routes::{images, redirects};
+use crate::view::{about, authors, shortcodes};
+
+mod external;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a post, pointing at `file:line` like a compiler would.
+pub struct Diagnostic {
+    pub file: String,
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(file: impl Into<String>, line: Option<usize>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            file: file.into(),
+            line,
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(
+        file: impl Into<String>,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(file, line, message)
+        }
+    }
+
+    pub fn log(&self) {
+        match self.severity {
+            Severity::Error => log::error!("{self}"),
+            Severity::Warning => log::warn!("{self}"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {severity}: {}", self.file, self.message),
+            None => write!(f, "{}: {severity}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// A link or image destination and the 1-based line it appears on.
+pub struct Link {
+    pub url: String,
+    pub line: usize,
+    pub image: bool,
+}
+
+pub fn links(content: &str) -> Vec<Link> {
+    fn walk(node: &Node, links: &mut Vec<Link>) {
+        let line = node.position().map_or(1, |position| position.start.line);
+        match node {
+            Node::Link(link) => links.push(Link {
+                url: link.url.clone(),
+                line,
+                image: false,
+            }),
+            Node::Definition(definition) => links.push(Link {
+                url: definition.url.clone(),
+                line,
+                image: false,
+            }),
+            Node::Image(image) => links.push(Link {
+                url: image.url.clone(),
+                line,
+                image: true,
+            }),
+            _ => {}
+        }
+        for child in node.children().into_iter().flatten() {
+            walk(child, links);
+        }
+    }
+
+    let mut links = Vec::new();
+    if let Ok(root) = markdown::to_mdast(content, &ParseOptions::gfm()) {
+        walk(&root, &mut links);
+    }
+    links
+}
+
+/// Path part of a site-relative URL without query or fragment, percent-decoded.
+pub fn local_path(url: &str) -> Option<String> {
+    if !url.starts_with('/') || url.starts_with("//") {
+        return None;
+    }
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    Some(
+        urlencoding::decode(path)
+            .map(|path| path.into_owned())
+            .unwrap_or_else(|_| path.to_string()),
+    )
+}
+
+/// Checks internal `/post/...` links, `/assets/...` images and the files
+/// a post refers to relative to its bundle directory, if it has one.
+pub fn check_links(
+    file: &str,
+    first_line: usize,
+    content: &str,
+    bundle: Option<&str>,
+    post_exists: impl Fn(&str) -> bool,
+) -> Vec<Diagnostic> {
+    let assets = Path::new("assets");
+    let mut diagnostics = Vec::new();
+
+    for link in links(content) {
+        let line = Some(first_line + link.line - 1);
+        let Some(path) = local_path(&link.url) else {
+            let relative =
+                link.url.split(['?', '#']).next().filter(|path| {
+                    !path.is_empty() && !path.starts_with("//") && !path.contains(':')
+                });
+            let missing = match (relative, bundle) {
+                (Some(path), Some(dir)) => {
+                    let path = urlencoding::decode(path).map_or(path.into(), |path| path);
+                    about::post_file(&format!("{dir}/{}", path.trim_start_matches("./")))
+                        .is_none()
+                        .then(|| format!("{} does not exist in posts/{dir}/", link.url))
+                }
+                (Some(_), None) if link.image => images::source(&link.url)
+                    .is_some_and(|source| images::load(&source).is_none())
+                    .then(|| format!("image {} does not exist in posts/", link.url)),
+                _ => None,
+            };
+            if let Some(message) = missing {
+                diagnostics.push(Diagnostic::error(file, line, message));
+            }
+            continue;
+        };
+
+        if let Some((slug, bundle_file)) = path
+            .strip_prefix("/post/")
+            .and_then(|rest| rest.split_once('/'))
+            .filter(|(_, bundle_file)| !bundle_file.is_empty())
+        {
+            let found = about::bundle_source(slug, bundle_file)
+                .is_some_and(|source| images::load(&source).is_some());
+            if !found {
+                diagnostics.push(Diagnostic::error(
+                    file,
+                    line,
+                    format!(
+                        "broken link to {}, no such file in the bundle of `{slug}`",
+                        link.url
+                    ),
+                ));
+            }
+        } else if let Some(slug) = path.strip_prefix("/post/") {
+            let slug = slug.trim_end_matches('/');
+            if !post_exists(slug) && redirects::lookup(&path).is_none() {
+                diagnostics.push(Diagnostic::error(
+                    file,
+                    line,
+                    format!("broken link to {}, no post has the slug `{slug}`", link.url),
+                ));
+            }
+        } else if let Some(asset) = path.strip_prefix("/assets/") {
+            if assets.is_dir() && !assets.join(asset).is_file() {
+                diagnostics.push(Diagnostic::error(
+                    file,
+                    line,
+                    format!("{} does not exist in assets/", link.url),
+                ));
+            }
+        } else if link.image {
+            diagnostics.push(Diagnostic::error(
+                file,
+                line,
+                format!("image {} is not served, put it in assets/", link.url),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Warns about LaTeX commands that render as errors in `$...$` and `$$...$$`.
+pub fn check_math(file: &str, first_line: usize, content: &str) -> Vec<Diagnostic> {
+    fn walk(node: &Node, formulas: &mut Vec<(String, usize)>) {
+        let line = node.position().map_or(1, |position| position.start.line);
+        match node {
+            Node::Math(math) => formulas.push((math.value.clone(), line)),
+            Node::InlineMath(math) => formulas.push((math.value.clone(), line)),
+            _ => {}
+        }
+        for child in node.children().into_iter().flatten() {
+            walk(child, formulas);
+        }
+    }
+
+    let options = ParseOptions {
+        constructs: math::constructs(),
+        ..ParseOptions::gfm()
+    };
+    let mut formulas = Vec::new();
+    if let Ok(root) = markdown::to_mdast(content, &options) {
+        walk(&root, &mut formulas);
+    }
+
+    formulas
+        .into_iter()
+        .flat_map(|(formula, line)| {
+            math::unsupported(&formula).into_iter().map(move |command| {
+                Diagnostic::warning(
+                    file,
+                    Some(first_line + line - 1),
+                    format!("unsupported math command {command}, it is shown as an error"),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Reports unknown shortcodes and shortcodes with bad arguments, which
+/// render as their source text.
+pub fn check_shortcodes(file: &str, first_line: usize, content: &str) -> Vec<Diagnostic> {
+    /// Blanks code and raw HTML, where shortcodes are shown rather than expanded.
+    fn blank(node: &Node, text: &mut [u8], offset: usize) {
+        if matches!(
+            node,
+            Node::InlineCode(_) | Node::InlineMath(_) | Node::Html(_)
+        ) && let Some(position) = node.position()
+        {
+            for byte in &mut text[position.start.offset - offset..position.end.offset - offset] {
+                if *byte != b'\n' {
+                    *byte = b' ';
+                }
+            }
+        }
+        for child in node.children().into_iter().flatten() {
+            blank(child, text, offset);
+        }
+    }
+
+    fn walk(node: &Node, content: &str, found: &mut Vec<(String, usize)>) {
+        if let (Node::Paragraph(_), Some(position)) = (node, node.position()) {
+            let mut text = content.as_bytes()[position.start.offset..position.end.offset].to_vec();
+            blank(node, &mut text, position.start.offset);
+            let text = String::from_utf8_lossy(&text);
+            for (range, tag) in shortcodes::tags(&text).filter(|(_, tag)| !tag.closing) {
+                if let Err(err) = shortcodes::expand(&tag.name, &tag.args, None) {
+                    let line = position.start.line + text[..range.start].matches('\n').count();
+                    found.push((err, line));
+                }
+            }
+            return;
+        }
+        for child in node.children().into_iter().flatten() {
+            walk(child, content, found);
+        }
+    }
+
+    let options = ParseOptions {
+        constructs: math::constructs(),
+        ..ParseOptions::gfm()
+    };
+    let mut found = Vec::new();
+    if let Ok(root) = markdown::to_mdast(content, &options) {
+        walk(&root, content, &mut found);
+    }
+
+    found
+        .into_iter()
+        .map(|(message, line)| Diagnostic::error(file, Some(first_line + line - 1), message))
+        .collect()
+}
+
+/// Validates everything the server would refuse or silently drop at startup.
+pub fn diagnostics() -> Vec<Diagnostic> {
+    let mut diagnostics = about::check_posts();
+    diagnostics.extend(authors::check());
+
+    if !Path::new("assets").is_dir() {
+        diagnostics.push(Diagnostic::warning(
+            "assets",
+            None,
+            "directory not found, skipping image checks",
+        ));
+    }
+    if let Err(err) = redirects::validate() {
+        diagnostics.push(Diagnostic::error("redirects", None, err));
+    }
+
+    diagnostics
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// `focus check [--links [--offline] [--cache <file>]]`: prints all
+/// diagnostics, optionally reports external links, and exits non-zero on
+/// errors or broken links.
+pub fn run(args: &[String]) -> std::io::Result<()> {
+    let diagnostics = diagnostics();
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    println!(
+        "Checked {} post(s): {errors} error(s), {warnings} warning(s)",
+        about::post_count()
+    );
+
+    let broken_links = if args.iter().any(|arg| arg == "--links") {
+        external::run(args)?
+    } else {
+        false
+    };
+
+    if errors > 0 || broken_links {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(diagnostics: &[Diagnostic]) -> Vec<Option<usize>> {
+        diagnostics.iter().map(|d| d.line).collect()
+    }
+
+    #[test]
+    fn links_to_missing_posts_are_errors() {
+        let content = "[ok](/post/hello)\n\n[gone](/post/goodbye#top)\n";
+        let diagnostics = check_links("post.md", 4, content, None, |slug| slug == "hello");
+        assert_eq!(lines(&diagnostics), [Some(6)]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "post.md:6: error: broken link to /post/goodbye#top, no post has the slug `goodbye`"
+        );
+    }
+
+    #[test]
+    fn images_must_be_served_from_assets() {
+        let content = "![a](/assets/taste.svg)\n![b](/assets/missing.png)\n![c](/elsewhere.png)\n![d](https://example.com/x.png)\n";
+        let diagnostics = check_links("post.md", 1, content, None, |_| true);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "/assets/missing.png does not exist in assets/",
+                "image /elsewhere.png is not served, put it in assets/",
+            ]
+        );
+        assert_eq!(lines(&diagnostics), [Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn external_and_relative_links_are_left_alone() {
+        let content = "[a](https://example.com/post/x) [b](other.md) [c](#section) [d](mailto:me@example.com)";
+        assert!(check_links("post.md", 1, content, None, |_| false).is_empty());
+    }
+
+    #[test]
+    fn unsupported_math_is_a_warning() {
+        let content = "Fine: $x^2$\n\n$$\n\\nosuchcommand x\n$$\n";
+        let diagnostics = check_math("post.md", 10, content);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].severity == Severity::Warning);
+        assert_eq!(diagnostics[0].line, Some(12));
+        assert!(diagnostics[0].message.contains("\\nosuchcommand"));
+        assert!(!has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn bad_shortcodes_are_errors_outside_code() {
+        let content = "{{< youtube id=\"abc\" >}}\n\n{{< nosuch >}}\n\n`{{< nosuch >}}`\n\n```\n{{< nosuch >}}\n```\n\n{{< youtube id=\"a b\" >}}\n";
+        let diagnostics = check_shortcodes("post.md", 1, content);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown shortcode `nosuch`",
+                "shortcode `youtube`: invalid video id `a b`",
+            ]
+        );
+        assert_eq!(lines(&diagnostics), [Some(3), Some(11)]);
+        assert!(has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn only_errors_fail_the_check() {
+        assert!(!has_errors(&[]));
+        assert!(!has_errors(&[Diagnostic::warning("a", None, "w")]));
+        assert!(has_errors(&[
+            Diagnostic::warning("a", None, "w"),
+            Diagnostic::error("b", Some(1), "e"),
+        ]));
+    }
+}
@@ -1,9 +1,168 @@
+use jiff::tz::TimeZone;
 use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static CONFIG: OnceLock<Server> = OnceLock::new();
 
 #[derive(Clone)]
 pub struct Server {
     port: u16,
     host: String,
+    workers: Option<usize>,
+    keep_alive: u64,
+    backlog: u32,
+    shutdown_timeout: u64,
+    security: SecurityHeaders,
+    sanitize: Sanitize,
+    images: Images,
+    home: Home,
+    page_size: usize,
+    related_posts: usize,
+    languages: Vec<String>,
+    timezone: TimeZone,
+    data_dir: PathBuf,
+    site_url: Option<String>,
+    trusted_proxies: Vec<Network>,
+    webmentions: bool,
+    analytics: bool,
+    reactions: bool,
+    comments: Comments,
+    newsletter: Newsletter,
+    admin: Admin,
+}
+
+/// What the home page shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Home {
+    /// The newest post in full.
+    Latest,
+    /// Paginated cards of all posts.
+    List,
+}
+
+/// Addresses from `address/prefix`, or a single address.
+#[derive(Clone, Copy)]
+pub struct Network {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    pub fn parse(value: &str) -> Option<Network> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address.parse().ok()?, Some(prefix.parse().ok()?)),
+            None => (value.parse().ok()?, None),
+        };
+        let bits = if matches!(address, IpAddr::V4(_)) {
+            32
+        } else {
+            128
+        };
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(Network { address, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            v4 => v4,
+        };
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Header values sent with every response. `None` means the header is omitted,
+/// which is what an empty environment variable asks for.
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    pub enabled: bool,
+    /// `{nonce}` is replaced with the per-request script nonce.
+    pub content_security_policy: Option<String>,
+    pub frame_ancestors: Option<String>,
+    pub content_type_options: Option<String>,
+    pub referrer_policy: Option<String>,
+    pub permissions_policy: Option<String>,
+}
+
+/// Policy applied to rendered post HTML when posts come from more than one author.
+#[derive(Clone)]
+pub struct Sanitize {
+    pub enabled: bool,
+    /// Tags allowed on top of the built-in allow-list.
+    pub tags: Vec<String>,
+    /// `attr` for every tag or `tag:attr` for a single one.
+    pub attributes: Vec<String>,
+    /// Hosts iframes may point at; other iframes are rewritten to plain links.
+    pub iframe_hosts: Vec<String>,
+}
+
+/// Responsive variants generated for post images.
+#[derive(Clone)]
+pub struct Images {
+    /// Widths in pixels offered in `srcset`, never wider than the source.
+    pub widths: Vec<u32>,
+    /// `sizes` attribute telling the browser how wide images are displayed.
+    pub sizes: String,
+}
+
+/// Reader comments under posts, held for moderation before they show.
+#[derive(Clone)]
+pub struct Comments {
+    pub enabled: bool,
+    /// Comments one address may submit per hour.
+    pub rate_limit: usize,
+    /// Longest comment body accepted, in characters.
+    pub max_length: usize,
+}
+
+/// Email updates for readers who subscribed and confirmed their address.
+#[derive(Clone)]
+pub struct Newsletter {
+    pub enabled: bool,
+    /// Sender, a bare address or `Name <address>`.
+    pub from: Option<String>,
+    pub smtp_host: Option<String>,
+    /// Defaults to the usual port of `smtp_security`.
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_security: SmtpSecurity,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection, usually on port 587.
+    StartTls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// No encryption, for a relay on the same machine.
+    None,
+}
+
+/// The `/admin` area. Without a password it does not exist.
+#[derive(Clone)]
+pub struct Admin {
+    pub password: Option<String>,
+    /// At least 64 bytes signing and encrypting the session cookie. Without
+    /// one a key is generated at startup and logins end with a restart.
+    pub session_key: Option<String>,
 }
 
 impl Server {
@@ -14,15 +173,403 @@ impl Server {
     pub fn host(&self) -> String {
         self.host.clone()
     }
+
+    /// Number of worker threads, `None` lets actix use one per physical core.
+    pub fn workers(&self) -> Option<usize> {
+        self.workers
+    }
+
+    pub fn keep_alive(&self) -> Duration {
+        Duration::from_secs(self.keep_alive)
+    }
+
+    pub fn backlog(&self) -> u32 {
+        self.backlog
+    }
+
+    /// Seconds in-flight requests get to finish after a stop signal.
+    pub fn shutdown_timeout(&self) -> u64 {
+        self.shutdown_timeout
+    }
+
+    pub fn security(&self) -> &SecurityHeaders {
+        &self.security
+    }
+
+    pub fn sanitize(&self) -> &Sanitize {
+        &self.sanitize
+    }
+
+    pub fn images(&self) -> &Images {
+        &self.images
+    }
+
+    pub fn home(&self) -> Home {
+        self.home
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Related posts shown under a post, 0 hides the section.
+    pub fn related_posts(&self) -> usize {
+        self.related_posts
+    }
+
+    /// Content languages, the first is the default posts are written in.
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+
+    /// IANA time zone dates are shown in and dates without an offset are read in.
+    pub fn timezone(&self) -> &TimeZone {
+        &self.timezone
+    }
+
+    /// Directory the server writes to, such as the comment store.
+    pub fn data_dir(&self) -> &PathBuf {
+        &self.data_dir
+    }
+
+    /// Public address of the site, for absolute URLs in feeds, the sitemap,
+    /// hreflang links, emails and webmentions. These never come from the
+    /// `Host` header of a request.
+    pub fn site_url(&self) -> Option<&str> {
+        self.site_url.as_deref()
+    }
+
+    /// Whether `/webmention` accepts mentions and posts show them.
+    pub fn webmentions(&self) -> bool {
+        self.webmentions
+    }
+
+    /// Proxies whose `X-Forwarded-For` tells the client's address. Without
+    /// any, the address of the connection is the client.
+    pub fn trusted_proxies(&self) -> &[Network] {
+        &self.trusted_proxies
+    }
+
+    /// Whether post views are counted for the admin area.
+    pub fn analytics(&self) -> bool {
+        self.analytics
+    }
+
+    /// Whether readers can react to posts with an emoji.
+    pub fn reactions(&self) -> bool {
+        self.reactions
+    }
+
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+
+    pub fn newsletter(&self) -> &Newsletter {
+        &self.newsletter
+    }
+
+    pub fn admin(&self) -> &Admin {
+        &self.admin
+    }
+}
+
+/// Makes `server` the configuration `get` returns. The server calls this
+/// once at startup, before anything reads it.
+pub fn init(server: Server) -> &'static Server {
+    if CONFIG.set(server).is_err() {
+        panic!("could not set the configuration, it is already in use");
+    }
+    get()
+}
+
+/// The configuration of this process. Without `init`, such as in commands
+/// and tests, it is read from the environment the first time it is needed.
+pub fn get() -> &'static Server {
+    CONFIG.get_or_init(from_env)
+}
+
+const DEFAULT_CSP: &str = "default-src 'self'; \
+    script-src 'self' 'nonce-{nonce}'; \
+    style-src 'self' 'unsafe-inline'; \
+    img-src 'self' data: https:; \
+    font-src 'self' data:; \
+    connect-src 'self'; \
+    object-src 'none'; \
+    base-uri 'self'; \
+    form-action 'self'";
+
+fn list_from_env(
+    var: &impl Fn(&str) -> Result<String, env::VarError>,
+    key: &str,
+    default: &str,
+) -> Vec<String> {
+    var(key)
+        .unwrap_or(default.to_string())
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
 }
 
+fn header_from_env(
+    var: &impl Fn(&str) -> Result<String, env::VarError>,
+    key: &str,
+    default: &str,
+) -> Option<String> {
+    let value = var(key).unwrap_or(default.to_string());
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Reads the configuration from the `g_*` environment variables. Values
+/// that do not parse panic, so a bad one stops the server before it starts.
 pub fn from_env() -> Server {
-    let port: u16 = env::var("g_port")
+    from_vars(|key| env::var(key))
+}
+
+/// Like `from_env`, with `var` looking up each variable.
+pub fn from_vars(var: impl Fn(&str) -> Result<String, env::VarError>) -> Server {
+    let port: u16 = var("g_port")
         .map(|e| e.parse().expect("could not parse port"))
         .unwrap_or(9999);
-    let host: String = env::var("g_host")
+    let host: String = var("g_host")
         .map(|e| e.parse().expect("could not parse host"))
         .unwrap_or("0.0.0.0".to_string());
+    let workers: Option<usize> = var("g_workers")
+        .map(|e| e.parse().expect("could not parse workers"))
+        .ok()
+        .filter(|&w| w > 0);
+    let keep_alive: u64 = var("g_keep_alive")
+        .map(|e| e.parse().expect("could not parse keep alive"))
+        .unwrap_or(5);
+    let backlog: u32 = var("g_backlog")
+        .map(|e| e.parse().expect("could not parse backlog"))
+        .unwrap_or(2048);
+    let shutdown_timeout: u64 = var("g_shutdown_timeout")
+        .map(|e| e.parse().expect("could not parse shutdown timeout"))
+        .unwrap_or(30);
+
+    let home = match var("g_home").as_deref() {
+        Ok("list") => Home::List,
+        Ok("latest") | Err(_) => Home::Latest,
+        Ok(other) => panic!("could not parse home: {other}, expected latest or list"),
+    };
+    let page_size: usize = var("g_page_size")
+        .map(|e| e.parse().expect("could not parse page size"))
+        .unwrap_or(10)
+        .max(1);
+    let related_posts: usize = var("g_related_posts")
+        .map(|e| e.parse().expect("could not parse related posts"))
+        .unwrap_or(3);
+    let mut languages = list_from_env(&var, "g_languages", "en");
+    if languages.is_empty() {
+        languages.push("en".to_string());
+    }
+    let timezone = var("g_timezone").unwrap_or("UTC".to_string());
+    let timezone = TimeZone::get(&timezone)
+        .unwrap_or_else(|err| panic!("could not find time zone {timezone}: {err}"));
+    let data_dir = PathBuf::from(var("g_data_dir").unwrap_or("data".to_string()));
+    let site_url =
+        header_from_env(&var, "g_site_url", "").map(|url| url.trim_end_matches('/').to_string());
+    let trusted_proxies = list_from_env(&var, "g_trusted_proxies", "")
+        .iter()
+        .map(|proxy| {
+            Network::parse(proxy)
+                .unwrap_or_else(|| panic!("could not parse trusted proxy: {proxy}"))
+        })
+        .collect();
+    let webmentions: bool = var("g_webmentions")
+        .map(|e| e.parse().expect("could not parse webmentions flag"))
+        .unwrap_or(true);
+    let analytics: bool = var("g_analytics")
+        .map(|e| e.parse().expect("could not parse analytics flag"))
+        .unwrap_or(true);
+    let reactions: bool = var("g_reactions")
+        .map(|e| e.parse().expect("could not parse reactions flag"))
+        .unwrap_or(true);
+
+    let comments = Comments {
+        enabled: var("g_comments")
+            .map(|e| e.parse().expect("could not parse comments flag"))
+            .unwrap_or(true),
+        rate_limit: var("g_comment_rate_limit")
+            .map(|e| e.parse().expect("could not parse comment rate limit"))
+            .unwrap_or(5),
+        max_length: var("g_comment_max_length")
+            .map(|e| e.parse().expect("could not parse comment max length"))
+            .unwrap_or(5000),
+    };
+
+    let newsletter = Newsletter {
+        enabled: var("g_newsletter")
+            .map(|e| e.parse().expect("could not parse newsletter flag"))
+            .unwrap_or(false),
+        from: header_from_env(&var, "g_newsletter_from", ""),
+        smtp_host: header_from_env(&var, "g_smtp_host", ""),
+        smtp_port: var("g_smtp_port")
+            .map(|e| e.parse().expect("could not parse smtp port"))
+            .ok(),
+        smtp_username: header_from_env(&var, "g_smtp_username", ""),
+        smtp_password: header_from_env(&var, "g_smtp_password", ""),
+        smtp_security: match var("g_smtp_security").as_deref() {
+            Ok("starttls") | Err(_) => SmtpSecurity::StartTls,
+            Ok("tls") => SmtpSecurity::Tls,
+            Ok("none") => SmtpSecurity::None,
+            Ok(other) => {
+                panic!("could not parse smtp security: {other}, expected starttls, tls or none")
+            }
+        },
+    };
+    // Links in the emails need the public address of the blog
+    if newsletter.enabled
+        && (newsletter.from.is_none() || newsletter.smtp_host.is_none() || site_url.is_none())
+    {
+        panic!(
+            "could not enable the newsletter, it needs g_newsletter_from, g_smtp_host and g_site_url"
+        );
+    }
+
+    let admin = Admin {
+        password: header_from_env(&var, "g_admin_password", ""),
+        session_key: header_from_env(&var, "g_session_key", ""),
+    };
+    if admin.session_key.as_ref().is_some_and(|key| key.len() < 64) {
+        panic!("could not use session key, it needs at least 64 bytes");
+    }
+
+    let sanitize = Sanitize {
+        enabled: var("g_sanitize")
+            .map(|e| e.parse().expect("could not parse sanitize flag"))
+            .unwrap_or(false),
+        tags: list_from_env(&var, "g_sanitize_tags", ""),
+        attributes: list_from_env(&var, "g_sanitize_attributes", ""),
+        iframe_hosts: list_from_env(
+            &var,
+            "g_iframe_hosts",
+            "www.youtube-nocookie.com,player.vimeo.com",
+        ),
+    };
+
+    let mut widths: Vec<u32> = list_from_env(&var, "g_image_widths", "320,640,960,1280")
+        .iter()
+        .map(|w| w.parse().expect("could not parse image widths"))
+        .filter(|&w| w > 0)
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+    let images = Images {
+        widths,
+        sizes: var("g_image_sizes").unwrap_or("(min-width: 65ch) 65ch, 100vw".to_string()),
+    };
+
+    let frame_src = sanitize
+        .iframe_hosts
+        .iter()
+        .map(|host| format!(" https://{host}"))
+        .collect::<String>();
+    let default_csp = format!("{DEFAULT_CSP}; frame-src 'self'{frame_src}");
 
-    Server { port, host }
+    let security = SecurityHeaders {
+        enabled: var("g_security_headers")
+            .map(|e| e.parse().expect("could not parse security headers flag"))
+            .unwrap_or(true),
+        content_security_policy: header_from_env(&var, "g_csp", &default_csp),
+        frame_ancestors: header_from_env(&var, "g_frame_ancestors", "'none'"),
+        content_type_options: header_from_env(&var, "g_content_type_options", "nosniff"),
+        referrer_policy: header_from_env(
+            &var,
+            "g_referrer_policy",
+            "strict-origin-when-cross-origin",
+        ),
+        permissions_policy: header_from_env(
+            &var,
+            "g_permissions_policy",
+            "camera=(), microphone=(), geolocation=(), payment=(), usb=()",
+        ),
+    };
+
+    Server {
+        port,
+        host,
+        workers,
+        keep_alive,
+        backlog,
+        shutdown_timeout,
+        security,
+        sanitize,
+        images,
+        home,
+        page_size,
+        related_posts,
+        languages,
+        timezone,
+        data_dir,
+        site_url,
+        trusted_proxies,
+        webmentions,
+        analytics,
+        reactions,
+        comments,
+        newsletter,
+        admin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(vars: &[(&str, &str)]) -> Server {
+        from_vars(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+                .ok_or(env::VarError::NotPresent)
+        })
+    }
+
+    #[test]
+    fn defaults_without_variables() {
+        let server = with(&[]);
+        assert_eq!(server.port(), 9999);
+        assert_eq!(server.languages(), ["en"]);
+        assert_eq!(server.timezone(), &TimeZone::UTC);
+        assert_eq!(server.site_url(), None);
+        assert!(!server.newsletter().enabled);
+        assert_eq!(server.images().widths, [320, 640, 960, 1280]);
+    }
+
+    #[test]
+    fn reads_lists_and_urls() {
+        let server = with(&[
+            ("g_languages", "de, en,"),
+            ("g_site_url", "https://example.com/"),
+            ("g_image_widths", "640,320,640"),
+        ]);
+        assert_eq!(server.languages(), ["de", "en"]);
+        assert_eq!(server.site_url(), Some("https://example.com"));
+        assert_eq!(server.images().widths, [320, 640]);
+    }
+
+    #[test]
+    #[should_panic(expected = "could not parse port")]
+    fn bad_number_panics() {
+        with(&[("g_port", "http")]);
+    }
+
+    #[test]
+    #[should_panic(expected = "could not find time zone Mars/Olympus")]
+    fn unknown_time_zone_panics() {
+        with(&[("g_timezone", "Mars/Olympus")]);
+    }
+
+    #[test]
+    #[should_panic(expected = "could not enable the newsletter")]
+    fn newsletter_needs_site_url() {
+        with(&[
+            ("g_newsletter", "true"),
+            ("g_newsletter_from", "blog@example.com"),
+            ("g_smtp_host", "localhost"),
+        ]);
+    }
 }
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Timelike, Utc};
+use jiff::civil;
+use jiff::tz::TimeZone;
+
+use crate::config;
+
+/// `instant` as the site's wall-clock time, with the offset in effect then.
+pub fn local(instant: DateTime<Utc>) -> DateTime<FixedOffset> {
+    local_in(instant, config::get().timezone())
+}
+
+fn local_in(instant: DateTime<Utc>, zone: &TimeZone) -> DateTime<FixedOffset> {
+    let offset = jiff::Timestamp::from_second(instant.timestamp())
+        .map(|timestamp| zone.to_offset(timestamp).seconds())
+        .unwrap_or_default();
+    let offset = FixedOffset::east_opt(offset).unwrap_or(FixedOffset::east_opt(0).unwrap());
+    instant.with_timezone(&offset)
+}
+
+/// The instant a wall-clock time happens in the site's time zone. Times
+/// skipped or repeated by daylight saving resolve like most calendars do.
+fn from_local(datetime: NaiveDateTime) -> Option<DateTime<Utc>> {
+    from_local_in(datetime, config::get().timezone())
+}
+
+fn from_local_in(datetime: NaiveDateTime, zone: &TimeZone) -> Option<DateTime<Utc>> {
+    let civil = civil::DateTime::new(
+        i16::try_from(datetime.year()).ok()?,
+        datetime.month() as i8,
+        datetime.day() as i8,
+        datetime.hour() as i8,
+        datetime.minute() as i8,
+        datetime.second() as i8,
+        0,
+    )
+    .ok()?;
+    let timestamp = civil.to_zoned(zone.clone()).ok()?.timestamp();
+    DateTime::from_timestamp(timestamp.as_second(), 0)
+}
+
+/// Midnight of `date` in the site's time zone.
+pub fn start_of_day(date: NaiveDate) -> Option<DateTime<Utc>> {
+    from_local(date.and_hms_opt(0, 0, 0)?)
+}
+
+/// A front matter date: RFC 3339 with an offset (`2026-01-05T14:30:00+01:00`),
+/// or a date or date and time without one, taken in the site's time zone.
+pub fn parse(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.to_utc());
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    });
+
+    naive.and_then(from_local).ok_or_else(|| {
+        format!("could not parse date `{value}`, expected e.g. 2026-01-05T14:30:00+01:00")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> TimeZone {
+        TimeZone::posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap()
+    }
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().to_utc()
+    }
+
+    #[test]
+    fn local_time_follows_daylight_saving() {
+        let winter = local_in(utc("2026-01-05T12:00:00Z"), &berlin());
+        assert_eq!(winter.to_rfc3339(), "2026-01-05T13:00:00+01:00");
+        let summer = local_in(utc("2026-07-05T12:00:00Z"), &berlin());
+        assert_eq!(summer.to_rfc3339(), "2026-07-05T14:00:00+02:00");
+    }
+
+    #[test]
+    fn wall_clock_times_resolve_around_transitions() {
+        let zone = berlin();
+        assert_eq!(
+            from_local_in(at("2026-01-05 13:00"), &zone),
+            Some(utc("2026-01-05T12:00:00Z"))
+        );
+        // Skipped when clocks go forward: taken as the time after the gap
+        assert_eq!(
+            from_local_in(at("2026-03-29 02:30"), &zone),
+            Some(utc("2026-03-29T01:30:00Z"))
+        );
+        // Repeated when clocks go back: the first of the two
+        assert_eq!(
+            from_local_in(at("2026-10-25 02:30"), &zone),
+            Some(utc("2026-10-25T00:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn dates_with_an_offset_ignore_the_time_zone() {
+        assert_eq!(
+            parse("2026-01-05T14:30:00+01:00"),
+            Ok(utc("2026-01-05T13:30:00Z"))
+        );
+        assert!(parse("5 January").is_err());
+    }
+}
@@ -1,22 +1,46 @@
-use actix_web::{App, HttpServer, middleware::Logger, web};
+use actix_web::dev::Service;
+use actix_web::{App, HttpServer, middleware::Logger, middleware::from_fn, web};
 use chrono::Utc;
 use env_logger::Env;
 use std::env;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
+use std::time::Duration;
 
+mod check;
 mod config;
+mod dates;
+mod html;
+mod math;
+mod newsletter;
+mod rate_limit;
 mod routes;
+mod sanitize;
+mod security;
+mod shutdown;
+mod slug;
+mod store;
 mod view;
+mod webmention;
+
+const ANALYTICS_FLUSH: Duration = Duration::from_secs(60);
 
 fn main() -> std::io::Result<()> {
     // Check for CLI commands
     let args: Vec<String> = env::args().collect();
 
-    // If there are arguments, create a post
+    // `focus check` validates posts, `focus webmention send` notifies linked
+    // pages, `focus newsletter` mails new posts, any other arguments create a
+    // post. `--` creates a post whose title starts with a subcommand name.
     if args.len() > 1 {
-        return create_post(&args);
+        return match args[1].as_str() {
+            "check" => check::run(&args[2..]),
+            "webmention" => webmention::run(&args[2..]),
+            "newsletter" => newsletter::run(&args[2..]),
+            "--" => create_post(&args[1..]),
+            _ => create_post(&args),
+        };
     }
 
     // Otherwise start the web server
@@ -27,35 +51,136 @@ fn main() -> std::io::Result<()> {
 async fn start_server() -> std::io::Result<()> {
     env_logger::init_from_env(Env::default().default_filter_or("debug"));
 
-    let c = config::from_env();
+    let c = config::init(config::from_env());
     let host = c.host();
     let port = c.port();
 
     let url = format!("http://{host}:{port}");
 
+    let diagnostics = check::diagnostics();
+    for diagnostic in &diagnostics {
+        diagnostic.log();
+    }
+    if check::has_errors(&diagnostics) {
+        return Err(std::io::Error::other(
+            "posts failed validation, run `focus check` for details",
+        ));
+    }
+
+    sanitize::prepare();
+    view::about::prepare();
+    view::comments::prepare();
+    view::webmentions::prepare();
+    view::analytics::prepare();
+    view::newsletter::prepare();
+    view::reactions::prepare();
+    if c.site_url().is_none() {
+        log::warn!(
+            "g_site_url is not set, author feeds, the sitemap and receiving webmentions are off"
+        );
+    }
+
     log::info!("Server started at {url}");
 
-    let server = HttpServer::new(move || {
+    let stats = shutdown::RequestStats::default();
+    let shutdown_timeout = c.shutdown_timeout();
+    let workers = c.workers();
+    let keep_alive = c.keep_alive();
+    let backlog = c.backlog();
+
+    let session_key = view::admin::session_key(c);
+    let tracked = stats.clone();
+    let mut server = HttpServer::new(move || {
+        let tracked = tracked.clone();
         App::new()
+            .wrap(view::errors::handlers())
+            .wrap(from_fn(security::headers))
+            .wrap(view::admin::sessions(session_key.clone()))
             .wrap(Logger::default())
+            .wrap_fn(move |req, srv| {
+                let in_flight = tracked.track();
+                let fut = srv.call(req);
+                async move {
+                    let res = fut.await;
+                    in_flight.complete();
+                    res
+                }
+            })
             .app_data(web::Data::new(c.clone()))
             .service(view::index_route)
             .service(view::about_readme_endpoint)
             .service(view::about::post_route)
             .service(view::about::post_slug_route)
+            .service(view::about::post_file_route)
+            .service(view::about::translated_post_route)
+            .service(view::about::series_route)
+            .service(view::about::translated_series_route)
+            .service(view::about::authors_route)
+            .service(view::about::author_route)
+            .service(view::about::author_feed_route)
+            .service(view::sitemap::sitemap_route)
+            .service(view::comments::comments_route)
+            .service(view::comments::submit_comment_route)
+            .service(view::admin::admin_route)
+            .service(view::admin::login_route)
+            .service(view::admin::logout_route)
+            .service(view::comments::moderate_route)
+            .service(view::webmentions::webmentions_route)
+            .service(view::webmentions::receive_route)
+            .service(view::webmentions::remove_route)
+            .service(view::analytics::analytics_route)
+            .service(view::newsletter::subscribe_route)
+            .service(view::newsletter::confirm_route)
+            .service(view::newsletter::unsubscribe_page_route)
+            .service(view::newsletter::unsubscribe_route)
+            .service(view::reactions::reactions_route)
+            .service(view::reactions::react_route)
             .service(routes::assets::scope())
+            .service(routes::images::scope())
+            .default_service(web::to(routes::redirects::fallback))
+    })
+    .keep_alive(keep_alive)
+    .backlog(backlog)
+    .shutdown_timeout(shutdown_timeout)
+    .shutdown_signal(shutdown::signal(
+        stats.clone(),
+        Duration::from_secs(shutdown_timeout),
+    ));
+
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+
+    // Page views are counted in memory and written out now and then
+    actix_web::rt::spawn(async {
+        let mut interval = actix_web::rt::time::interval(ANALYTICS_FLUSH);
+        loop {
+            interval.tick().await;
+            let _ = web::block(view::analytics::flush).await;
+        }
     });
+
     server
         .bind((host, port))
         .expect("Could not bind server address")
         .run()
-        .await
+        .await?;
+
+    view::analytics::flush();
+    stats.log_summary();
+    Ok(())
 }
 
 fn create_post(args: &[String]) -> std::io::Result<()> {
     if args.len() < 2 {
-        eprintln!("Usage: focus <title>");
+        eprintln!(
+            "Usage: focus [--] <title>\n       \
+             focus check [--links [--offline] [--cache <file>]]\n       \
+             focus webmention send <slug>\n       \
+             focus newsletter build|send"
+        );
         eprintln!("Example: focus \"My First Post\"");
+        eprintln!("Titles starting with check, webmention or newsletter need the --");
         std::process::exit(1);
     }
 
@@ -63,21 +188,12 @@ fn create_post(args: &[String]) -> std::io::Result<()> {
     let date = Utc::now();
     let date_str = date.format("%Y-%m-%d").to_string();
 
-    // Create slug from title (lowercase, replace spaces with hyphens)
-    let slug = title
-        .to_lowercase()
-        .chars()
-        .map(|c| {
-            if c.is_alphanumeric() {
-                c
-            } else if c.is_whitespace() {
-                '-'
-            } else {
-                '\0'
-            }
-        })
-        .filter(|&c| c != '\0')
-        .collect::<String>();
+    // Same slug the server derives from the filename
+    let slug = slug::slugify(&title);
+    if slug.is_empty() {
+        eprintln!("Error: title needs at least one letter or digit");
+        std::process::exit(1);
+    }
 
     let filename = format!("{date_str}-{slug}.md");
     let filepath = Path::new("posts").join(&filename);
@@ -0,0 +1,976 @@
+use lazy_static::lazy_static;
+use markdown::Constructs;
+use regex::{Captures, Regex};
+
+use crate::html::escape;
+
+/// Deepest nesting of groups and arguments. Formulas nested deeper are cut
+/// off there, so input like `{{{{…` cannot overflow the stack.
+const MAX_NESTING: usize = 64;
+
+lazy_static! {
+    /// Math as compiled by the markdown crate: `$$...$$` and ```` ```math ````
+    /// blocks in a `<pre>`, `$...$` inline.
+    static ref MATH: Regex = Regex::new(
+        r#"(?s)<pre><code class="language-math(?: math-display)?">(.*?)</code></pre>|<code class="language-math math-inline">(.*?)</code>"#
+    )
+    .unwrap();
+}
+
+/// GFM plus `$...$` and `$$...$$`.
+pub fn constructs() -> Constructs {
+    Constructs {
+        math_flow: true,
+        math_text: true,
+        ..Constructs::gfm()
+    }
+}
+
+/// Replaces the math code elements of rendered post HTML with MathML.
+pub fn rewrite(html: &str) -> String {
+    MATH.replace_all(html, |caps: &Captures| match caps.get(1) {
+        Some(display) => to_mathml(&unescape(display.as_str()), true),
+        None => to_mathml(&unescape(&caps[2]), false),
+    })
+    .into_owned()
+}
+
+/// Converts a LaTeX formula to a `<math>` element. Unsupported commands are
+/// shown as `<merror>` in place and the source is kept as an annotation.
+pub fn to_mathml(tex: &str, display: bool) -> String {
+    let mut parser = Parser::new(tex, display);
+    let body = row(parser.formula());
+    let display = if display { " display=\"block\"" } else { "" };
+    format!(
+        "<math{display}><semantics>{body}<annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        escape(tex.trim())
+    )
+}
+
+/// Commands in `tex` that render as errors, for `focus check`.
+pub fn unsupported(tex: &str) -> Vec<String> {
+    let mut parser = Parser::new(tex, false);
+    parser.formula();
+    parser.unsupported
+}
+
+fn unescape(html: &str) -> String {
+    html.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn row(items: Vec<String>) -> String {
+    match <[String; 1]>::try_from(items) {
+        Ok([item]) => item,
+        Err(items) => format!("<mrow>{}</mrow>", items.concat()),
+    }
+}
+
+fn mi(text: &str) -> String {
+    format!("<mi>{}</mi>", escape(text))
+}
+
+fn mi_normal(text: &str) -> String {
+    format!("<mi mathvariant=\"normal\">{}</mi>", escape(text))
+}
+
+fn mo(text: &str) -> String {
+    format!("<mo>{}</mo>", escape(text))
+}
+
+fn fence(text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", escape(text))
+    }
+}
+
+fn mspace(width: &str) -> String {
+    format!("<mspace width=\"{width}\"/>")
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Command(String),
+    Char(char),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Align,
+    NewRow,
+    /// Only significant inside `\text{...}`.
+    Space,
+}
+
+fn tokenize(tex: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = tex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some('\\') => Token::NewRow,
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut name = c.to_string();
+                    while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                        name.push(c);
+                        chars.next();
+                    }
+                    Token::Command(name)
+                }
+                Some(c) => Token::Command(c.to_string()),
+                None => continue,
+            },
+            '%' => {
+                // Comment until the end of the line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Sup,
+            '_' => Token::Sub,
+            '&' => Token::Align,
+            c if c.is_whitespace() => {
+                if tokens.last() == Some(&Token::Space) {
+                    continue;
+                }
+                Token::Space
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// One element plus whether scripts go under and over it in display math.
+struct Atom {
+    markup: String,
+    limits: bool,
+}
+
+impl Atom {
+    fn new(markup: String) -> Self {
+        Atom {
+            markup,
+            limits: false,
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Open braces around the current position.
+    depth: usize,
+    /// Atoms and arguments being parsed around the current position.
+    nesting: usize,
+    display: bool,
+    unsupported: Vec<String>,
+}
+
+impl Parser {
+    fn new(tex: &str, display: bool) -> Self {
+        Parser {
+            tokens: tokenize(tex),
+            position: 0,
+            depth: 0,
+            nesting: 0,
+            display,
+            unsupported: Vec::new(),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.tokens.get(self.position) == Some(&Token::Space) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<Token> {
+        self.skip_spaces();
+        self.tokens.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn unsupported(&mut self, what: String) -> String {
+        let markup = format!("<merror><mtext>{}</mtext></merror>", escape(&what));
+        self.unsupported.push(what);
+        markup
+    }
+
+    /// Drops the rest of the input once formulas nest deeper than
+    /// `MAX_NESTING`.
+    fn too_deep(&mut self) -> String {
+        self.position = self.tokens.len();
+        self.unsupported(format!("nesting deeper than {MAX_NESTING} levels"))
+    }
+
+    /// The whole input. Tokens that only make sense inside a group or an
+    /// environment are dropped or shown as errors.
+    fn formula(&mut self) -> Vec<String> {
+        let mut items = Vec::new();
+        loop {
+            items.extend(self.expression());
+            match self.next() {
+                None => break,
+                Some(Token::Command(name)) => items.push(self.unsupported(format!("\\{name}"))),
+                Some(_) => {}
+            }
+        }
+        items
+    }
+
+    /// Elements up to the end of input or a token that closes the current
+    /// group or environment cell, which is left for the caller.
+    fn expression(&mut self) -> Vec<String> {
+        let mut items = Vec::new();
+
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Close if self.depth == 0 => {
+                    // Stray closing brace
+                    self.next();
+                }
+                Token::Close | Token::Align | Token::NewRow => break,
+                Token::Command(name) if name == "right" || name == "end" => break,
+                _ => match self.scripted() {
+                    Some(item) if !item.is_empty() => items.push(item),
+                    Some(_) => {}
+                    None => break,
+                },
+            }
+        }
+
+        items
+    }
+
+    /// Elements of a group whose `{` was just consumed, up to its `}`.
+    fn group(&mut self) -> String {
+        self.depth += 1;
+        let items = self.expression();
+        self.depth -= 1;
+        if self.peek() == Some(Token::Close) {
+            self.next();
+        }
+        row(items)
+    }
+
+    /// An atom with its subscript, superscript and primes, if any.
+    fn scripted(&mut self) -> Option<String> {
+        let base = match self.peek()? {
+            Token::Sup | Token::Sub => Atom::new("<mrow></mrow>".to_string()),
+            _ => self.atom()?,
+        };
+
+        let mut sub = None;
+        let mut sup: Option<String> = None;
+        let mut primes = String::new();
+        loop {
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.next();
+                    sub = Some(self.argument());
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.next();
+                    sup = Some(self.argument());
+                }
+                Some(Token::Char('\'')) => {
+                    self.next();
+                    primes.push('′');
+                }
+                _ => break,
+            }
+        }
+
+        if !primes.is_empty() {
+            let primes = mo(&primes);
+            sup = Some(match sup {
+                Some(sup) => row(vec![primes, sup]),
+                None => primes,
+            });
+        }
+
+        let under = base.limits && self.display;
+        Some(match (sub, sup) {
+            (None, None) => base.markup,
+            (Some(sub), None) if under => format!("<munder>{}{sub}</munder>", base.markup),
+            (None, Some(sup)) if under => format!("<mover>{}{sup}</mover>", base.markup),
+            (Some(sub), Some(sup)) if under => {
+                format!("<munderover>{}{sub}{sup}</munderover>", base.markup)
+            }
+            (Some(sub), None) => format!("<msub>{}{sub}</msub>", base.markup),
+            (None, Some(sup)) => format!("<msup>{}{sup}</msup>", base.markup),
+            (Some(sub), Some(sup)) => format!("<msubsup>{}{sub}{sup}</msubsup>", base.markup),
+        })
+    }
+
+    /// A braced group or a single atom, as taken by `^`, `_` and commands.
+    fn argument(&mut self) -> String {
+        if self.nesting >= MAX_NESTING {
+            return self.too_deep();
+        }
+        self.nesting += 1;
+        let markup = match self.peek() {
+            Some(Token::Open) => {
+                self.next();
+                self.group()
+            }
+            Some(_) => self
+                .atom()
+                .map(|atom| atom.markup)
+                .unwrap_or_else(|| "<mrow></mrow>".to_string()),
+            None => "<mrow></mrow>".to_string(),
+        };
+        self.nesting -= 1;
+        markup
+    }
+
+    /// Source text of a braced group, for `\text` and font commands.
+    /// `None`, with nothing consumed, when the group holds more than text.
+    fn raw_argument(&mut self) -> Option<String> {
+        if self.peek() != Some(Token::Open) {
+            return match self.peek()? {
+                Token::Char(c) => {
+                    self.next();
+                    Some(c.to_string())
+                }
+                _ => None,
+            };
+        }
+
+        let start = self.position;
+        self.position += 1;
+        let mut depth = 1;
+        let mut text = String::new();
+        while let Some(token) = self.tokens.get(self.position).cloned() {
+            self.position += 1;
+            match token {
+                Token::Open => depth += 1,
+                Token::Close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(text);
+                    }
+                }
+                Token::Char(c) => text.push(c),
+                Token::Space => text.push(' '),
+                Token::Command(name) if name.len() == 1 => text.push_str(&name),
+                _ => {
+                    self.position = start;
+                    return None;
+                }
+            }
+        }
+        Some(text)
+    }
+
+    fn text_argument(&mut self) -> String {
+        let text = self.raw_argument().unwrap_or_default();
+        format!("<mtext>{}</mtext>", escape(&text).replace(' ', "\u{a0}"))
+    }
+
+    /// `\sqrt[3]{x}` takes its index in brackets.
+    fn optional_argument(&mut self) -> Option<String> {
+        if self.peek() != Some(Token::Char('[')) {
+            return None;
+        }
+        self.next();
+        let mut items = Vec::new();
+        while let Some(token) = self.peek() {
+            if token == Token::Char(']') {
+                self.next();
+                break;
+            }
+            match self.scripted() {
+                Some(item) => items.push(item),
+                None => break,
+            }
+        }
+        Some(row(items))
+    }
+
+    /// The delimiter after `\left`, `\right` or `\big`; `.` means none.
+    fn delimiter(&mut self) -> String {
+        match self.next() {
+            Some(Token::Char('.')) | None => String::new(),
+            Some(Token::Char(c)) => c.to_string(),
+            Some(Token::Command(name)) => match name.as_str() {
+                "{" | "lbrace" => "{".to_string(),
+                "}" | "rbrace" => "}".to_string(),
+                "|" | "Vert" => "‖".to_string(),
+                "vert" => "|".to_string(),
+                "langle" => "⟨".to_string(),
+                "rangle" => "⟩".to_string(),
+                "lfloor" => "⌊".to_string(),
+                "rfloor" => "⌋".to_string(),
+                "lceil" => "⌈".to_string(),
+                "rceil" => "⌉".to_string(),
+                _ => {
+                    self.unsupported(format!("\\{name}"));
+                    String::new()
+                }
+            },
+            Some(_) => String::new(),
+        }
+    }
+
+    fn atom(&mut self) -> Option<Atom> {
+        if self.nesting >= MAX_NESTING {
+            return Some(Atom::new(self.too_deep()));
+        }
+        self.nesting += 1;
+        let atom = self.next_atom();
+        self.nesting -= 1;
+        atom
+    }
+
+    fn next_atom(&mut self) -> Option<Atom> {
+        let token = self.next()?;
+        let markup = match token {
+            Token::Open => self.group(),
+            Token::Char(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                // Digits only continue a number when written together
+                while let Some(Token::Char(c)) = self.tokens.get(self.position) {
+                    if !(c.is_ascii_digit() || *c == '.') {
+                        break;
+                    }
+                    number.push(*c);
+                    self.position += 1;
+                }
+                format!("<mn>{number}</mn>")
+            }
+            Token::Char(c) if c.is_alphabetic() => mi(&c.to_string()),
+            Token::Char('-') => mo("−"),
+            Token::Char('*') => mo("∗"),
+            Token::Char('~') => mspace("0.25em"),
+            Token::Char(c) => mo(&c.to_string()),
+            Token::Command(name) => return self.command(&name),
+            Token::Close
+            | Token::Sup
+            | Token::Sub
+            | Token::Align
+            | Token::NewRow
+            | Token::Space => {
+                return None;
+            }
+        };
+        Some(Atom::new(markup))
+    }
+
+    fn command(&mut self, name: &str) -> Option<Atom> {
+        if let Some(symbol) = identifier(name) {
+            return Some(Atom::new(symbol));
+        }
+        if let Some(symbol) = operator(name) {
+            return Some(Atom::new(mo(symbol)));
+        }
+        if let Some((symbol, limits)) = large_operator(name) {
+            return Some(Atom {
+                markup: format!("<mo largeop=\"true\" movablelimits=\"{limits}\">{symbol}</mo>"),
+                limits,
+            });
+        }
+        if let Some(limits) = function(name) {
+            return Some(Atom {
+                markup: if limits {
+                    format!("<mo movablelimits=\"true\">{name}</mo>")
+                } else {
+                    mi(name)
+                },
+                limits,
+            });
+        }
+
+        let markup = match name {
+            "," | "thinspace" => mspace("0.1667em"),
+            ":" | ">" | "medspace" => mspace("0.2222em"),
+            ";" | "thickspace" => mspace("0.2778em"),
+            " " => mspace("0.25em"),
+            "quad" => mspace("1em"),
+            "qquad" => mspace("2em"),
+            "!" | "displaystyle" | "textstyle" | "limits" | "nolimits" => return self.empty(),
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                format!("<mfrac>{numerator}{denominator}</mfrac>")
+            }
+            "binom" => {
+                let top = self.argument();
+                let bottom = self.argument();
+                format!(
+                    "<mrow>{}<mfrac linethickness=\"0\">{top}{bottom}</mfrac>{}</mrow>",
+                    fence("("),
+                    fence(")")
+                )
+            }
+            "sqrt" => match self.optional_argument() {
+                Some(index) => format!("<mroot>{}{index}</mroot>", self.argument()),
+                None => format!("<msqrt>{}</msqrt>", self.argument()),
+            },
+            "text" | "textrm" | "textit" | "textbf" | "mbox" => self.text_argument(),
+            "operatorname" => match self.raw_argument() {
+                Some(text) => mi(&text),
+                None => self.argument(),
+            },
+            "mathrm" | "mathbf" | "mathit" | "mathbb" | "mathcal" | "mathfrak" | "mathsf"
+            | "mathtt" | "boldsymbol" => match self.raw_argument() {
+                Some(text) if name == "mathrm" => mi_normal(&text),
+                Some(text) => mi(&styled(name, &text)),
+                None => self.argument(),
+            },
+            "hat" | "widehat" => self.accent("^"),
+            "bar" | "overline" => self.accent("‾"),
+            "vec" | "overrightarrow" => self.accent("→"),
+            "tilde" | "widetilde" => self.accent("~"),
+            "dot" => self.accent("˙"),
+            "ddot" => self.accent("¨"),
+            "overbrace" => self.accent("⏞"),
+            "underline" => format!("<munder>{}{}</munder>", self.argument(), mo("_")),
+            "underbrace" => format!("<munder>{}{}</munder>", self.argument(), mo("⏟")),
+            "left" => {
+                let open = self.delimiter();
+                let items = self.expression();
+                let close = match self.peek() {
+                    Some(Token::Command(name)) if name == "right" => {
+                        self.next();
+                        self.delimiter()
+                    }
+                    _ => String::new(),
+                };
+                format!(
+                    "<mrow>{}{}{}</mrow>",
+                    fence(&open),
+                    items.concat(),
+                    fence(&close)
+                )
+            }
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl"
+            | "biggr" | "Biggl" | "Biggr" => mo(&self.delimiter()),
+            "middle" => fence(&self.delimiter()),
+            "not" => match self.next() {
+                Some(Token::Char('=')) => mo("≠"),
+                Some(Token::Command(name)) if name == "in" => mo("∉"),
+                _ => self.unsupported("\\not".to_string()),
+            },
+            "begin" => self.environment(),
+            _ => self.unsupported(format!("\\{name}")),
+        };
+        Some(Atom::new(markup))
+    }
+
+    fn empty(&mut self) -> Option<Atom> {
+        Some(Atom::new(String::new()))
+    }
+
+    fn accent(&mut self, mark: &str) -> String {
+        let base = self.argument();
+        format!("<mover accent=\"true\">{base}{}</mover>", mo(mark))
+    }
+
+    /// `\begin{name} ... \end{name}` for matrices, cases and aligned equations.
+    fn environment(&mut self) -> String {
+        let name = self.raw_argument().unwrap_or_default();
+        if name == "array" {
+            // Column specification, alignment is left to the table
+            self.raw_argument();
+        }
+
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        loop {
+            cells.push(row(self.expression()));
+            match self.next() {
+                Some(Token::Align) => {}
+                Some(Token::NewRow) => rows.push(std::mem::take(&mut cells)),
+                Some(Token::Command(end)) if end == "end" => {
+                    self.raw_argument();
+                    break;
+                }
+                _ => break,
+            }
+        }
+        if cells.iter().any(|cell| cell != "<mrow></mrow>") {
+            rows.push(cells);
+        }
+
+        let table = |align: &str| {
+            let rows: String = rows
+                .iter()
+                .map(|cells| {
+                    let cells: String = cells
+                        .iter()
+                        .map(|cell| format!("<mtd>{cell}</mtd>"))
+                        .collect();
+                    format!("<mtr>{cells}</mtr>")
+                })
+                .collect();
+            format!("<mtable{align}>{rows}</mtable>")
+        };
+        let fenced = |open: &str, close: &str| {
+            format!("<mrow>{}{}{}</mrow>", fence(open), table(""), fence(close))
+        };
+
+        match name.trim_end_matches('*') {
+            "matrix" | "array" | "gathered" | "gather" => table(""),
+            "pmatrix" => fenced("(", ")"),
+            "bmatrix" => fenced("[", "]"),
+            "Bmatrix" => fenced("{", "}"),
+            "vmatrix" => fenced("|", "|"),
+            "Vmatrix" => fenced("‖", "‖"),
+            "cases" => format!(
+                "<mrow>{}{}</mrow>",
+                fence("{"),
+                table(" columnalign=\"left left\"")
+            ),
+            "aligned" | "align" | "split" => table(" columnalign=\"right left\""),
+            _ => {
+                let table = table("");
+                format!("{}{table}", self.unsupported(format!("\\begin{{{name}}}")))
+            }
+        }
+    }
+}
+
+fn identifier(name: &str) -> Option<String> {
+    let lower = match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "varpi" => "ϖ",
+        "rho" => "ρ",
+        "varrho" => "ϱ",
+        "sigma" => "σ",
+        "varsigma" => "ς",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "ell" => "ℓ",
+        _ => "",
+    };
+    if !lower.is_empty() {
+        return Some(mi(lower));
+    }
+
+    let upright = match name {
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "emptyset" | "varnothing" => "∅",
+        "hbar" => "ℏ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "$" => "$",
+        "%" => "%",
+        "#" => "#",
+        "_" => "_",
+        _ => return None,
+    };
+    Some(mi_normal(upright))
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "+" => "+",
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "|" | "Vert" => "‖",
+        "vert" | "mid" => "|",
+        "&" => "&",
+        "times" => "×",
+        "cdot" => "⋅",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "pm" => "±",
+        "mp" => "∓",
+        "div" => "÷",
+        "setminus" => "∖",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "ll" => "≪",
+        "gg" => "≫",
+        "neq" | "ne" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "leftrightarrow" => "↔",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "neg" | "lnot" => "¬",
+        "forall" => "∀",
+        "exists" => "∃",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "angle" => "∠",
+        "oplus" => "⊕",
+        "otimes" => "⊗",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "prime" => "′",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        _ => return None,
+    })
+}
+
+/// Large operators and whether their scripts go under and over them.
+fn large_operator(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "sum" => ("∑", true),
+        "prod" => ("∏", true),
+        "coprod" => ("∐", true),
+        "bigcup" => ("⋃", true),
+        "bigcap" => ("⋂", true),
+        "bigoplus" => ("⨁", true),
+        "bigotimes" => ("⨂", true),
+        "int" => ("∫", false),
+        "iint" => ("∬", false),
+        "iiint" => ("∭", false),
+        "oint" => ("∮", false),
+        _ => return None,
+    })
+}
+
+/// Named functions and whether their scripts go under them, like `\lim`.
+fn function(name: &str) -> Option<bool> {
+    match name {
+        "lim" | "liminf" | "limsup" | "max" | "min" | "sup" | "inf" | "det" | "gcd" | "Pr"
+        | "argmax" | "argmin" => Some(true),
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "log" | "ln" | "lg" | "exp" | "deg" | "dim" | "ker" | "arg" | "hom"
+        | "mod" => Some(false),
+        _ => None,
+    }
+}
+
+/// Maps ASCII letters and digits to the Unicode mathematical alphabet of a
+/// font command; MathML Core only knows upright and italic otherwise.
+fn styled(command: &str, text: &str) -> String {
+    text.chars()
+        .map(|c| styled_char(command, c).unwrap_or(c))
+        .collect()
+}
+
+fn styled_char(command: &str, c: char) -> Option<char> {
+    let (upper, lower, digit) = match command {
+        "mathbf" | "boldsymbol" => (0x1D400, Some(0x1D41A), Some(0x1D7CE)),
+        "mathit" => (0x1D434, Some(0x1D44E), None),
+        "mathsf" => (0x1D5A0, Some(0x1D5BA), Some(0x1D7E2)),
+        "mathtt" => (0x1D670, Some(0x1D68A), Some(0x1D7F6)),
+        "mathbb" => (0x1D538, Some(0x1D552), Some(0x1D7D8)),
+        "mathcal" => (0x1D49C, None, None),
+        "mathfrak" => (0x1D504, Some(0x1D51E), None),
+        _ => return None,
+    };
+
+    // Letters that were encoded before the mathematical alphabets
+    let legacy = match (command, c) {
+        ("mathit", 'h') => Some('ℎ'),
+        ("mathbb", 'C') => Some('ℂ'),
+        ("mathbb", 'H') => Some('ℍ'),
+        ("mathbb", 'N') => Some('ℕ'),
+        ("mathbb", 'P') => Some('ℙ'),
+        ("mathbb", 'Q') => Some('ℚ'),
+        ("mathbb", 'R') => Some('ℝ'),
+        ("mathbb", 'Z') => Some('ℤ'),
+        ("mathcal", 'B') => Some('ℬ'),
+        ("mathcal", 'E') => Some('ℰ'),
+        ("mathcal", 'F') => Some('ℱ'),
+        ("mathcal", 'H') => Some('ℋ'),
+        ("mathcal", 'I') => Some('ℐ'),
+        ("mathcal", 'L') => Some('ℒ'),
+        ("mathcal", 'M') => Some('ℳ'),
+        ("mathcal", 'R') => Some('ℛ'),
+        ("mathfrak", 'C') => Some('ℭ'),
+        ("mathfrak", 'H') => Some('ℌ'),
+        ("mathfrak", 'I') => Some('ℑ'),
+        ("mathfrak", 'R') => Some('ℜ'),
+        ("mathfrak", 'Z') => Some('ℨ'),
+        _ => None,
+    };
+    if legacy.is_some() {
+        return legacy;
+    }
+
+    let code = match c {
+        'A'..='Z' => upper + (c as u32 - 'A' as u32),
+        'a'..='z' => lower? + (c as u32 - 'a' as u32),
+        '0'..='9' => digit? + (c as u32 - '0' as u32),
+        _ => return None,
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The MathML of `tex` without the wrapping `<math>` and annotation.
+    fn body(tex: &str) -> String {
+        let math = to_mathml(tex, false);
+        let start = math.find("<semantics>").unwrap() + "<semantics>".len();
+        let end = math.find("<annotation").unwrap();
+        math[start..end].to_string()
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(body(r"\frac{1}{2}"), "<mfrac><mn>1</mn><mn>2</mn></mfrac>");
+        assert_eq!(
+            body(r"\frac a {b+c}"),
+            "<mfrac><mi>a</mi><mrow><mi>b</mi><mo>+</mo><mi>c</mi></mrow></mfrac>"
+        );
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(body("x^2"), "<msup><mi>x</mi><mn>2</mn></msup>");
+        assert_eq!(body("a_i"), "<msub><mi>a</mi><mi>i</mi></msub>");
+        assert_eq!(
+            body("x_{n}^{2}"),
+            "<msubsup><mi>x</mi><mi>n</mi><mn>2</mn></msubsup>"
+        );
+        assert_eq!(body("f'"), "<msup><mi>f</mi><mo>′</mo></msup>");
+    }
+
+    #[test]
+    fn roots() {
+        assert_eq!(body(r"\sqrt{x}"), "<msqrt><mi>x</mi></msqrt>");
+        assert_eq!(body(r"\sqrt[3]{x}"), "<mroot><mi>x</mi><mn>3</mn></mroot>");
+    }
+
+    #[test]
+    fn greek_letters() {
+        assert!(body(r"\alpha + \beta").contains("<mi>α</mi><mo>+</mo><mi>β</mi>"));
+        assert!(body(r"\Omega").contains("Ω"));
+    }
+
+    #[test]
+    fn matrices() {
+        assert_eq!(
+            body(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mtable>\
+             <mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>\
+             <mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>\
+             </mtable><mo fence=\"true\" stretchy=\"true\">)</mo></mrow>"
+        );
+    }
+
+    #[test]
+    fn display_and_escaping() {
+        let math = to_mathml("a < b", true);
+        assert!(math.starts_with("<math display=\"block\">"));
+        assert!(math.contains("<mo>&lt;</mo>"));
+        assert!(math.contains("<annotation encoding=\"application/x-tex\">a &lt; b</annotation>"));
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert_eq!(unsupported(r"\nosuchcommand x"), ["\\nosuchcommand"]);
+        assert!(body(r"\nosuchcommand").contains("<merror>"));
+        // Unbalanced braces and dangling scripts still render
+        assert_eq!(body("{x"), "<mi>x</mi>");
+        assert_eq!(body("x}"), "<mi>x</mi>");
+        assert_eq!(body("x^"), "<msup><mi>x</mi><mrow></mrow></msup>");
+        assert_eq!(body(r"\frac{1}"), "<mfrac><mn>1</mn><mrow></mrow></mfrac>");
+        assert!(
+            unsupported(r"\begin{nosuchenv} x \end{nosuchenv}")
+                .contains(&"\\begin{nosuchenv}".to_string())
+        );
+        assert!(unsupported("").is_empty());
+    }
+
+    #[test]
+    fn deep_nesting_is_cut_off() {
+        for tex in [
+            "{".repeat(100_000),
+            r"\sqrt".repeat(100_000),
+            "x^{".repeat(100_000),
+            r"\left(".repeat(100_000),
+            r"\begin{matrix}".repeat(100_000),
+        ] {
+            let errors = unsupported(&tex);
+            assert_eq!(
+                errors,
+                [format!("nesting deeper than {MAX_NESTING} levels")]
+            );
+            assert!(to_mathml(&tex, true).contains("<merror>"));
+        }
+        assert!(unsupported(&format!("{}x{}", "{".repeat(20), "}".repeat(20))).is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, Message, SmtpTransport, Transport};
+
+use crate::config::{Newsletter, SmtpSecurity};
+
+/// An email in plain text with an HTML alternative.
+#[derive(Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    /// Where one click unsubscribes, for mail clients that offer a button.
+    pub unsubscribe: Option<String>,
+}
+
+/// Delivers email. SMTP is the default; tests can stand in a local server
+/// or collect what would have gone out.
+pub trait Mailer {
+    fn send(&self, email: &Email) -> Result<(), String>;
+}
+
+/// Keeps what would have gone out instead of sending it, and refuses the
+/// addresses in `refused`.
+#[cfg(test)]
+#[derive(Default)]
+pub struct Outbox {
+    pub sent: std::sync::Mutex<Vec<Email>>,
+    pub refused: Vec<String>,
+}
+
+#[cfg(test)]
+impl Mailer for Outbox {
+    fn send(&self, email: &Email) -> Result<(), String> {
+        if self.refused.contains(&email.to) {
+            return Err(format!("{} refused", email.to));
+        }
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+/// Whether `address` looks deliverable enough to send a confirmation to.
+pub fn valid_address(address: &str) -> bool {
+    address.len() <= 254
+        && address
+            .parse::<Address>()
+            .is_ok_and(|address| address.domain().contains('.'))
+}
+
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(settings: &Newsletter) -> Result<Self, String> {
+        let from = settings
+            .from
+            .as_deref()
+            .ok_or("g_newsletter_from is not set")?;
+        let from: Mailbox = from
+            .parse()
+            .map_err(|err| format!("invalid sender {from}: {err}"))?;
+        let host = settings
+            .smtp_host
+            .as_deref()
+            .ok_or("g_smtp_host is not set")?;
+
+        let mut builder = match settings.smtp_security {
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(host),
+            SmtpSecurity::Tls => SmtpTransport::relay(host),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(host)),
+        }
+        .map_err(|err| format!("could not use {host}: {err}"))?;
+        if let Some(port) = settings.smtp_port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&settings.smtp_username, &settings.smtp_password)
+        {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), String> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|err| format!("invalid recipient {}: {err}", email.to))?;
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject);
+        if let Some(url) = &email.unsubscribe {
+            // RFC 8058: mail clients post to the URL instead of opening it
+            message = message
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                    format!("<{url}>"),
+                ))
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                    "List-Unsubscribe=One-Click".to_string(),
+                ));
+        }
+        let message = message
+            .multipart(MultiPart::alternative_plain_html(
+                email.text.clone(),
+                email.html.clone(),
+            ))
+            .map_err(|err| format!("could not build email: {err}"))?;
+
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_addresses() {
+        assert!(valid_address("reader@example.com"));
+        assert!(valid_address("first.last+news@mail.example.org"));
+        assert!(!valid_address("reader"));
+        assert!(!valid_address("reader@localhost"));
+        assert!(!valid_address("reader@exa mple.com"));
+        assert!(!valid_address(&format!("{}@example.com", "a".repeat(250))));
+    }
+}
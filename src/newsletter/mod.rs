@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Days, SubsecRound, Utc};
+use maud::html;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::dates;
+use crate::store::{Record, Store};
+use crate::view::newsletter::{self, Recipient};
+use crate::view::{about, i18n};
+
+pub mod mail;
+
+use mail::{Email, Mailer, SmtpMailer};
+
+/// How far back the first issue reaches, when no earlier send says.
+const FIRST_ISSUE_DAYS: u64 = 30;
+/// Stands in for the recipient's own unsubscribe link until it is sent.
+const UNSUBSCRIBE: &str = "{unsubscribe}";
+
+/// A digest that went out, so the next one starts where it ended.
+#[derive(Clone, Serialize, Deserialize)]
+struct Issue {
+    id: String,
+    sent: DateTime<Utc>,
+    posts: Vec<String>,
+    recipients: usize,
+}
+
+impl Record for Issue {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+fn issues(data_dir: &Path) -> io::Result<Store<Issue>> {
+    Store::open(data_dir.join("newsletter.jsonl"))
+}
+
+/// New posts as an email in `lang`, with `UNSUBSCRIBE` where the link goes.
+struct Digest {
+    subject: String,
+    text: String,
+    html: String,
+}
+
+type Post = (&'static str, &'static str, DateTime<Utc>, &'static str);
+
+fn digest(site: &str, posts: &[Post], lang: &str) -> Digest {
+    let strings = i18n::strings(lang);
+    let url = |slug: &str| format!("{site}/post/{slug}");
+    let titles: Vec<&str> = posts.iter().map(|(_, title, ..)| *title).collect();
+    let subject = match titles.as_slice() {
+        [title] => title.to_string(),
+        titles => format!("{}: {}", strings.new_posts, titles.join(", ")),
+    };
+
+    let mut text = format!("{}\n\n", strings.new_posts);
+    for (slug, title, date, excerpt) in posts {
+        text.push_str(&format!(
+            "{title}\n{}\n\n{excerpt}\n\n{}: {}\n\n",
+            strings.date(dates::local(*date)),
+            strings.read_more,
+            url(slug)
+        ));
+    }
+    text.push_str(&format!(
+        "--\n{}\n{}: {UNSUBSCRIBE}\n",
+        strings.digest_footer, strings.unsubscribe
+    ));
+
+    let html = html! {
+        div style="max-width:36rem;margin:0 auto;font-family:sans-serif;line-height:1.5" {
+            h1 style="font-size:1.25rem" { (strings.new_posts) }
+            @for (slug, title, date, excerpt) in posts {
+                div style="margin:1.5rem 0" {
+                    h2 style="font-size:1.125rem;margin:0" {
+                        a href=(url(slug)) { (title) }
+                    }
+                    p style="margin:0;color:#666;font-size:0.875rem" {
+                        (strings.date(dates::local(*date)))
+                    }
+                    p { (excerpt) }
+                    a href=(url(slug)) { (strings.read_more) " →" }
+                }
+            }
+            hr;
+            p style="color:#666;font-size:0.875rem" {
+                (strings.digest_footer) " "
+                a href=(UNSUBSCRIBE) { (strings.unsubscribe) }
+            }
+        }
+    }
+    .into_string();
+
+    Digest {
+        subject,
+        text,
+        html,
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: focus newsletter build [--site <url>] [--since <date>] [--out <dir>]\n       \
+         focus newsletter send [--site <url>] [--since <date>]"
+    );
+    std::process::exit(1);
+}
+
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+}
+
+/// `focus newsletter build|send`. Both take the posts published since the
+/// last send, or since `--since`; `build` writes the email to files to look
+/// at and `send` mails it to every confirmed subscriber.
+pub fn run(args: &[String]) -> io::Result<()> {
+    let command = match args.first().map(String::as_str) {
+        Some(command @ ("build" | "send")) => command,
+        _ => usage(),
+    };
+    let server = config::get();
+    let Some(site) = option(args, "--site")
+        .cloned()
+        .or_else(|| server.site_url().map(str::to_string))
+    else {
+        eprintln!("Error: set g_site_url or pass --site with the public address of the blog");
+        std::process::exit(1);
+    };
+    let site = site.trim_end_matches('/');
+
+    let issues = issues(server.data_dir())?;
+    let now = Utc::now().trunc_subsecs(0);
+    let since = match option(args, "--since") {
+        Some(since) => dates::parse(since).unwrap_or_else(|err| {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }),
+        None => issues
+            .all()
+            .iter()
+            .map(|issue| issue.sent)
+            .max()
+            .unwrap_or(now - Days::new(FIRST_ISSUE_DAYS)),
+    };
+    let posts = about::posts_between(since, now);
+    if posts.is_empty() {
+        println!("No new posts since {}", dates::local(since).to_rfc3339());
+        return Ok(());
+    }
+
+    if command == "build" {
+        let out = PathBuf::from(option(args, "--out").map_or(".", String::as_str));
+        fs::create_dir_all(&out)?;
+        let digest = digest(site, &posts, i18n::default_language());
+        let sample = newsletter::unsubscribe_url(site, "TOKEN");
+        fs::write(
+            out.join("newsletter.txt"),
+            format!(
+                "Subject: {}\n\n{}",
+                digest.subject,
+                digest.text.replace(UNSUBSCRIBE, &sample)
+            ),
+        )?;
+        fs::write(
+            out.join("newsletter.html"),
+            digest.html.replace(UNSUBSCRIBE, &sample),
+        )?;
+        println!(
+            "Wrote newsletter.txt and newsletter.html with {} post(s) to {}",
+            posts.len(),
+            out.display()
+        );
+        return Ok(());
+    }
+
+    let mailer = SmtpMailer::new(server.newsletter()).unwrap_or_else(|err| {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    });
+    let recipients = newsletter::recipients(server.data_dir())?;
+    let (sent, failed) = send(&mailer, site, &posts, &recipients);
+    println!(
+        "Sent {} post(s) to {sent} subscriber(s): {failed} failed",
+        posts.len()
+    );
+
+    if sent > 0 {
+        issues.put(Issue {
+            id: now.to_rfc3339(),
+            sent: now,
+            posts: posts.iter().map(|(slug, ..)| slug.to_string()).collect(),
+            recipients: sent,
+        })?;
+    }
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Mails the digest to every recipient in their language. Returns how many
+/// went out and how many failed.
+fn send(
+    mailer: &impl Mailer,
+    site: &str,
+    posts: &[Post],
+    recipients: &[Recipient],
+) -> (usize, usize) {
+    let mut digests: HashMap<String, Digest> = HashMap::new();
+    let (mut sent, mut failed) = (0, 0);
+    for recipient in recipients {
+        let digest = digests
+            .entry(recipient.lang.clone())
+            .or_insert_with(|| digest(site, posts, &recipient.lang));
+        let unsubscribe = newsletter::unsubscribe_url(site, &recipient.token);
+        let email = Email {
+            to: recipient.email.clone(),
+            subject: digest.subject.clone(),
+            text: digest.text.replace(UNSUBSCRIBE, &unsubscribe),
+            html: digest.html.replace(UNSUBSCRIBE, &unsubscribe),
+            unsubscribe: Some(unsubscribe),
+        };
+        match mailer.send(&email) {
+            Ok(()) => sent += 1,
+            Err(err) => {
+                failed += 1;
+                eprintln!("failed  {} ({err})", recipient.email);
+            }
+        }
+    }
+    (sent, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::mail::Outbox;
+    use super::*;
+
+    const SITE: &str = "https://blog.example";
+
+    fn posts() -> Vec<Post> {
+        let date = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        vec![
+            ("first", "First Post", date, "The first excerpt."),
+            ("second", "Second <Post>", date, "The second excerpt."),
+        ]
+    }
+
+    fn recipient(email: &str, lang: &str, token: &str) -> Recipient {
+        Recipient {
+            email: email.to_string(),
+            lang: lang.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    #[test]
+    fn send_mails_every_recipient_their_own_link() {
+        let outbox = Outbox::default();
+        let recipients = [
+            recipient("ada@example.com", "en", "token-ada"),
+            recipient("bert@example.com", "de", "token-bert"),
+        ];
+
+        assert_eq!(send(&outbox, SITE, &posts(), &recipients), (2, 0));
+
+        let sent = outbox.sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        for (email, token) in sent.iter().zip(["token-ada", "token-bert"]) {
+            let unsubscribe = format!("{SITE}/newsletter/unsubscribe/{token}");
+            assert_eq!(email.unsubscribe.as_deref(), Some(unsubscribe.as_str()));
+            assert!(email.text.contains(&unsubscribe));
+            assert!(email.html.contains(&unsubscribe));
+            assert!(!email.text.contains(UNSUBSCRIBE));
+            assert!(email.text.contains("https://blog.example/post/first"));
+            assert!(email.html.contains("Second &lt;Post&gt;"));
+        }
+        assert_eq!(sent[0].to, "ada@example.com");
+        assert_ne!(sent[0].subject, sent[1].subject);
+    }
+
+    #[test]
+    fn send_counts_failures() {
+        let outbox = Outbox {
+            refused: vec!["bert@example.com".to_string()],
+            ..Outbox::default()
+        };
+        let recipients = [
+            recipient("ada@example.com", "en", "a"),
+            recipient("bert@example.com", "en", "b"),
+            recipient("cleo@example.com", "en", "c"),
+        ];
+
+        assert_eq!(send(&outbox, SITE, &posts(), &recipients), (2, 1));
+        assert_eq!(outbox.sent.lock().unwrap().len(), 2);
+        assert_eq!(send(&outbox, SITE, &posts(), &[]), (0, 0));
+    }
+
+    #[test]
+    fn digest_subject() {
+        let posts = posts();
+        assert_eq!(digest(SITE, &posts[..1], "en").subject, "First Post");
+        assert!(
+            digest(SITE, &posts, "en")
+                .subject
+                .contains("First Post, Second <Post>")
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpRequest, web};
+
+use crate::config::{Network, Server};
+
+/// Allows each client a number of actions per sliding window, counted in
+/// memory. Counts start over when the server restarts.
+pub struct RateLimit {
+    max: usize,
+    window: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimit {
+    pub fn new(max: usize, window: Duration) -> Self {
+        RateLimit {
+            max,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts an action by `client`, false if it is over the limit. Refused
+    /// actions are not counted, so waiting always helps.
+    pub fn allow(&self, client: &str) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(|err| err.into_inner());
+        hits.retain(|_, times| {
+            while times
+                .front()
+                .is_some_and(|&time| now.duration_since(time) >= self.window)
+            {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let times = hits.entry(client.to_string()).or_default();
+        if times.len() >= self.max {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+/// Address of the client. The connection's peer is the client unless it is
+/// one of `g_trusted_proxies`; then the client is the last address in
+/// `X-Forwarded-For` that no trusted proxy added, as the values before it
+/// come from the client and can say anything.
+pub fn client(req: &HttpRequest) -> String {
+    let trusted = req
+        .app_data::<web::Data<Server>>()
+        .map(|server| server.trusted_proxies())
+        .unwrap_or_default();
+    client_behind(req, trusted)
+}
+
+fn client_behind(req: &HttpRequest, trusted: &[Network]) -> String {
+    let Some(peer) = req.peer_addr().map(|addr| addr.ip()) else {
+        return "unknown".to_string();
+    };
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|network| network.contains(ip));
+    if !is_trusted(peer) {
+        return peer.to_string();
+    }
+
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    for value in forwarded.iter().rev() {
+        match value.parse::<IpAddr>() {
+            Ok(ip) if is_trusted(ip) => continue,
+            Ok(ip) => return ip.to_string(),
+            Err(_) => break,
+        }
+    }
+    peer.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn request(forwarded: Option<&str>) -> HttpRequest {
+        let request = TestRequest::default().peer_addr("10.0.0.2:4000".parse().unwrap());
+        match forwarded {
+            Some(forwarded) => request.insert_header(("x-forwarded-for", forwarded)),
+            None => request,
+        }
+        .to_http_request()
+    }
+
+    fn networks(values: &[&str]) -> Vec<Network> {
+        values
+            .iter()
+            .map(|value| Network::parse(value).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn limits_each_client_separately() {
+        let limit = RateLimit::new(2, Duration::from_secs(60));
+        assert!(limit.allow("a"));
+        assert!(limit.allow("a"));
+        assert!(!limit.allow("a"));
+        assert!(limit.allow("b"));
+    }
+
+    #[test]
+    fn ignores_forwarded_headers_without_a_trusted_proxy() {
+        assert_eq!(
+            client_behind(&request(Some("203.0.113.7")), &[]),
+            "10.0.0.2"
+        );
+        assert_eq!(client_behind(&request(None), &[]), "10.0.0.2");
+    }
+
+    #[test]
+    fn takes_the_address_the_trusted_proxy_added() {
+        let trusted = networks(&["10.0.0.0/8"]);
+        // The client made up the first value, the proxy appended the second
+        let req = request(Some("198.51.100.1, 203.0.113.7"));
+        assert_eq!(client_behind(&req, &trusted), "203.0.113.7");
+    }
+
+    #[test]
+    fn skips_chained_trusted_proxies() {
+        let trusted = networks(&["10.0.0.2", "10.1.0.0/16"]);
+        let req = request(Some("203.0.113.7, 10.1.2.3"));
+        assert_eq!(client_behind(&req, &trusted), "203.0.113.7");
+    }
+
+    #[test]
+    fn falls_back_to_the_peer_on_garbage() {
+        let trusted = networks(&["10.0.0.2"]);
+        assert_eq!(
+            client_behind(&request(Some("nonsense")), &trusted),
+            "10.0.0.2"
+        );
+        assert_eq!(client_behind(&request(None), &trusted), "10.0.0.2");
+    }
+}
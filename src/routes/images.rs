@@ -0,0 +1,394 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use actix_web::http::header;
+use actix_web::web::{self, Bytes};
+use actix_web::{HttpResponse, Scope};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use lazy_static::lazy_static;
+use maud::{PreEscaped, html};
+use regex::{Captures, Regex};
+
+use crate::config;
+use crate::sanitize;
+use crate::view::about;
+
+lazy_static! {
+    /// An `<img>` tag, with the paragraph around it when it stands alone.
+    static ref IMG: Regex = Regex::new(
+        r#"(?is)(<p>\s*)?<img\b((?:[^>"']|"[^"]*"|'[^']*')*)>(\s*</p>)?"#
+    )
+    .unwrap();
+    static ref DIMENSIONS: Mutex<HashMap<String, Option<(u32, u32)>>> = Mutex::new(HashMap::new());
+    /// Encoded variants, `None` for ones no smaller than the original.
+    static ref VARIANTS: Mutex<HashMap<String, Option<Bytes>>> = Mutex::new(HashMap::new());
+}
+
+const CACHE_CONTROL: &str = "public, max-age=604800";
+
+/// Modern formats offered next to the original, best compression first.
+#[derive(Clone, Copy)]
+enum Format {
+    Avif,
+    Webp,
+}
+
+const FORMATS: [Format; 2] = [Format::Avif, Format::Webp];
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Avif => "avif",
+            Format::Webp => "webp",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            Format::Avif => "image/avif",
+            Format::Webp => "image/webp",
+        }
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        FORMATS
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// Maps an image URL from a post to its source: `/assets/...` files, files
+/// of a post bundle and paths relative to `posts/`. Other URLs are left to the browser.
+pub fn source(src: &str) -> Option<String> {
+    if src.contains(':') || src.starts_with("//") {
+        return None;
+    }
+    let path = src.split(['?', '#']).next()?;
+    let path = urlencoding::decode(path).ok()?;
+    if path.split('/').any(|segment| segment == "..") {
+        return None;
+    }
+
+    if let Some(asset) = path.strip_prefix("/assets/") {
+        return Some(format!("assets/{asset}"));
+    }
+    if let Some((slug, file)) = path
+        .strip_prefix("/post/")
+        .and_then(|rest| rest.split_once('/'))
+    {
+        return about::bundle_source(slug, file);
+    }
+    (!path.starts_with('/')).then(|| format!("posts/{}", path.trim_start_matches("./")))
+}
+
+/// Raw bytes of a source, read from disk for assets and from the
+/// embedded posts directory otherwise.
+pub fn load(source: &str) -> Option<Cow<'static, [u8]>> {
+    if source.split('/').any(|segment| segment == "..") {
+        return None;
+    }
+    match source.split_once('/')? {
+        ("assets", _) => fs::read(source).ok().map(Cow::Owned),
+        ("posts", path) => about::post_file(path).map(Cow::Borrowed),
+        _ => None,
+    }
+}
+
+/// Formats the pipeline can decode; anything else, like SVG, is served as is.
+fn raster_format(source: &str) -> Option<ImageFormat> {
+    ImageFormat::from_path(source).ok().filter(|format| {
+        matches!(
+            format,
+            ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP
+        )
+    })
+}
+
+fn dimensions(source: &str) -> Option<(u32, u32)> {
+    if let Some(&cached) = DIMENSIONS.lock().unwrap().get(source) {
+        return cached;
+    }
+    // Read without holding the lock, so other pages do not wait on this file
+    let dimensions = read_dimensions(source);
+    DIMENSIONS
+        .lock()
+        .unwrap()
+        .insert(source.to_string(), dimensions);
+    dimensions
+}
+
+fn read_dimensions(source: &str) -> Option<(u32, u32)> {
+    raster_format(source)?;
+    let bytes = load(source)?;
+    ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Configured widths narrower than the original, plus the original itself
+/// unless it is wider than every configured width.
+fn widths(original: u32) -> Vec<u32> {
+    let configured = &config::get().images().widths;
+    let mut widths: Vec<u32> = configured
+        .iter()
+        .copied()
+        .filter(|&width| width < original)
+        .collect();
+    if configured
+        .last()
+        .is_some_and(|&largest| original <= largest)
+    {
+        widths.push(original);
+    }
+    widths
+}
+
+fn encode_path(source: &str) -> String {
+    source
+        .split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn srcset(source: &str, widths: &[u32], format: Format) -> String {
+    let source = encode_path(source);
+    widths
+        .iter()
+        .map(|width| format!("/images/{width}.{}/{source} {width}w", format.extension()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn rewrite_image(caps: &Captures) -> String {
+    let attributes = &caps[2];
+    let attribute = |name: &str| sanitize::attribute(attributes, name);
+    let standalone = caps.get(1).is_some() && caps.get(3).is_some();
+
+    let src = attribute("src").unwrap_or_default();
+    let alt = attribute("alt").unwrap_or_default();
+    let title = attribute("title").filter(|title| !title.is_empty());
+
+    let image = match source(&src).and_then(|source| Some((dimensions(&source)?, source))) {
+        Some(((width, height), source)) => {
+            let widths = widths(width);
+            html! {
+                picture {
+                    @if !widths.is_empty() {
+                        @for format in FORMATS {
+                            source type=(format.mime()) srcset=(srcset(&source, &widths, format)) sizes=(config::get().images().sizes);
+                        }
+                    }
+                    img src={ "/images/original/" (encode_path(&source)) } alt=(alt) title=[&title]
+                        width=(width) height=(height) loading="lazy" decoding="async";
+                }
+            }
+        }
+        // Unknown images keep their markup and only load lazily
+        None if attribute("loading").is_some() => PreEscaped(format!("<img{attributes}>")),
+        None => PreEscaped(format!("<img loading=\"lazy\"{attributes}>")),
+    };
+
+    let caption = title.or((!alt.is_empty()).then_some(alt));
+    match (standalone, caption) {
+        (true, Some(caption)) => html! {
+            figure {
+                (image)
+                figcaption { (caption) }
+            }
+        }
+        .into_string(),
+        (true, None) => html! { p { (image) } }.into_string(),
+        (false, _) => format!(
+            "{}{}{}",
+            caps.get(1).map_or("", |m| m.as_str()),
+            image.into_string(),
+            caps.get(3).map_or("", |m| m.as_str())
+        ),
+    }
+}
+
+/// Turns the `<img>` tags of rendered post HTML into lazily loaded,
+/// responsive `<picture>`s with their dimensions. Images alone in a
+/// paragraph become a `<figure>` captioned by their title or alt text.
+pub fn rewrite(html: &str) -> String {
+    IMG.replace_all(html, rewrite_image).into_owned()
+}
+
+/// The variant of `original`, or `None` if it comes out no smaller than the
+/// original file. Lossless WebP often does for photos.
+fn smaller_variant(
+    original: &[u8],
+    width: u32,
+    format: Format,
+) -> image::ImageResult<Option<Vec<u8>>> {
+    let encoded = encode(original, width, format)?;
+    Ok((encoded.len() < original.len()).then_some(encoded))
+}
+
+fn encode(bytes: &[u8], width: u32, format: Format) -> image::ImageResult<Vec<u8>> {
+    let image = image::load_from_memory(bytes)?;
+    let image = if width < image.width() {
+        image.resize(width, u32::MAX, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    let image = DynamicImage::ImageRgba8(image.to_rgba8());
+
+    let mut encoded = Vec::new();
+    match format {
+        Format::Avif => {
+            image.write_with_encoder(AvifEncoder::new_with_speed_quality(&mut encoded, 8, 75))?
+        }
+        Format::Webp => image.write_with_encoder(WebPEncoder::new_lossless(&mut encoded))?,
+    }
+    Ok(encoded)
+}
+
+fn image_response(content_type: &str, bytes: Bytes) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((header::CACHE_CONTROL, CACHE_CONTROL))
+        .body(bytes)
+}
+
+/// `/images/{width}.{format}/{source}` resizes and encodes a variant on
+/// first request and keeps it in memory, or redirects to the original when
+/// the variant would be no smaller; `/images/original/{source}` serves the
+/// source file.
+async fn variant(path: web::Path<(String, String)>) -> HttpResponse {
+    let (variant, source) = path.into_inner();
+    let (Some(original), Some(format)) = (load(&source), raster_format(&source)) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    if variant == "original" {
+        return image_response(format.to_mime_type(), Bytes::from(original.into_owned()));
+    }
+
+    let requested = variant.split_once('.').and_then(|(width, extension)| {
+        Some((
+            width.parse::<u32>().ok()?,
+            Format::from_extension(extension)?,
+        ))
+    });
+    let Some((width, format)) = requested else {
+        return HttpResponse::NotFound().finish();
+    };
+    let allowed =
+        dimensions(&source).is_some_and(|(original, _)| widths(original).contains(&width));
+    if !allowed {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let key = format!("{variant}/{source}");
+    let respond = |bytes: Option<Bytes>| match bytes {
+        Some(bytes) => image_response(format.mime(), bytes),
+        None => HttpResponse::Found()
+            .insert_header((
+                header::LOCATION,
+                format!("/images/original/{}", encode_path(&source)),
+            ))
+            .insert_header((header::CACHE_CONTROL, CACHE_CONTROL))
+            .finish(),
+    };
+    if let Some(bytes) = VARIANTS.lock().unwrap().get(&key) {
+        return respond(bytes.clone());
+    }
+
+    match web::block(move || smaller_variant(&original, width, format)).await {
+        Ok(Ok(encoded)) => {
+            let bytes = encoded.map(Bytes::from);
+            VARIANTS.lock().unwrap().insert(key, bytes.clone());
+            respond(bytes)
+        }
+        Ok(Err(err)) => {
+            log::error!("Could not encode {variant} of {source}: {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(err) => {
+            log::error!("Could not encode {variant} of {source}: {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+pub fn scope() -> Scope {
+    web::scope("/images").route("/{variant}/{source:.*}", web::get().to(variant))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageBuffer, Rgb};
+
+    use super::*;
+
+    fn image(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 3]) -> DynamicImage {
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| Rgb(pixel(x, y))))
+    }
+
+    fn bytes(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    /// Deterministic noise, which lossless encoders cannot shrink.
+    fn noise(x: u32, y: u32) -> [u8; 3] {
+        let n =
+            (x.wrapping_mul(374_761_393) ^ y.wrapping_mul(668_265_263)).wrapping_mul(1_274_126_177);
+        [(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8]
+    }
+
+    #[test]
+    fn skips_variants_larger_than_the_original() {
+        let photo = bytes(&image(256, 256, noise), ImageFormat::Jpeg);
+        assert!(
+            smaller_variant(&photo, 256, Format::Webp)
+                .unwrap()
+                .is_none()
+        );
+
+        let flat = bytes(&image(256, 256, |_, _| [200, 120, 40]), ImageFormat::Png);
+        let webp = smaller_variant(&flat, 128, Format::Webp).unwrap().unwrap();
+        assert!(webp.len() < flat.len());
+        let decoded = image::load_from_memory_with_format(&webp, ImageFormat::WebP).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 128));
+    }
+
+    #[test]
+    fn dimensions_of_unknown_sources_are_cached() {
+        assert_eq!(dimensions("assets/missing.png"), None);
+        assert_eq!(dimensions("assets/grocy.svg"), None);
+        assert!(
+            DIMENSIONS
+                .lock()
+                .unwrap()
+                .contains_key("assets/missing.png")
+        );
+    }
+
+    #[test]
+    fn sources() {
+        assert_eq!(source("/assets/a.png").as_deref(), Some("assets/a.png"));
+        assert_eq!(
+            source("./img/a%20b.png").as_deref(),
+            Some("posts/img/a b.png")
+        );
+        assert_eq!(source("https://example.com/a.png"), None);
+        assert_eq!(source("//example.com/a.png"), None);
+        assert_eq!(source("/assets/../Cargo.toml"), None);
+        assert_eq!(load("assets/../Cargo.toml"), None);
+    }
+}
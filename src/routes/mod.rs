@@ -1,2 +1,4 @@
 pub mod assets;
+pub mod images;
+pub mod redirects;
 pub mod technical;
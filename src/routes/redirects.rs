@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use actix_web::http::{Method, header};
+use actix_web::{HttpRequest, HttpResponse};
+use lazy_static::lazy_static;
+
+use crate::view::about;
+
+/// Site-wide path mappings, one `from to` pair per line. Lines starting with `#` are comments.
+static REDIRECTS_FILE: &str = include_str!("../../redirects.txt");
+
+lazy_static! {
+    static ref REDIRECTS: Result<HashMap<String, String>, String> = build();
+}
+
+fn normalize(path: &str) -> String {
+    let path = path.trim();
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    };
+    match path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+fn parse_file(file: &str) -> Result<Vec<(String, String, String)>, String> {
+    let mut entries = Vec::new();
+
+    for (number, line) in file.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let origin = format!("redirects.txt:{}", number + 1);
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [from, to] => entries.push((from.to_string(), to.to_string(), origin)),
+            _ => return Err(format!("{origin}: expected `from to`, got `{line}`")),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn build() -> Result<HashMap<String, String>, String> {
+    let aliases = about::post_aliases().into_iter().map(|(from, to)| {
+        let origin = format!("alias of {to}");
+        (from, to, origin)
+    });
+    let entries = parse_file(REDIRECTS_FILE)?.into_iter().chain(aliases);
+    table(entries, &about::post_paths())
+}
+
+/// Normalized `from -> to` map, refusing loops, shadowed posts and paths
+/// redirected twice.
+fn table(
+    entries: impl IntoIterator<Item = (String, String, String)>,
+    canonical: &[String],
+) -> Result<HashMap<String, String>, String> {
+    let mut redirects = HashMap::new();
+    let mut origins: HashMap<String, String> = HashMap::new();
+
+    for (from, to, origin) in entries {
+        let from = normalize(&from);
+        let to = if to.contains("://") {
+            to
+        } else {
+            normalize(&to)
+        };
+
+        if from == to {
+            return Err(format!("{origin}: {from} redirects to itself"));
+        }
+        if canonical.contains(&from) {
+            return Err(format!("{origin}: {from} is already the path of a post"));
+        }
+        if let Some(first) = origins.get(&from) {
+            return Err(format!("{origin}: {from} is already redirected by {first}"));
+        }
+
+        origins.insert(from.clone(), origin);
+        redirects.insert(from, to);
+    }
+
+    Ok(redirects)
+}
+
+/// Builds the redirect table so duplicate aliases stop the server at startup.
+pub fn validate() -> Result<usize, String> {
+    REDIRECTS.as_ref().map(HashMap::len).map_err(Clone::clone)
+}
+
+pub fn lookup(path: &str) -> Option<&'static str> {
+    REDIRECTS
+        .as_ref()
+        .ok()?
+        .get(&normalize(path))
+        .map(String::as_str)
+}
+
+pub fn permanent(target: &str, query: &str) -> HttpResponse {
+    let location = if query.is_empty() {
+        target.to_string()
+    } else {
+        format!("{target}?{query}")
+    };
+
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+/// Fallback for paths no route matched.
+pub async fn fallback(req: HttpRequest) -> HttpResponse {
+    if matches!(*req.method(), Method::GET | Method::HEAD) {
+        let path = urlencoding::decode(req.path())
+            .map(|path| path.into_owned())
+            .unwrap_or_else(|_| req.path().to_string());
+
+        if let Some(target) = lookup(&path) {
+            return permanent(target, req.query_string());
+        }
+    }
+
+    HttpResponse::NotFound().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn entries(file: &str) -> Vec<(String, String, String)> {
+        parse_file(file).unwrap()
+    }
+
+    #[test]
+    fn parses_pairs_and_skips_comments() {
+        let file = "# comment\n\n  /old /new  \n/gone\thttps://example.com/\n";
+        let parsed: Vec<_> = entries(file)
+            .into_iter()
+            .map(|(from, to, origin)| format!("{from} {to} {origin}"))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                "/old /new redirects.txt:3",
+                "/gone https://example.com/ redirects.txt:4",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_lines_without_exactly_two_paths() {
+        assert_eq!(
+            parse_file("/a /b\n/c\n").unwrap_err(),
+            "redirects.txt:2: expected `from to`, got `/c`"
+        );
+        assert!(parse_file("/a /b /c").is_err());
+    }
+
+    #[test]
+    fn normalizes_both_sides() {
+        let redirects = table(entries("old/ /new/\n/ext https://example.com/x/"), &[]).unwrap();
+        assert_eq!(redirects["/old"], "/new");
+        assert_eq!(redirects["/ext"], "https://example.com/x/");
+    }
+
+    #[test]
+    fn rejects_duplicates_loops_and_post_paths() {
+        assert_eq!(
+            table(entries("/a /b\n/a/ /c"), &[]).unwrap_err(),
+            "redirects.txt:2: /a is already redirected by redirects.txt:1"
+        );
+        assert_eq!(
+            table(entries("/a /a/"), &[]).unwrap_err(),
+            "redirects.txt:1: /a redirects to itself"
+        );
+        let canonical = ["/post/kept".to_string()];
+        let alias = (
+            "/post/kept".to_string(),
+            "/post/other".to_string(),
+            "alias of /post/other".to_string(),
+        );
+        assert_eq!(
+            table([alias], &canonical).unwrap_err(),
+            "alias of /post/other: /post/kept is already the path of a post"
+        );
+    }
+
+    #[test]
+    fn shipped_redirects_are_valid() {
+        assert!(validate().is_ok());
+    }
+
+    #[actix_web::test]
+    async fn fallback_redirects_aliases_permanently() {
+        let req = TestRequest::get()
+            .uri("/post/My-own-world/?ref=feed")
+            .to_http_request();
+        let response = fallback(req).await;
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "/post/my-own-world?ref=feed"
+        );
+
+        let req = TestRequest::post()
+            .uri("/post/My-own-world")
+            .to_http_request();
+        assert_eq!(fallback(req).await.status(), StatusCode::NOT_FOUND);
+        let req = TestRequest::get().uri("/nowhere").to_http_request();
+        assert_eq!(fallback(req).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,342 @@
+use std::borrow::Cow;
+
+use ammonia::Builder;
+use lazy_static::lazy_static;
+use maud::html;
+use regex::{Captures, Regex};
+use url::Url;
+
+use crate::config::{self, Sanitize};
+
+lazy_static! {
+    static ref POLICY: Sanitize = checked(config::get().sanitize().clone());
+    static ref IFRAME: Regex =
+        Regex::new(r#"(?is)<iframe\b((?:[^>"']|"[^"]*"|'[^']*')*)>(?:.*?</iframe\s*>)?"#).unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"(?i)([a-z][a-z0-9-]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+}
+
+/// Tags whose content ammonia drops entirely; allowing them as tags would panic.
+const CONTENT_TAGS: [&str; 2] = ["script", "style"];
+
+/// Checks the configured policy at startup, so its warnings are logged
+/// before the first post is rendered.
+pub fn prepare() {
+    lazy_static::initialize(&POLICY);
+}
+
+/// Sanitizes rendered post HTML according to the configured policy.
+/// Returns the input unchanged when sanitization is disabled.
+pub fn clean(html: &str) -> String {
+    if !POLICY.enabled {
+        return html.to_string();
+    }
+    clean_with(html, &POLICY)
+}
+
+pub fn clean_with(html: &str, policy: &Sanitize) -> String {
+    let html = rewrite_iframes(html, &policy.iframe_hosts);
+    builder(policy).clean(&html).to_string()
+}
+
+/// Renders markdown written by readers. Raw HTML is escaped and only text
+/// formatting, lists, quotes, code and links survive; links are marked as
+/// user content so they pass on no ranking.
+pub fn reader_markdown(source: &str) -> String {
+    let html = markdown::to_html_with_options(source, &markdown::Options::gfm())
+        .unwrap_or_else(|_| markdown::to_html(source));
+    Builder::empty()
+        .add_tags([
+            "p",
+            "br",
+            "strong",
+            "em",
+            "del",
+            "code",
+            "pre",
+            "blockquote",
+            "ul",
+            "ol",
+            "li",
+            "a",
+        ])
+        .add_tag_attributes("a", ["href"])
+        .url_schemes(["http", "https", "mailto"].into())
+        .link_rel(Some("nofollow ugc noopener noreferrer"))
+        .clean(&html)
+        .to_string()
+}
+
+fn builder(policy: &Sanitize) -> Builder<'_> {
+    let mut builder = Builder::default();
+    builder
+        // Produced by the GFM compiler: task lists, footnotes and code languages
+        .add_tags(["input", "section", "iframe"])
+        .add_generic_attributes([
+            "id",
+            "aria-label",
+            "aria-describedby",
+            "data-footnotes",
+            "data-footnote-ref",
+            "data-footnote-backref",
+        ])
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("section", ["class"])
+        // Admonitions and collapsibles from the post renderer
+        .add_tag_attributes("div", ["class", "role"])
+        .add_tag_attributes("details", ["class"])
+        .add_tag_attributes("summary", ["class"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("img", ["loading", "width", "height"])
+        .add_tag_attributes(
+            "iframe",
+            ["src", "title", "width", "height", "allowfullscreen"],
+        )
+        .set_tag_attribute_value("iframe", "loading", "lazy")
+        .set_tag_attribute_value(
+            "iframe",
+            "referrerpolicy",
+            "strict-origin-when-cross-origin",
+        )
+        .set_tag_attribute_value(
+            "iframe",
+            "sandbox",
+            "allow-scripts allow-same-origin allow-presentation allow-popups",
+        );
+
+    builder.add_tags(
+        policy
+            .tags
+            .iter()
+            .map(String::as_str)
+            .filter(|tag| is_safe_tag(tag)),
+    );
+
+    for attribute in policy
+        .attributes
+        .iter()
+        .map(String::as_str)
+        .filter(|attribute| is_safe_attribute(attribute))
+    {
+        match attribute.split_once(':') {
+            Some((tag, name)) => {
+                builder.add_tag_attributes(tag, [name]);
+            }
+            None => {
+                builder.add_generic_attributes([attribute]);
+            }
+        }
+    }
+
+    let hosts = policy.iframe_hosts.clone();
+    builder.attribute_filter(move |element, attribute, value| {
+        if element == "iframe" && attribute == "src" {
+            return allowed_frame(value, &hosts).map(Cow::Owned);
+        }
+        Some(Cow::Borrowed(value))
+    });
+
+    builder
+}
+
+fn is_safe_tag(tag: &str) -> bool {
+    !CONTENT_TAGS.contains(&tag.to_ascii_lowercase().as_str())
+}
+
+/// Whether `name` or `tag:name` can be allowed. Event handlers, `style` and
+/// `srcdoc` can run scripts; `rel` is set on links by the sanitizer itself
+/// and attributes of content tags never survive, so ammonia refuses both.
+fn is_safe_attribute(attribute: &str) -> bool {
+    let (tag, name) = match attribute.split_once(':') {
+        Some((tag, name)) => (Some(tag), name),
+        None => (None, attribute),
+    };
+    let name = name.to_ascii_lowercase();
+    !name.is_empty()
+        && !name.starts_with("on")
+        && !matches!(name.as_str(), "style" | "srcdoc" | "rel")
+        && tag.is_none_or(|tag| !tag.is_empty() && is_safe_tag(tag))
+}
+
+/// Drops what the sanitizer cannot allow from the configured policy.
+fn checked(mut policy: Sanitize) -> Sanitize {
+    policy.tags.retain(|tag| {
+        let safe = is_safe_tag(tag);
+        if !safe {
+            log::warn!("Sanitizer: ignoring allowed tag {tag}, its content is always removed");
+        }
+        safe
+    });
+    policy.attributes.retain(|attribute| {
+        let safe = is_safe_attribute(attribute);
+        if !safe {
+            log::warn!("Sanitizer: ignoring allowed attribute {attribute}, it cannot be allowed");
+        }
+        safe
+    });
+    policy
+}
+
+/// Value of attribute `name` in the attribute text of a tag, with the
+/// common character references decoded.
+pub fn attribute(attributes: &str, name: &str) -> Option<String> {
+    ATTRIBUTE
+        .captures_iter(attributes)
+        .find(|attr| attr[1].eq_ignore_ascii_case(name))
+        .and_then(|attr| attr.get(2).or(attr.get(3)).or(attr.get(4)))
+        .map(|value| {
+            value
+                .as_str()
+                .replace("&quot;", "\"")
+                .replace("&#x27;", "'")
+                .replace("&#39;", "'")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&")
+        })
+}
+
+/// Normalizes an iframe source to https if its host is allow-listed.
+fn allowed_frame(src: &str, hosts: &[String]) -> Option<String> {
+    let src = src.trim();
+    let mut url = match src.strip_prefix("//") {
+        Some(rest) => Url::parse(&format!("https://{rest}")).ok()?,
+        None => Url::parse(src).ok()?,
+    };
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let host = url.host_str()?;
+    if !hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        return None;
+    }
+
+    url.set_scheme("https").ok()?;
+    Some(url.to_string())
+}
+
+/// Rebuilds iframes from their `src` alone, so authors cannot smuggle in
+/// `srcdoc` or permission attributes. Iframes to other hosts become links.
+fn rewrite_iframes<'a>(html: &'a str, hosts: &[String]) -> Cow<'a, str> {
+    IFRAME.replace_all(html, |caps: &Captures| {
+        let attribute = |name: &str| attribute(&caps[1], name);
+        let dimension = |name: &str| attribute(name).filter(|v| v.parse::<u32>().is_ok());
+
+        let Some(src) = attribute("src") else {
+            return String::new();
+        };
+
+        match allowed_frame(&src, hosts) {
+            Some(src) => html! {
+                iframe src=(src) title=[attribute("title")] width=[dimension("width")] height=[dimension("height")] allowfullscreen {}
+            },
+            None => html! {
+                a href=(src) { (src) }
+            },
+        }
+        .into_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Sanitize {
+        Sanitize {
+            enabled: true,
+            tags: Vec::new(),
+            attributes: Vec::new(),
+            iframe_hosts: vec!["www.youtube-nocookie.com".to_string()],
+        }
+    }
+
+    #[test]
+    fn removes_scripts() {
+        let html = clean_with("<p>hi</p><script>alert(1)</script>", &policy());
+        assert_eq!(html, "<p>hi</p>");
+    }
+
+    #[test]
+    fn removes_event_handlers() {
+        let html = clean_with(r#"<img src="x.png" onerror="alert(1)">"#, &policy());
+        assert!(!html.contains("onerror"), "{html}");
+        assert!(html.contains(r#"src="x.png""#), "{html}");
+    }
+
+    #[test]
+    fn removes_javascript_links() {
+        let html = clean_with(r#"<a href="javascript:alert(1)">x</a>"#, &policy());
+        assert!(!html.contains("javascript"), "{html}");
+    }
+
+    #[test]
+    fn rebuilds_iframes_without_srcdoc() {
+        let html = clean_with(
+            r#"<iframe src="https://www.youtube-nocookie.com/embed/x" srcdoc="<script>alert(1)</script>"></iframe>"#,
+            &policy(),
+        );
+        assert!(!html.contains("srcdoc"), "{html}");
+        assert!(!html.contains("<script"), "{html}");
+        assert!(
+            html.contains(r#"src="https://www.youtube-nocookie.com/embed/x""#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn turns_iframes_to_other_hosts_into_links() {
+        let html = clean_with(
+            r#"<iframe src="https://evil.example/frame"></iframe>"#,
+            &policy(),
+        );
+        assert!(!html.contains("<iframe"), "{html}");
+        assert!(
+            html.contains(r#"href="https://evil.example/frame""#),
+            "{html}"
+        );
+
+        let html = clean_with(r#"<iframe src="javascript:alert(1)"></iframe>"#, &policy());
+        assert!(!html.contains("<iframe"), "{html}");
+        assert!(!html.contains("href"), "{html}");
+    }
+
+    #[test]
+    fn ignores_configured_attributes_that_run_scripts() {
+        let mut policy = policy();
+        policy.attributes = vec!["onclick".to_string(), "img:style".to_string()];
+        let html = clean_with(
+            r#"<p onclick="alert(1)">x</p><img src="x.png" style="x">"#,
+            &policy,
+        );
+        assert!(!html.contains("onclick"), "{html}");
+        assert!(!html.contains("style"), "{html}");
+    }
+
+    #[test]
+    fn survives_attributes_ammonia_refuses() {
+        let mut policy = policy();
+        policy.tags = vec!["script".to_string()];
+        policy.attributes = ["rel", "a:rel", "script:src", "style:x"]
+            .map(String::from)
+            .to_vec();
+        let html = clean_with(r#"<a href="https://example.com" rel="me">x</a>"#, &policy);
+        assert!(html.contains("noopener"), "{html}");
+
+        let policy = checked(policy);
+        assert!(policy.tags.is_empty());
+        assert!(policy.attributes.is_empty());
+    }
+
+    #[test]
+    fn reader_markdown_escapes_html() {
+        let html = reader_markdown("<script>alert(1)</script> [x](javascript:alert(1))");
+        assert!(!html.contains("<script"), "{html}");
+        assert!(!html.contains("href"), "{html}");
+    }
+}
@@ -0,0 +1,96 @@
+use std::convert::Infallible;
+use std::future::{Ready, ready};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, web};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::Rng;
+
+use crate::config::Server;
+
+/// Per-request nonce that marks our own `script` tags as trusted under the CSP.
+/// Empty when security headers are disabled, in which case no attribute is rendered.
+#[derive(Clone, Default)]
+pub struct Nonce(Option<String>);
+
+impl Nonce {
+    fn generate() -> Self {
+        let bytes: [u8; 16] = rand::rng().random();
+        Nonce(Some(STANDARD.encode(bytes)))
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    pub fn of(req: &HttpRequest) -> Self {
+        req.extensions().get::<Nonce>().cloned().unwrap_or_default()
+    }
+}
+
+impl FromRequest for Nonce {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Nonce::of(req)))
+    }
+}
+
+pub async fn headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let config = req
+        .app_data::<web::Data<Server>>()
+        .map(|server| server.security().clone());
+
+    let Some(config) = config.filter(|c| c.enabled) else {
+        return next.call(req).await;
+    };
+
+    let nonce = Nonce::generate();
+    req.extensions_mut().insert(nonce.clone());
+
+    let mut res = next.call(req).await?;
+    let headers = res.headers_mut();
+
+    let csp = [
+        config
+            .content_security_policy
+            .map(|policy| policy.replace("{nonce}", nonce.value().unwrap_or_default())),
+        config
+            .frame_ancestors
+            .map(|ancestors| format!("frame-ancestors {ancestors}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("; ");
+
+    let values = [
+        (
+            "content-security-policy",
+            Some(csp).filter(|c| !c.is_empty()),
+        ),
+        ("x-content-type-options", config.content_type_options),
+        ("referrer-policy", config.referrer_policy),
+        ("permissions-policy", config.permissions_policy),
+    ];
+
+    for (name, value) in values {
+        let Some(value) = value else { continue };
+        match HeaderValue::from_str(&value) {
+            Ok(value) => {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+            Err(_) => log::warn!("Skipping invalid {name} header value: {value}"),
+        }
+    }
+
+    Ok(res)
+}
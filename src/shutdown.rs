@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Counters shared between all workers so the shutdown summary can report
+/// how many requests were drained and how many were cut off.
+#[derive(Clone, Default)]
+pub struct RequestStats {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    in_flight: AtomicUsize,
+    served: AtomicUsize,
+    aborted: AtomicUsize,
+    stop: Mutex<Option<Stop>>,
+}
+
+struct Stop {
+    signal: &'static str,
+    at: Instant,
+    in_flight: usize,
+    served: usize,
+}
+
+impl RequestStats {
+    pub fn track(&self) -> InFlight {
+        self.inner.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight {
+            inner: self.inner.clone(),
+            completed: false,
+        }
+    }
+
+    fn mark_stop(&self, signal: &'static str) {
+        let stop = Stop {
+            signal,
+            at: Instant::now(),
+            in_flight: self.inner.in_flight.load(Ordering::SeqCst),
+            served: self.inner.served.load(Ordering::SeqCst),
+        };
+        *self.inner.stop.lock().unwrap() = Some(stop);
+    }
+
+    pub fn log_summary(&self) {
+        let served = self.inner.served.load(Ordering::SeqCst);
+        let aborted = self.inner.aborted.load(Ordering::SeqCst);
+
+        match self.inner.stop.lock().unwrap().as_ref() {
+            Some(stop) => log::info!(
+                "Shutdown after {} complete in {:.2?}: {} request(s) in flight at signal, {} drained, {} aborted, {} served in total",
+                stop.signal,
+                stop.at.elapsed(),
+                stop.in_flight,
+                served - stop.served,
+                aborted,
+                served
+            ),
+            None => log::info!("Server stopped: {served} request(s) served, {aborted} aborted"),
+        }
+    }
+}
+
+/// Guard held for the lifetime of a request. Dropping it without calling
+/// [`InFlight::complete`] means the worker was torn down mid-request.
+pub struct InFlight {
+    inner: Arc<Inner>,
+    completed: bool,
+}
+
+impl InFlight {
+    pub fn complete(mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.inner.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if self.completed {
+            self.inner.served.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.inner.aborted.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Resolves on SIGTERM or SIGINT. Handed to `HttpServer::shutdown_signal`, which
+/// then stops accepting connections and gives workers `timeout` to drain.
+pub async fn signal(stats: RequestStats, timeout: Duration) {
+    let signal = wait_for_signal().await;
+    stats.mark_stop(signal);
+    log::info!(
+        "Received {signal}, no longer accepting connections; draining {} in-flight request(s) for up to {}s",
+        stats.inner.in_flight.load(Ordering::SeqCst),
+        timeout.as_secs()
+    );
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("could not listen for SIGTERM");
+    let mut interrupt = signal(SignalKind::interrupt()).expect("could not listen for SIGINT");
+
+    tokio::select! {
+        _ = terminate.recv() => "SIGTERM",
+        _ = interrupt.recv() => "SIGINT",
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("could not listen for ctrl-c");
+    "ctrl-c"
+}
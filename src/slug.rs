@@ -0,0 +1,69 @@
+use deunicode::deunicode;
+
+/// URL slug shared by `create_post` and the post routes: transliterated to
+/// ASCII, lowercased, with every run of other characters collapsed into a
+/// single hyphen. Apostrophes are dropped so "don't" becomes "dont".
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+
+    for c in deunicode(text).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if c == '\'' {
+            continue;
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+/// Levenshtein distance over chars, used to suggest posts for mistyped slugs.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_transliterates_unicode() {
+        assert_eq!(slugify("Grüße aus Köln"), "grusse-aus-koln");
+        assert_eq!(slugify("Café déjà vu"), "cafe-deja-vu");
+        assert_eq!(slugify("北京"), "bei-jing");
+    }
+
+    #[test]
+    fn slugify_collapses_punctuation() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  --Rust &  htmx--  "), "rust-htmx");
+        assert_eq!(slugify("Don't panic"), "dont-panic");
+        assert_eq!(slugify("C++ in 2024"), "c-in-2024");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn edit_distance_counts_changes() {
+        assert_eq!(edit_distance("rust", "rust"), 0);
+        assert_eq!(edit_distance("rust", "rusty"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Files with this many lines, over half of them outdated versions, are
+/// rewritten with only the latest ones when opened.
+const COMPACT_AFTER: usize = 1000;
+
+/// Something kept in a `Store`, told apart from other records by its id.
+pub trait Record: Serialize + DeserializeOwned + Clone {
+    fn id(&self) -> &str;
+}
+
+/// Records appended to a file one JSON object per line. Changing a record
+/// appends it again; the last line with an id wins when the file is read.
+pub struct Store<T> {
+    path: PathBuf,
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    file: File,
+    records: Vec<T>,
+    positions: HashMap<String, usize>,
+}
+
+impl<T> State<T>
+where
+    T: Record,
+{
+    fn insert(&mut self, record: T) {
+        match self.positions.get(record.id()) {
+            Some(&at) => self.records[at] = record,
+            None => {
+                self.positions
+                    .insert(record.id().to_string(), self.records.len());
+                self.records.push(record);
+            }
+        }
+    }
+}
+
+impl<T> Store<T>
+where
+    T: Record,
+{
+    /// Reads `path`, creating it and its directory if needed. Lines that do
+    /// not parse, such as one cut short by a crash, are skipped with a warning.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        // Appending after a partial line would corrupt the next record too
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+
+        let (records, lines) = parse(&path, &contents);
+        let mut state = State {
+            file,
+            records: Vec::new(),
+            positions: HashMap::new(),
+        };
+        for record in records {
+            state.insert(record);
+        }
+
+        if lines > COMPACT_AFTER && lines > 2 * state.records.len() {
+            state.file = compact(&path, &state.records)?;
+        }
+
+        Ok(Store {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Latest version of every record in `path` without opening it for
+    /// writing, for readers next to a server that owns the file. A missing
+    /// file holds no records.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<T>> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(parse(path, &contents).0),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes `record` to disk, replacing any earlier one with its id.
+    pub fn put(&self, record: T) -> io::Result<()> {
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.file.write_all(line.as_bytes())?;
+        state.file.sync_data()?;
+        state.insert(record);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<T> {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.positions.get(id).map(|&at| state.records[at].clone())
+    }
+
+    /// Latest version of every record, in the order they were first added.
+    pub fn all(&self) -> Vec<T> {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.records.clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Latest version of every record in `contents`, and how many lines they
+/// took up.
+fn parse<T: Record>(path: &Path, contents: &str) -> (Vec<T>, usize) {
+    let mut records: Vec<T> = Vec::new();
+    let mut positions = HashMap::new();
+    let mut lines = 0;
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        lines += 1;
+        match serde_json::from_str::<T>(line) {
+            Ok(record) => match positions.get(record.id()) {
+                Some(&at) => records[at] = record,
+                None => {
+                    positions.insert(record.id().to_string(), records.len());
+                    records.push(record);
+                }
+            },
+            Err(err) => log::warn!(
+                "{}:{}: skipping unreadable record: {err}",
+                path.display(),
+                number + 1
+            ),
+        }
+    }
+    (records, lines)
+}
+
+/// Replaces the file at `path` with one line per record and returns it
+/// ready for appending. The old file stays until the new one is complete.
+fn compact<T: Record>(path: &Path, records: &[T]) -> io::Result<File> {
+    let partial = path.with_extension("compacting");
+    let mut file = File::create(&partial)?;
+    for record in records {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+    }
+    file.sync_all()?;
+    fs::rename(&partial, path)?;
+    OpenOptions::new().append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        id: String,
+        text: String,
+    }
+
+    impl Record for Note {
+        fn id(&self) -> &str {
+            &self.id
+        }
+    }
+
+    fn note(id: &str, text: &str) -> Note {
+        Note {
+            id: id.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    /// A path in a fresh directory, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("focus-store-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            TempFile(dir.join("notes.jsonl"))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.0.parent().unwrap());
+        }
+    }
+
+    #[test]
+    fn last_write_wins_across_reopening() {
+        let file = TempFile::new("reload");
+        let store = Store::open(&file.0).unwrap();
+        store.put(note("a", "first")).unwrap();
+        store.put(note("b", "other")).unwrap();
+        store.put(note("a", "second")).unwrap();
+        assert_eq!(store.get("a"), Some(note("a", "second")));
+        assert_eq!(store.get("c"), None);
+        drop(store);
+
+        let store = Store::<Note>::open(&file.0).unwrap();
+        // First-added order, latest version
+        assert_eq!(store.all(), [note("a", "second"), note("b", "other")]);
+        assert_eq!(
+            Store::<Note>::read(&file.0).unwrap(),
+            [note("a", "second"), note("b", "other")]
+        );
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let file = TempFile::new("partial");
+        fs::create_dir_all(file.0.parent().unwrap()).unwrap();
+        fs::write(
+            &file.0,
+            "{\"id\":\"a\",\"text\":\"kept\"}\nnot json\n{\"id\":\"b\",\"te",
+        )
+        .unwrap();
+
+        let store = Store::<Note>::open(&file.0).unwrap();
+        assert_eq!(store.all(), [note("a", "kept")]);
+        // The cut-off line does not swallow the next record
+        store.put(note("c", "after")).unwrap();
+        drop(store);
+        assert_eq!(
+            Store::<Note>::read(&file.0).unwrap(),
+            [note("a", "kept"), note("c", "after")]
+        );
+    }
+
+    #[test]
+    fn mostly_outdated_files_are_compacted() {
+        let file = TempFile::new("compact");
+        let store = Store::open(&file.0).unwrap();
+        for version in 0..=COMPACT_AFTER {
+            store.put(note("a", &version.to_string())).unwrap();
+        }
+        store.put(note("b", "other")).unwrap();
+        drop(store);
+
+        let store = Store::<Note>::open(&file.0).unwrap();
+        assert_eq!(fs::read_to_string(&file.0).unwrap().lines().count(), 2);
+        store.put(note("b", "changed")).unwrap();
+        drop(store);
+        assert_eq!(
+            Store::<Note>::read(&file.0).unwrap(),
+            [note("a", &COMPACT_AFTER.to_string()), note("b", "changed")]
+        );
+    }
+
+    #[test]
+    fn reading_a_missing_file_finds_nothing() {
+        let file = TempFile::new("missing");
+        assert!(Store::<Note>::read(&file.0).unwrap().is_empty());
+        assert!(!file.0.exists());
+    }
+}
@@ -1,53 +1,288 @@
-use actix_web::{HttpResponse, Result as AwResult, get, web};
-use chrono::{DateTime, Utc};
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, error, get, web};
+use chrono::{DateTime, NaiveDate, Utc};
 use include_dir::{Dir, include_dir};
 use lazy_static::lazy_static;
-use markdown::{self, CompileOptions, Options};
+use markdown::mdast::Node;
+use markdown::{self, ParseOptions};
 use maud::{Markup, PreEscaped, html};
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+
+use super::authors::{self, Author};
+use super::feed::{self, Entry, Feed};
+use super::front_matter::{self, FrontMatter};
+use super::related::{self, Document};
+use super::render;
+use super::{Locale, analytics, comments, i18n, navbar, newsletter, reactions, webmentions};
+use crate::check::{self, Diagnostic};
+use crate::config::{self, Server};
+use crate::routes::{images, redirects};
+use crate::security::Nonce;
+use crate::{dates, math, sanitize, slug};
 
 static POSTS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/posts");
 
+lazy_static! {
+    static ref RELATIVE_LINK: Regex =
+        Regex::new(r#"(?i)\b(href|src)\s*=\s*("([^"]*)"|'([^']*)')"#).unwrap();
+}
+
 #[derive(Debug, Clone)]
 struct Post {
     slug: String,
+    title: String,
     content: String,
+    excerpt: String,
+    word_count: usize,
     date: DateTime<Utc>,
+    /// Last significant change, shown next to the date and in feeds.
+    updated: Option<DateTime<Utc>>,
+    aliases: Vec<String>,
+    /// Author ids, in byline order.
+    authors: Vec<String>,
+    /// Slugified topics, compared when looking for related posts.
+    tags: Vec<String>,
+    /// Series name as written in front matter, and the post's place in it.
+    series: Option<String>,
+    series_order: Option<u32>,
+    /// Directory under `posts/` for bundles, which keep files next to `index.md`.
+    bundle: Option<String>,
+    /// Source file and the line its markdown body starts on, for diagnostics.
+    file: String,
+    body_line: usize,
 }
 
+const WORDS_PER_MINUTE: usize = 200;
+const EXCERPT_WORDS: usize = 50;
+const MORE_MARKER: &str = "<!-- more -->";
+const BUNDLE_INDEX: &str = "index.md";
+
 impl Post {
-    pub fn human_date(&self) -> String {
-        self.date.format("%B %d, %Y").to_string()
+    /// Latest of the publication and update time.
+    pub fn last_modified(&self) -> DateTime<Utc> {
+        self.updated.unwrap_or(self.date).max(self.date)
+    }
+
+    pub fn reading_minutes(&self) -> usize {
+        self.word_count.div_ceil(WORDS_PER_MINUTE).max(1)
+    }
+
+    /// Base for the relative links of a bundle post.
+    fn base_url(&self) -> Option<String> {
+        self.bundle
+            .as_ref()
+            .map(|_| format!("/post/{}/", self.slug))
     }
 }
 
-fn post_to_html(post: Post, current_index: usize) -> maud::Markup {
-    let as_html = markdown::to_html_with_options(
-        &post.content,
-        &Options {
-            compile: CompileOptions {
-                allow_dangerous_html: true,
-                allow_dangerous_protocol: false,
-                ..CompileOptions::default()
-            },
-            ..Options::gfm()
-        },
-    )
-    .unwrap();
+/// "By Jane, Joe and Ann" with links to the author pages. Ids missing from
+/// the registry are reported by `focus check` and shown as written.
+fn byline(post: &Post, lang: &str) -> Markup {
+    let strings = i18n::strings(lang);
+    let count = post.authors.len();
+    html! {
+        (strings.by) " "
+        @for (at, id) in post.authors.iter().enumerate() {
+            @if at > 0 {
+                @if at + 1 == count { " " (strings.and) " " } @else { ", " }
+            }
+            @match authors::get(id) {
+                Some(author) => a href={"/authors/" (id)} class="link link-hover" { (author.name) },
+                None => (id),
+            }
+        }
+    }
+}
+
+/// A date in the site's time zone, with the exact time for machines.
+pub(super) fn time(instant: DateTime<Utc>, lang: &str) -> Markup {
+    let local = dates::local(instant);
+    html! {
+        time datetime=(local.to_rfc3339()) { (i18n::strings(lang).date(local)) }
+    }
+}
+
+fn post_meta(post: &Post, lang: &str) -> Markup {
+    let strings = i18n::strings(lang);
+    html! {
+        @if !post.authors.is_empty() {
+            (byline(post, lang)) " · "
+        }
+        (time(post.date, lang)) " · "
+        @if let Some(updated) = post.updated {
+            (strings.updated_on) " " (time(updated, lang)) " · "
+        }
+        span title={(post.word_count) " " (strings.words)} { (post.reading_minutes()) " " (strings.min_read) }
+    }
+}
+
+fn collect_text(node: &Node, out: &mut String) {
+    match node {
+        Node::Text(text) => out.push_str(&text.value),
+        Node::InlineCode(code) => out.push_str(&code.value),
+        Node::Code(_) | Node::Html(_) | Node::Yaml(_) | Node::Toml(_) => {}
+        Node::Break(_) => out.push(' '),
+        _ => {
+            for child in node.children().into_iter().flatten() {
+                collect_text(child, out);
+            }
+            if matches!(
+                node,
+                Node::Paragraph(_) | Node::Heading(_) | Node::ListItem(_) | Node::TableCell(_)
+            ) {
+                out.push(' ');
+            }
+        }
+    }
+}
+
+/// Plain text of a markdown document without its title and code blocks.
+fn plain_text(content: &str) -> String {
+    let Ok(root) = markdown::to_mdast(content, &ParseOptions::gfm()) else {
+        return String::new();
+    };
+
+    let mut out = String::new();
+    let mut title_skipped = false;
+    for node in root.children().into_iter().flatten() {
+        if !title_skipped && matches!(node, Node::Heading(h) if h.depth == 1) {
+            title_skipped = true;
+            continue;
+        }
+        collect_text(node, &mut out);
+    }
+
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `create_post` writes a subtitle line directly between the title and a `---` rule.
+fn extract_subtitle(content: &str) -> Option<String> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty());
+    lines.next()?.strip_prefix("# ")?;
+
+    let mut subtitle = Vec::new();
+    for line in lines {
+        if line == "---" {
+            return (!subtitle.is_empty()).then(|| plain_text(&subtitle.join("\n")));
+        }
+        if line.is_empty() {
+            return None;
+        }
+        subtitle.push(line);
+    }
+    None
+}
+
+/// Everything above `<!-- more -->`, else the subtitle, else the first words.
+fn extract_excerpt(content: &str) -> String {
+    if let Some((before, _)) = content.split_once(MORE_MARKER) {
+        return plain_text(before);
+    }
+    if let Some(subtitle) = extract_subtitle(content) {
+        return subtitle;
+    }
+
+    let text = plain_text(content);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() <= EXCERPT_WORDS {
+        text
+    } else {
+        format!("{}…", words[..EXCERPT_WORDS].join(" "))
+    }
+}
+
+/// Text of the first top-level `#` heading.
+fn extract_title(content: &str) -> Option<String> {
+    let root = markdown::to_mdast(content, &ParseOptions::gfm()).ok()?;
+    let heading = root
+        .children()?
+        .iter()
+        .find(|node| matches!(node, Node::Heading(h) if h.depth == 1))?;
+
+    let mut title = String::new();
+    collect_text(heading, &mut title);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Points relative `href` and `src` attributes at `base`, so bundle files
+/// resolve from `/post/{slug}` whatever URL the post is shown under.
+fn rebase_relative_links(html: &str, base: &str) -> String {
+    RELATIVE_LINK
+        .replace_all(html, |caps: &Captures| {
+            let url = caps.get(3).or(caps.get(4)).map_or("", |m| m.as_str());
+            let relative = !url.is_empty()
+                && !url.starts_with(['/', '#', '?'])
+                && !url
+                    .split('/')
+                    .next()
+                    .is_some_and(|first| first.contains(':'));
+            if relative {
+                let url = url.trim_start_matches("./");
+                format!("{}=\"{base}{url}\"", &caps[1])
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+fn render_markdown(content: &str, base: Option<&str>) -> Result<String, String> {
+    let html = render::to_html(content)?;
+    let html = match base {
+        Some(base) => rebase_relative_links(&html, base),
+        None => html,
+    };
+
+    Ok(images::rewrite(&math::rewrite(&sanitize::clean(&html))))
+}
+
+fn render_error(err: String) -> actix_web::Error {
+    log::error!("Could not render markdown: {err}");
+    error::ErrorInternalServerError("Could not render page")
+}
+
+fn post_to_html(post: Post, current_index: usize) -> AwResult<Markup> {
+    let lang = i18n::default_language();
+    let strings = i18n::strings(lang);
+    let as_html =
+        render_markdown(&post.content, post.base_url().as_deref()).map_err(render_error)?;
 
     let next_index = current_index + 1;
     let next_url = format!("/posts/{next_index}");
     let has_next = next_index < POSTS.len();
     let has_prev = current_index > 0;
 
-    maud::html! {
+    Ok(maud::html! {
         div id="post" class="flex flex-col h-full" {
             div class="flex-1 overflow-y-auto space-y-6 pb-6" {
                 div class="prose" {
-                    p class="post-date" {(post.human_date())}
+                    p class="post-date" {(post_meta(&post, lang))}
                 }
 
                 div class="divider" {}
                 (PreEscaped(as_html))
+                @if let Some(series) = series_box(current_index, lang) {
+                    (series)
+                }
+                @if let Some(reactions) = reactions::placeholder(&post.slug, lang) {
+                    (reactions)
+                }
+                @if let Some(related) = related_posts(current_index, lang) {
+                    (related)
+                }
+                @if let Some(signup) = newsletter::form(&post.slug, lang) {
+                    (signup)
+                }
+                @if let Some(mentions) = webmentions::placeholder(&post.slug, lang) {
+                    (mentions)
+                }
+                @if let Some(comments) = comments::placeholder(&post.slug, lang) {
+                    (comments)
+                }
             }
 
             div class="mt-6 flex justify-between sticky bottom-0 bg-base-100 py-4 border-t border-base-200" {
@@ -57,7 +292,7 @@ fn post_to_html(post: Post, current_index: usize) -> maud::Markup {
                         hx-get={"/posts/" (current_index - 1)}
                         hx-target="#post"
                         hx-swap="outerHTML" {
-                        "Previous Post"
+                        (strings.previous_post)
                     }
                 } @else {
                     div {}
@@ -68,137 +303,448 @@ fn post_to_html(post: Post, current_index: usize) -> maud::Markup {
                         hx-get=(next_url)
                         hx-target="#post"
                         hx-swap="outerHTML" {
-                        "Next Post"
+                        (strings.next_post)
                     }
                 }
             }
         }
-    }
+    })
 }
 
-fn post_to_html_with_slug(post: Post, current_index: usize) -> maud::Markup {
-    let as_html = markdown::to_html_with_options(
-        &post.content,
-        &Options {
-            compile: CompileOptions {
-                allow_dangerous_html: true,
-                allow_dangerous_protocol: false,
-                ..CompileOptions::default()
-            },
-            ..Options::gfm()
-        },
-    )
-    .unwrap();
+fn post_to_html_with_slug(post: &Post, current_index: usize, lang: &str) -> AwResult<Markup> {
+    let strings = i18n::strings(lang);
+    let as_html =
+        render_markdown(&post.content, post.base_url().as_deref()).map_err(render_error)?;
 
     let (prev_slug, next_slug) = get_adjacent_post_slugs(current_index);
 
-    maud::html! {
+    Ok(maud::html! {
         div id="post" class="flex flex-col h-full" {
             div class="flex-1 overflow-y-auto space-y-6 pb-6" {
-
                 (PreEscaped(as_html))
+                @if let Some(series) = series_box(current_index, lang) {
+                    (series)
+                }
+                @if let Some(reactions) = reactions::placeholder(&post.slug, lang) {
+                    (reactions)
+                }
+                @if let Some(related) = related_posts(current_index, lang) {
+                    (related)
+                }
+                @if let Some(signup) = newsletter::form(&post.slug, lang) {
+                    (signup)
+                }
+                @if let Some(mentions) = webmentions::placeholder(&post.slug, lang) {
+                    (mentions)
+                }
+                @if let Some(comments) = comments::placeholder(&post.slug, lang) {
+                    (comments)
+                }
             }
 
             div class="mt-6 flex justify-between sticky bottom-0 bg-base-100 py-4 border-t border-base-200" {
                 @if let Some(prev) = prev_slug {
                     button
                         class="btn btn-primary"
-                        hx-get={"/post/" (prev)}
+                        hx-get=(post_url(&prev, lang))
                         hx-target="#post"
                         hx-swap="outerHTML"
                         hx-push-url="true" {
-                        "Previous Post"
+                        (strings.previous_post)
                     }
                 } @else {
                     div {}
                 }
                 div {
-                    p class="post-date" {(post.human_date())}
+                    p class="post-date" {(post_meta(post, lang))}
                 }
                 @if let Some(next) = next_slug {
                     button
                         class="btn btn-primary"
-                        hx-get={"/post/" (next)}
+                        hx-get=(post_url(&next, lang))
                         hx-target="#post"
                         hx-swap="outerHTML"
                         hx-push-url="true" {
-                        "Next Post"
+                        (strings.next_post)
                     }
                 } @else {
                     div {}
                 }
             }
         }
+    })
+}
+
+/// Splits `YYYY-MM-DD-name` into its date and name. Files without a date
+/// prefix keep their whole stem as the name.
+fn split_filename(stem: &str) -> (Option<NaiveDate>, &str) {
+    let date = stem
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+
+    match (date, stem.get(10..)) {
+        (Some(date), Some(rest)) => (Some(date), rest.trim_start_matches(['-', '_', ' '])),
+        _ => (None, stem),
     }
 }
 
-fn extract_slug_from_filename(filename: &str) -> String {
-    // Parse slug from filename (format: YYYY-MM-DD-slug.md)
-    if filename.len() > 11 {
-        // Skip the date part (YYYY-MM-DD-) and get the slug
-        let slug_part = &filename[11..];
-        slug_part.to_string()
+fn extract_slug_from_filename(stem: &str) -> String {
+    let (_, name) = split_filename(stem);
+    let slug = slug::slugify(name);
+    if slug.is_empty() {
+        slug::slugify(stem)
     } else {
-        // Fallback: generate slug from filename
-        filename
-            .to_lowercase()
-            .chars()
-            .map(|c| {
-                if c.is_alphanumeric() {
-                    c
-                } else if c.is_whitespace() {
-                    '-'
-                } else {
-                    '\0'
-                }
-            })
-            .filter(|&c| c != '\0')
-            .collect::<String>()
+        slug
     }
 }
 
 use std::collections::HashMap;
 
-lazy_static! {
-    static ref POSTS: Vec<Post> = {
-        let mut posts: Vec<Post> = POSTS_DIR
-            .files()
-            .filter_map(|file| {
-                let path = file.path();
-
-                // Only process markdown files
-                if path.extension().is_none_or(|ext| ext != "md") {
-                    return None;
-                }
+/// `name.de.md`, or `index.de.md` in a bundle, translates `name.md` into
+/// one of the configured languages. Returns the original and the language.
+fn translation(path: &Path) -> Option<(PathBuf, &str)> {
+    let (name, lang) = path.file_stem()?.to_str()?.rsplit_once('.')?;
+    i18n::is_translation(lang).then(|| (path.with_file_name(format!("{name}.md")), lang))
+}
 
-                // Parse date from filename (format: YYYY-MM-DD-title.md)
-                let filename = path.file_stem()?.to_str()?;
-                let date = if filename.len() >= 10 {
-                    let date_str = &filename[..10];
-                    chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-                        .ok()
-                        .and_then(|naive_date| {
-                            naive_date.and_hms_opt(0, 0, 0)
-                                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
-                        })
-                        .unwrap_or_else(Utc::now)
-                } else {
-                    Utc::now()
-                };
+fn is_bundle_index(path: &Path) -> bool {
+    path.ends_with(BUNDLE_INDEX)
+        || translation(path).is_some_and(|(original, _)| original.ends_with(BUNDLE_INDEX))
+}
 
-                let content = file.contents_utf8().unwrap_or("");
-                let slug = extract_slug_from_filename(filename);
+/// Single `.md` files plus the `index.md` of every bundle directory, and
+/// their translations.
+fn markdown_files() -> impl Iterator<Item = (&'static Path, &'static [u8])> {
+    let files = POSTS_DIR
+        .files()
+        .filter(|file| file.path().extension().is_some_and(|ext| ext == "md"));
+    let bundles = POSTS_DIR
+        .dirs()
+        .flat_map(|dir| dir.files())
+        .filter(|file| is_bundle_index(file.path()));
 
-                Some(Post {
-                    slug,
-                    content: content.to_string(),
-                    date,
-                })
-            })
+    files
+        .chain(bundles)
+        .map(|file| (file.path(), file.contents()))
+}
+
+/// A non-post file embedded from `posts/`, such as an image next to a post.
+pub fn post_file(path: &str) -> Option<&'static [u8]> {
+    POSTS_DIR.get_file(path).map(|file| file.contents())
+}
+
+/// Parses one post file. Problems that still leave a usable post, like a
+/// missing title, are reported next to it; the server logs them at startup.
+fn parse_post(path: &Path, raw: &[u8]) -> (Option<Post>, Vec<Diagnostic>) {
+    let file = format!("posts/{}", path.display());
+    let mut diagnostics = Vec::new();
+
+    let Ok(raw) = std::str::from_utf8(raw) else {
+        diagnostics.push(Diagnostic::error(&file, None, "file is not valid UTF-8"));
+        return (None, diagnostics);
+    };
+    // Bundles take their date and slug from the directory name, translations
+    // from the file they translate
+    let source = translation(path).map_or_else(|| path.to_path_buf(), |(original, _)| original);
+    let bundle = source
+        .parent()
+        .filter(|parent| source.ends_with(BUNDLE_INDEX) && !parent.as_os_str().is_empty());
+    let name = match bundle {
+        Some(dir) => dir.file_name(),
+        None => source.file_stem(),
+    };
+    let Some(filename) = name.and_then(|name| name.to_str()) else {
+        diagnostics.push(Diagnostic::error(
+            &file,
+            None,
+            "file name is not valid UTF-8",
+        ));
+        return (None, diagnostics);
+    };
+
+    // Parse date from filename (format: YYYY-MM-DD-title.md)
+    let (file_date, name) = split_filename(filename);
+
+    let (front_matter, content) = front_matter::split(raw);
+    // Line of the body's first line within the file
+    let body_line = raw[..raw.len() - content.len()].lines().count() + 1;
+    let front_matter = match front_matter.map(front_matter::parse).transpose() {
+        Ok(front_matter) => front_matter.unwrap_or_default(),
+        Err(err) => {
+            // +1 for the opening `---`
+            let line = err.location().map(|location| location.line() + 1);
+            diagnostics.push(Diagnostic::error(
+                &file,
+                line,
+                format!("invalid front matter: {err}"),
+            ));
+            FrontMatter::default()
+        }
+    };
+
+    let mut parse_date = |value: Option<&String>, field: &str| match value
+        .map(|value| dates::parse(value))
+        .transpose()
+    {
+        Ok(date) => date,
+        Err(err) => {
+            diagnostics.push(Diagnostic::error(
+                &file,
+                None,
+                format!("invalid `{field}`: {err}"),
+            ));
+            None
+        }
+    };
+    let published = parse_date(front_matter.date.as_ref(), "date");
+    let updated = parse_date(front_matter.updated.as_ref(), "updated");
+
+    if file_date.is_none() && published.is_none() {
+        diagnostics.push(Diagnostic::error(
+            &file,
+            None,
+            "file name does not start with a YYYY-MM-DD date and there is no `date:`",
+        ));
+    }
+    if let (Some(day), Some(published)) = (file_date, published)
+        && dates::local(published).date_naive() != day
+    {
+        diagnostics.push(Diagnostic::warning(
+            &file,
+            None,
+            format!(
+                "`date` falls on {}, the file name says {day}",
+                dates::local(published).date_naive()
+            ),
+        ));
+    }
+    // Dates from the file name start at midnight where the site is
+    let date = published
+        .or_else(|| file_date.and_then(dates::start_of_day))
+        .unwrap_or_else(Utc::now);
+    if updated.is_some_and(|updated| updated < date) {
+        diagnostics.push(Diagnostic::warning(
+            &file,
+            None,
+            "`updated` is before the post's date",
+        ));
+    }
+
+    let slug = front_matter
+        .slug
+        .as_deref()
+        .map(slug::slugify)
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| extract_slug_from_filename(filename));
+    let title = match front_matter.title.or_else(|| extract_title(content)) {
+        Some(title) => title,
+        None => {
+            diagnostics.push(Diagnostic::error(
+                &file,
+                Some(body_line),
+                "missing title, add a `# Title` heading or `title:` front matter",
+            ));
+            name.replace(['-', '_'], " ")
+        }
+    };
+    let excerpt = extract_excerpt(content);
+    let word_count = plain_text(content).split_whitespace().count();
+    if word_count == 0 {
+        diagnostics.push(Diagnostic::error(
+            &file,
+            Some(body_line),
+            "post has no content",
+        ));
+    }
+
+    let post = Post {
+        slug,
+        title,
+        content: content.to_string(),
+        excerpt,
+        word_count,
+        date,
+        updated,
+        aliases: front_matter.aliases,
+        authors: front_matter.authors,
+        tags: front_matter
+            .tags
+            .iter()
+            .map(|tag| slug::slugify(tag))
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        series: front_matter
+            .series
+            .map(|series| series.trim().to_string())
+            .filter(|series| !series.is_empty()),
+        series_order: front_matter.series_order,
+        bundle: bundle.map(|dir| dir.display().to_string()),
+        file,
+        body_line,
+    };
+    (Some(post), diagnostics)
+}
+
+/// Validates every post file, including problems between posts such as
+/// duplicate slugs and links to posts that do not exist.
+pub fn check_posts() -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut posts = Vec::new();
+    let mut translations = Vec::new();
+
+    for (path, raw) in markdown_files() {
+        let (post, problems) = parse_post(path, raw);
+        diagnostics.extend(problems);
+        match translation(path) {
+            Some((original, _)) => translations.extend(post.map(|post| (original, post))),
+            None => posts.extend(post),
+        }
+    }
+
+    for (original, translation) in &translations {
+        let original = format!("posts/{}", original.display());
+        if !posts.iter().any(|post| post.file == original) {
+            diagnostics.push(Diagnostic::error(
+                &translation.file,
+                None,
+                format!("translates {original}, which does not exist"),
+            ));
+        }
+    }
+
+    let mut seen: HashMap<&str, &Post> = HashMap::new();
+    for post in &posts {
+        if let Some(first) = seen.insert(&post.slug, post) {
+            diagnostics.push(Diagnostic::error(
+                &post.file,
+                None,
+                format!("slug `{}` is also used by {}", post.slug, first.file),
+            ));
+        }
+    }
+
+    for post in &posts {
+        for id in post.authors.iter().filter(|id| authors::get(id).is_none()) {
+            diagnostics.push(Diagnostic::error(
+                &post.file,
+                None,
+                format!("unknown author `{id}`, add authors/{id}.yaml"),
+            ));
+        }
+    }
+
+    let mut series: HashMap<String, Vec<&Post>> = HashMap::new();
+    for post in &posts {
+        match &post.series {
+            Some(name) => series.entry(slug::slugify(name)).or_default().push(post),
+            None if post.series_order.is_some() => diagnostics.push(Diagnostic::warning(
+                &post.file,
+                None,
+                "`series_order` has no effect without `series`",
+            )),
+            None => {}
+        }
+    }
+    for parts in series.values_mut() {
+        parts.sort_by_key(|post| series_key(post));
+        for pair in parts.windows(2) {
+            let (first, post) = (pair[0], pair[1]);
+            if post.series != first.series {
+                diagnostics.push(Diagnostic::warning(
+                    &post.file,
+                    None,
+                    format!(
+                        "series `{}` is spelled `{}` in {}",
+                        post.series.as_deref().unwrap_or_default(),
+                        first.series.as_deref().unwrap_or_default(),
+                        first.file
+                    ),
+                ));
+            }
+            if let Some(order) = post
+                .series_order
+                .filter(|_| post.series_order == first.series_order)
+            {
+                diagnostics.push(Diagnostic::error(
+                    &post.file,
+                    None,
+                    format!("`series_order: {order}` is also used by {}", first.file),
+                ));
+            }
+        }
+    }
+
+    for post in posts
+        .iter()
+        .chain(translations.iter().map(|(_, post)| post))
+    {
+        diagnostics.extend(check::check_links(
+            &post.file,
+            post.body_line,
+            &post.content,
+            post.bundle.as_deref(),
+            |slug| seen.contains_key(slug),
+        ));
+        diagnostics.extend(check::check_math(&post.file, post.body_line, &post.content));
+        diagnostics.extend(check::check_shortcodes(
+            &post.file,
+            post.body_line,
+            &post.content,
+        ));
+    }
+
+    diagnostics
+}
+
+/// Builds the translation, series and related posts indexes at startup
+/// rather than during the first request.
+pub fn prepare() {
+    lazy_static::initialize(&TRANSLATIONS);
+    lazy_static::initialize(&SERIES);
+    lazy_static::initialize(&RELATED);
+}
+
+pub fn post_count() -> usize {
+    POSTS.len()
+}
+
+pub fn has_post(slug: &str) -> bool {
+    SLUG_TO_INDEX.contains_key(slug)
+}
+
+pub fn post_title(slug: &str) -> Option<&'static str> {
+    let index = *SLUG_TO_INDEX.get(slug)?;
+    Some(POSTS[index].title.as_str())
+}
+
+/// Markdown body of the post at `slug`.
+pub fn post_content(slug: &str) -> Option<&'static str> {
+    let index = *SLUG_TO_INDEX.get(slug)?;
+    Some(POSTS[index].content.as_str())
+}
+
+/// Slug of the post a path such as `/post/{slug}` or `/{lang}/post/{slug}`
+/// shows, in any language.
+pub fn slug_for_path(path: &str) -> Option<String> {
+    let path = path.trim_end_matches('/');
+    let slug = match path.trim_start_matches('/').split('/').collect::<Vec<_>>()[..] {
+        ["post", slug] => slug,
+        [lang, "post", slug] if i18n::is_translation(lang) => slug,
+        _ => return None,
+    };
+    let slug = urlencoding::decode(slug).ok()?;
+    has_post(&slug).then(|| slug.into_owned())
+}
+
+lazy_static! {
+    static ref POSTS: Vec<Post> = {
+        let mut posts: Vec<Post> = markdown_files()
+            .filter(|(path, _)| translation(path).is_none())
+            .filter_map(|(path, raw)| parse_post(path, raw).0)
             .collect();
 
         // Sort by date (newest first)
-        posts.sort_by(|a, b| b.date.cmp(&a.date));
+        posts.sort_by_key(|post| std::cmp::Reverse(post.date));
         posts
     };
 }
@@ -207,36 +753,205 @@ lazy_static! {
     static ref SLUG_TO_INDEX: HashMap<String, usize> = {
         let mut map = HashMap::new();
         for (index, post) in POSTS.iter().enumerate() {
-            map.insert(post.slug.clone(), index);
+            // Duplicates are reported by `check_posts`, the newest post wins
+            map.entry(post.slug.clone()).or_insert(index);
         }
         map
     };
 }
 
 lazy_static! {
-    static ref README_HTML: String = {
+    /// Translated posts by language and slug. Everything but the text comes
+    /// from the original, so slugs, dates and series stay in sync.
+    static ref TRANSLATIONS: HashMap<(String, String), Post> = markdown_files()
+        .filter_map(|(path, raw)| {
+            let (original, lang) = translation(path)?;
+            let original = format!("posts/{}", original.display());
+            let post = POSTS.iter().find(|post| post.file == original)?;
+            let translated = parse_post(path, raw).0?;
+            let translated = Post {
+                title: translated.title,
+                content: translated.content,
+                excerpt: translated.excerpt,
+                word_count: translated.word_count,
+                updated: translated.updated.or(post.updated),
+                file: translated.file,
+                body_line: translated.body_line,
+                ..post.clone()
+            };
+            Some(((lang.to_string(), post.slug.clone()), translated))
+        })
+        .collect();
+}
+
+lazy_static! {
+    /// Indices of the posts related to each post, most related first.
+    static ref RELATED: Vec<Vec<usize>> = {
+        let texts: Vec<String> = POSTS
+            .iter()
+            .map(|post| format!("{} {}", post.title, plain_text(&post.content)))
+            .collect();
+        let documents: Vec<Document> = POSTS
+            .iter()
+            .zip(&texts)
+            .map(|(post, text)| Document {
+                tags: &post.tags,
+                text,
+            })
+            .collect();
+        related::rank(&documents, config::get().related_posts())
+    };
+}
+
+/// A series with its parts in reading order, as indices into `POSTS`.
+struct Series {
+    name: String,
+    parts: Vec<usize>,
+}
+
+/// Reading order of a series: `series_order` first, then by date.
+fn series_key(post: &Post) -> (u32, DateTime<Utc>, &str) {
+    (
+        post.series_order.unwrap_or(u32::MAX),
+        post.date,
+        post.slug.as_str(),
+    )
+}
+
+lazy_static! {
+    /// Series by the slug of their name, which is also their URL.
+    static ref SERIES: HashMap<String, Series> = {
+        let mut series: HashMap<String, Series> = HashMap::new();
+        for (index, post) in POSTS.iter().enumerate() {
+            let Some(name) = &post.series else {
+                continue;
+            };
+            series
+                .entry(slug::slugify(name))
+                .or_insert_with(|| Series {
+                    name: name.clone(),
+                    parts: Vec::new(),
+                })
+                .parts
+                .push(index);
+        }
+        for series in series.values_mut() {
+            series.parts.sort_by_key(|&index| series_key(&POSTS[index]));
+        }
+        series
+    };
+}
+
+lazy_static! {
+    static ref README_HTML: Result<String, String> = {
         let markdown_content = include_str!("../../README.md");
-        let html_output = markdown::to_html_with_options(
-            markdown_content,
-            &Options {
-                compile: CompileOptions {
-                    allow_dangerous_html: true,
-                    allow_dangerous_protocol: false,
-                    ..CompileOptions::default()
-                },
-                ..Options::gfm()
-            },
-        )
-        .unwrap();
-
-        format!("<div class=\"space-y-6\">{html_output}</div>")
+        render_markdown(markdown_content, None)
+            .map(|html_output| format!("<div class=\"space-y-6\">{html_output}</div>"))
     };
 }
 
-pub fn readme() -> Markup {
-    html! {
-         (PreEscaped(&*README_HTML))
-    }
+pub fn readme() -> AwResult<Markup> {
+    let html_output = README_HTML.clone().map_err(render_error)?;
+    Ok(html! {
+         (PreEscaped(html_output))
+    })
+}
+
+/// `(alias path, canonical path)` for every alias declared in front matter.
+/// Bare aliases are old slugs and live under `/post/`.
+pub fn post_aliases() -> Vec<(String, String)> {
+    POSTS
+        .iter()
+        .flat_map(|post| {
+            post.aliases.iter().map(|alias| {
+                let from = if alias.starts_with('/') {
+                    alias.clone()
+                } else {
+                    format!("/post/{alias}")
+                };
+                (from, format!("/post/{}", post.slug))
+            })
+        })
+        .collect()
+}
+
+/// Every page worth indexing with the time it last changed, if known.
+pub fn sitemap_entries() -> Vec<(String, Option<DateTime<Utc>>)> {
+    let newest = POSTS.iter().map(Post::last_modified).max();
+    let mut entries = vec![("/".to_string(), newest), ("/about".to_string(), None)];
+
+    entries.extend(
+        POSTS
+            .iter()
+            .map(|post| (format!("/post/{}", post.slug), Some(post.last_modified()))),
+    );
+    let mut translations: Vec<_> = TRANSLATIONS
+        .iter()
+        .map(|((lang, slug), post)| (format!("/{lang}/post/{slug}"), Some(post.last_modified())))
+        .collect();
+    translations.sort();
+    entries.extend(translations);
+
+    let mut series: Vec<_> = SERIES
+        .iter()
+        .map(|(slug, series)| {
+            let changed = series
+                .parts
+                .iter()
+                .map(|&index| POSTS[index].last_modified())
+                .max();
+            (format!("/series/{slug}"), changed)
+        })
+        .collect();
+    series.sort();
+    entries.extend(series);
+
+    entries.extend(authors::all().filter_map(|author| {
+        let changed = author_posts(&author.id).map(Post::last_modified).max()?;
+        Some((format!("/authors/{}", author.id), Some(changed)))
+    }));
+    entries
+}
+
+/// `(slug, title, date, excerpt)` of the posts published after `since` and
+/// no later than `until`, oldest first.
+pub fn posts_between(
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<(&'static str, &'static str, DateTime<Utc>, &'static str)> {
+    POSTS
+        .iter()
+        .rev()
+        .filter(|post| post.date > since && post.date <= until)
+        .map(|post| {
+            (
+                post.slug.as_str(),
+                post.title.as_str(),
+                post.date,
+                post.excerpt.as_str(),
+            )
+        })
+        .collect()
+}
+
+pub fn post_paths() -> Vec<String> {
+    POSTS
+        .iter()
+        .map(|post| format!("/post/{}", post.slug))
+        .collect()
+}
+
+/// `(file, first body line, body)` of every post, for `focus check --links`.
+pub fn post_sources() -> impl Iterator<Item = (&'static str, usize, &'static str)> {
+    POSTS
+        .iter()
+        .map(|post| (post.file.as_str(), post.body_line, post.content.as_str()))
+}
+
+/// `posts/...` source of a file in the bundle of the post at `slug`.
+pub fn bundle_source(slug: &str, file: &str) -> Option<String> {
+    let bundle = POSTS.get(*SLUG_TO_INDEX.get(slug)?)?.bundle.as_ref()?;
+    Some(format!("posts/{bundle}/{file}"))
 }
 
 fn get_post(page: usize) -> Option<Post> {
@@ -261,13 +976,359 @@ fn get_adjacent_post_slugs(current_index: usize) -> (Option<String>, Option<Stri
     (prev_slug, next_slug)
 }
 
+/// The series of the post at `index`, its slug and the post's position in it.
+fn get_series(index: usize) -> Option<(&'static str, &'static Series, usize)> {
+    let name = POSTS.get(index)?.series.as_deref()?;
+    let (slug, series) = SERIES.get_key_value(&slug::slugify(name))?;
+    let position = series.parts.iter().position(|&part| part == index)?;
+    Some((slug, series, position))
+}
+
+/// URL of a post in `lang`, or in the default language if it has no
+/// translation.
+pub(super) fn post_url(slug: &str, lang: &str) -> String {
+    if TRANSLATIONS.contains_key(&(lang.to_string(), slug.to_string())) {
+        format!("/{lang}/post/{slug}")
+    } else {
+        format!("/post/{slug}")
+    }
+}
+
+/// URL of a series page in `lang`.
+fn series_url(slug: &str, lang: &str) -> String {
+    if lang == i18n::default_language() {
+        format!("/series/{slug}")
+    } else {
+        format!("/{lang}/series/{slug}")
+    }
+}
+
+/// The post at `index` in `lang` where translated.
+fn localized_post(index: usize, lang: &str) -> &'static Post {
+    let post = &POSTS[index];
+    TRANSLATIONS
+        .get(&(lang.to_string(), post.slug.clone()))
+        .unwrap_or(post)
+}
+
+/// All parts of the post's series with the current one highlighted, and
+/// links to the parts before and after it.
+fn series_box(index: usize, lang: &str) -> Option<Markup> {
+    let strings = i18n::strings(lang);
+    let (slug, series, position) = get_series(index)?;
+    let part = |position: usize| localized_post(series.parts[position], lang);
+    let prev = position.checked_sub(1).map(part);
+    let next = series.parts.get(position + 1).map(|_| part(position + 1));
+
+    Some(html! {
+        nav class="card bg-base-200" aria-label="Series" {
+            div class="card-body gap-3" {
+                p class="text-sm text-base-content/60" {
+                    (strings.part) " " (position + 1) " " (strings.of) " " (series.parts.len()) " " (strings.in_series) " "
+                    a href=(series_url(slug, lang)) class="link" { (series.name) }
+                }
+                ol class="list-decimal list-inside space-y-1" {
+                    @for (at, &part) in series.parts.iter().enumerate() {
+                        @let post = localized_post(part, lang);
+                        @if at == position {
+                            li class="font-semibold" aria-current="page" { (post.title) }
+                        } @else {
+                            li { a href=(post_url(&post.slug, lang)) class="link link-hover" { (post.title) } }
+                        }
+                    }
+                }
+                div class="flex justify-between gap-4 text-sm" {
+                    @if let Some(prev) = prev {
+                        a href=(post_url(&prev.slug, lang)) class="link" { "← " (strings.part) " " (position) ": " (prev.title) }
+                    } @else {
+                        span {}
+                    }
+                    @if let Some(next) = next {
+                        a href=(post_url(&next.slug, lang)) class="link text-right" { (strings.part) " " (position + 2) ": " (next.title) " →" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn related_posts(index: usize, lang: &str) -> Option<Markup> {
+    let related = RELATED.get(index).filter(|related| !related.is_empty())?;
+    Some(html! {
+        section class="space-y-3" {
+            h2 class="text-lg font-semibold" { (i18n::strings(lang).related_posts) }
+            @for &other in related {
+                (post_card(localized_post(other, lang), lang))
+            }
+        }
+    })
+}
+
+fn author_card(author: &Author) -> Markup {
+    html! {
+        div class="flex items-center gap-4" {
+            @if let Some(avatar) = &author.avatar {
+                div class="avatar" {
+                    div class="w-16 rounded-full" {
+                        img src=(avatar) alt=(author.name) loading="lazy";
+                    }
+                }
+            }
+            div class="space-y-1" {
+                a href={"/authors/" (author.id)} class="text-lg font-semibold hover:underline" { (author.name) }
+                @if let Some(bio) = &author.bio {
+                    p class="text-sm text-base-content/70" { (bio) }
+                }
+            }
+        }
+    }
+}
+
+fn author_posts(id: &str) -> impl Iterator<Item = &'static Post> {
+    POSTS
+        .iter()
+        .filter(move |post| post.authors.iter().any(|author| author == id))
+}
+
+pub(super) fn page(html: Markup, req: &HttpRequest, nonce: &Nonce) -> HttpResponse {
+    let html = if super::is_fragment_request(req) {
+        html
+    } else {
+        super::index(Some(html), nonce)
+    };
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(html.into_string())
+}
+
+/// The post at `slug` in every language it is available in, for `hreflang`
+/// and the language switcher. URLs are absolute when `g_site_url` is set.
+/// Posts without translations have no alternates.
+fn post_locale(slug: &str, lang: &str, server: &Server) -> Locale {
+    let site = server.site_url().unwrap_or_default();
+    let alternates: Vec<(String, String)> = i18n::languages()
+        .iter()
+        .filter(|available| {
+            available.as_str() == i18n::default_language()
+                || TRANSLATIONS.contains_key(&(available.to_string(), slug.to_string()))
+        })
+        .map(|available| {
+            (
+                available.clone(),
+                format!("{site}{}", post_url(slug, available)),
+            )
+        })
+        .collect();
+
+    Locale {
+        lang: lang.to_string(),
+        alternates: if alternates.len() > 1 {
+            alternates
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+/// Like `page`, in the page's language. Fragments carry the language
+/// switcher along so it matches the post htmx swapped in.
+fn localized_page(html: Markup, locale: &Locale, req: &HttpRequest, nonce: &Nonce) -> HttpResponse {
+    let html = if super::is_fragment_request(req) {
+        html! {
+            (html)
+            (navbar::language_switcher(locale, true))
+        }
+    } else {
+        super::localized(Some(html), nonce, locale)
+    };
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(html.into_string())
+}
+
+#[get("/authors")]
+pub async fn authors_route(req: HttpRequest, nonce: Nonce) -> HttpResponse {
+    let strings = i18n::strings(i18n::default_language());
+    let html = html! {
+        div class="max-w-3xl mx-auto space-y-6" {
+            h1 class="text-2xl font-semibold" { (strings.authors) }
+            @for author in authors::all() {
+                div class="space-y-1" {
+                    (author_card(author))
+                    @let count = author_posts(&author.id).count();
+                    p class="text-sm text-base-content/60" {
+                        (count) " " @if count == 1 { (strings.post) } @else { (strings.posts) }
+                    }
+                }
+            }
+        }
+    };
+    page(html, &req, &nonce)
+}
+
+#[get("/authors/{id}")]
+pub async fn author_route(
+    server: web::Data<Server>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> HttpResponse {
+    let Some(author) = authors::get(&path.into_inner()) else {
+        return HttpResponse::NotFound().body("Author not found");
+    };
+
+    let html = html! {
+        div class="max-w-3xl mx-auto space-y-4" {
+            (author_card(author))
+            div class="flex flex-wrap gap-4 text-sm" {
+                @for link in &author.links {
+                    a href=(link.url) class="link" rel="me" { (link.name) }
+                }
+                @if server.site_url().is_some() {
+                    a href={"/authors/" (author.id) "/feed.xml"} class="link" type="application/atom+xml" { "Feed" }
+                }
+            }
+            div class="divider" {}
+            @for post in author_posts(&author.id) {
+                (post_card(post, i18n::default_language()))
+            }
+        }
+    };
+    page(html, &req, &nonce)
+}
+
+/// Atom feed of an author's posts, newest first. Feeds need absolute URLs,
+/// so there is none without `g_site_url`.
+#[get("/authors/{id}/feed.xml")]
+pub async fn author_feed_route(
+    server: web::Data<Server>,
+    path: web::Path<String>,
+) -> AwResult<HttpResponse> {
+    let (Some(author), Some(site)) = (authors::get(&path.into_inner()), server.site_url()) else {
+        return Ok(HttpResponse::NotFound().body("Feed not found"));
+    };
+
+    let entries = author_posts(&author.id)
+        .map(|post| {
+            let url = format!("{site}/post/{}", post.slug);
+            let content =
+                render_markdown(&post.content, post.base_url().as_deref()).map_err(render_error)?;
+            Ok(Entry {
+                title: post.title.clone(),
+                published: post.date,
+                updated: post.last_modified(),
+                authors: post
+                    .authors
+                    .iter()
+                    .map(|id| authors::get(id).map_or(id.clone(), |author| author.name.clone()))
+                    .collect(),
+                summary: post.excerpt.clone(),
+                content: Some(content),
+                url,
+            })
+        })
+        .collect::<AwResult<Vec<_>>>()?;
+
+    let feed = Feed {
+        title: author.name.clone(),
+        url: format!("{site}/authors/{}", author.id),
+        self_url: format!("{site}/authors/{}/feed.xml", author.id),
+        entries,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(feed::CONTENT_TYPE)
+        .body(feed::atom(&feed)))
+}
+
+#[get("/series/{name}")]
+pub async fn series_route(
+    server: web::Data<Server>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
+    Ok(series_page(
+        &path.into_inner(),
+        i18n::default_language(),
+        &server,
+        &req,
+        &nonce,
+    ))
+}
+
+/// A series at `/{lang}/series/{name}`, with its parts in `lang` where
+/// they are translated.
+#[get("/{lang}/series/{name}")]
+pub async fn translated_series_route(
+    server: web::Data<Server>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
+    let (lang, name) = path.into_inner();
+    if !i18n::is_translation(&lang) {
+        return Ok(HttpResponse::NotFound().body("Series not found"));
+    }
+    Ok(series_page(&name, &lang, &server, &req, &nonce))
+}
+
+fn series_page(
+    name: &str,
+    lang: &str,
+    server: &Server,
+    req: &HttpRequest,
+    nonce: &Nonce,
+) -> HttpResponse {
+    let slug = slug::slugify(name);
+    let Some(series) = SERIES.get(&slug) else {
+        return HttpResponse::NotFound().body("Series not found");
+    };
+    if slug != name {
+        return redirects::permanent(&series_url(&slug, lang), req.query_string());
+    }
+
+    let strings = i18n::strings(lang);
+    let html = html! {
+        div class="max-w-3xl mx-auto space-y-4" {
+            h1 class="text-2xl font-semibold" { (series.name) }
+            p class="text-sm text-base-content/60" { (series.parts.len()) " " (strings.parts) }
+            @for (position, &index) in series.parts.iter().enumerate() {
+                div class="space-y-1" {
+                    p class="text-sm text-base-content/60" { (strings.part) " " (position + 1) }
+                    (post_card(localized_post(index, lang), lang))
+                }
+            }
+        }
+    };
+    let site = server.site_url().unwrap_or_default();
+    let alternates: Vec<(String, String)> = i18n::languages()
+        .iter()
+        .map(|available| {
+            (
+                available.clone(),
+                format!("{site}{}", series_url(&slug, available)),
+            )
+        })
+        .collect();
+    let locale = Locale {
+        lang: lang.to_string(),
+        alternates: if alternates.len() > 1 {
+            alternates
+        } else {
+            Vec::new()
+        },
+    };
+    localized_page(html, &locale, req, nonce)
+}
+
 #[get("/posts/{index}")]
-pub async fn post_route(path: web::Path<usize>) -> AwResult<HttpResponse> {
+pub async fn post_route(path: web::Path<usize>, req: HttpRequest) -> AwResult<HttpResponse> {
     let index = path.into_inner();
 
     match get_post(index) {
         Some(post) => {
-            let html = post_to_html(post, index);
+            analytics::record(&req, &post.slug);
+            let html = post_to_html(post, index)?;
             Ok(HttpResponse::Ok()
                 .content_type("text/html")
                 .body(html.into_string()))
@@ -277,27 +1338,305 @@ pub async fn post_route(path: web::Path<usize>) -> AwResult<HttpResponse> {
 }
 
 #[get("/post/{slug}")]
-pub async fn post_slug_route(path: web::Path<String>) -> AwResult<HttpResponse> {
+pub async fn post_slug_route(
+    server: web::Data<Server>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
     let slug = path.into_inner();
 
     match get_post_by_slug(&slug) {
         Some((post, index)) => {
-            let html = post_to_html_with_slug(post, index);
-            Ok(HttpResponse::Ok()
-                .content_type("text/html")
-                .body(html.into_string()))
+            analytics::record(&req, &slug);
+            let lang = i18n::default_language();
+            let html = post_to_html_with_slug(&post, index, lang)?;
+            Ok(localized_page(
+                html,
+                &post_locale(&slug, lang, &server),
+                &req,
+                &nonce,
+            ))
         }
-        None => Ok(HttpResponse::NotFound().body("Post not found")),
+        None => match redirects::lookup(&format!("/post/{slug}")) {
+            Some(target) => Ok(redirects::permanent(target, req.query_string())),
+            None => Ok(HttpResponse::NotFound().body("Post not found")),
+        },
     }
 }
 
-pub fn posts() -> Markup {
+/// A translated post at `/{lang}/post/{slug}`. Posts without a translation
+/// into `lang` redirect to the original.
+#[get("/{lang}/post/{slug}")]
+pub async fn translated_post_route(
+    server: web::Data<Server>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
+    let (lang, slug) = path.into_inner();
+    let Some(&index) = SLUG_TO_INDEX
+        .get(&slug)
+        .filter(|_| i18n::is_translation(&lang))
+    else {
+        return Ok(HttpResponse::NotFound().body("Post not found"));
+    };
+    let Some(post) = TRANSLATIONS.get(&(lang.clone(), slug.clone())) else {
+        return Ok(HttpResponse::NotFound().body("Post not found"));
+    };
+
+    analytics::record(&req, &slug);
+    let html = post_to_html_with_slug(post, index, &lang)?;
+    Ok(localized_page(
+        html,
+        &post_locale(&slug, &lang, &server),
+        &req,
+        &nonce,
+    ))
+}
+
+/// Files of a bundle post, everything but its `index.md` and translations.
+#[get("/post/{slug}/{file:.*}")]
+pub async fn post_file_route(path: web::Path<(String, String)>) -> HttpResponse {
+    let (slug, file) = path.into_inner();
+    let contents = bundle_source(&slug, &file)
+        .filter(|_| {
+            !is_bundle_index(Path::new(&file)) && !file.split('/').any(|segment| segment == "..")
+        })
+        .and_then(|source| post_file(source.strip_prefix("posts/")?));
+
+    match contents {
+        Some(contents) => HttpResponse::Ok()
+            .content_type(
+                mime_guess::from_path(&file)
+                    .first_or_octet_stream()
+                    .as_ref(),
+            )
+            .body(contents),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+pub fn posts() -> AwResult<Markup> {
+    let lang = i18n::default_language();
     match get_post(0) {
-        Some(post) => post_to_html_with_slug(post, 0),
-        None => html! {
+        Some(post) => post_to_html_with_slug(&post, 0, lang),
+        None => Ok(html! {
             div class="space-y-6" {
-                p { "No posts available yet." }
+                p { (i18n::strings(lang).no_posts) }
             }
-        },
+        }),
+    }
+}
+
+fn post_card(post: &Post, lang: &str) -> Markup {
+    html! {
+        article class="card border border-base-200" {
+            div class="card-body gap-2" {
+                a href=(post_url(&post.slug, lang)) class="card-title hover:underline" {
+                    (post.title)
+                }
+                p class="text-sm text-base-content/60" {
+                    (post_meta(post, lang))
+                }
+                p { (post.excerpt) }
+                @if let Some(reactions) = reactions::summary(&post.slug) {
+                    (reactions)
+                }
+            }
+        }
+    }
+}
+
+/// One page of post cards followed by a sentinel that fetches the next page
+/// once it scrolls into view. Pages start at 1; pages past the last one
+/// are empty.
+pub fn post_page(page: usize, page_size: usize) -> Markup {
+    let page = page.max(1);
+    let Some(start) = (page - 1).checked_mul(page_size) else {
+        return html! {};
+    };
+    let cards = POSTS.iter().skip(start).take(page_size);
+    let has_more = start.saturating_add(page_size) < POSTS.len();
+    let next_url = format!("/?page={}", page.saturating_add(1));
+
+    html! {
+        @for post in cards {
+            (post_card(post, i18n::default_language()))
+        }
+        @if has_more {
+            div id="load-more" class="flex justify-center py-4"
+                hx-get=(next_url)
+                hx-trigger="intersect once"
+                hx-swap="outerHTML" {
+                button class="btn btn-ghost"
+                    hx-get=(next_url)
+                    hx-target="#load-more"
+                    hx-swap="outerHTML" {
+                    (i18n::strings(i18n::default_language()).load_more)
+                }
+            }
+        }
+    }
+}
+
+pub fn post_list(page: usize, page_size: usize) -> AwResult<Markup> {
+    if POSTS.is_empty() {
+        return posts();
+    }
+
+    Ok(html! {
+        div id="post-list" class="max-w-3xl mx-auto space-y-4" {
+            (post_page(page, page_size))
+        }
+    })
+}
+
+/// Posts whose slug is within a small edit distance of `query`, closest first.
+pub fn similar_posts(query: &str, limit: usize) -> Vec<(String, String)> {
+    let query = slug::slugify(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let max_distance = (query.len() / 3).max(2);
+    let mut matches: Vec<(usize, &Post)> = SLUG_TO_INDEX
+        .iter()
+        .filter_map(|(slug, &index)| {
+            let distance = slug::edit_distance(&query, slug);
+            let contained = query.len() >= 4 && slug.contains(&query);
+            (distance <= max_distance || contained).then(|| (distance, &POSTS[index]))
+        })
+        .collect();
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.slug.cmp(&b.1.slug)));
+
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, post)| (post.slug.clone(), post.title.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(path: &str, raw: &str) -> Post {
+        parse_post(Path::new(path), raw.as_bytes())
+            .0
+            .expect("post parses")
+    }
+
+    #[test]
+    fn title_and_slug_come_from_the_file_without_front_matter() {
+        let post = parse("2024-01-02-Hello World.md", "# Hello, *World*\n\nText.\n");
+        assert_eq!(post.slug, "hello-world");
+        assert_eq!(post.title, "Hello, World");
+    }
+
+    #[test]
+    fn front_matter_overrides_title_and_slug() {
+        let post = parse(
+            "2024-01-02-hello.md",
+            "---\ntitle: From Front Matter\nslug: Über Uns\n---\n# Heading\n\nText.\n",
+        );
+        assert_eq!(post.slug, "uber-uns");
+        assert_eq!(post.title, "From Front Matter");
+    }
+
+    #[test]
+    fn empty_front_matter_slug_falls_back_to_the_file_name() {
+        let post = parse(
+            "2024-01-02-hello.md",
+            "---\nslug: '!!!'\n---\n# Hi\n\nText.\n",
+        );
+        assert_eq!(post.slug, "hello");
+    }
+
+    #[test]
+    fn missing_title_is_reported() {
+        let (post, diagnostics) = parse_post(Path::new("2024-01-02-no_title.md"), b"Just text.\n");
+        assert_eq!(post.unwrap().title, "no title");
+        assert!(!diagnostics.is_empty());
+    }
+
+    #[test]
+    fn pages_past_the_end_are_empty() {
+        assert!(!POSTS.is_empty());
+        assert!(post_page(1, 1).into_string().contains("/post/"));
+        assert_eq!(post_page(usize::MAX, 10).into_string(), "");
+        assert_eq!(post_page(2, usize::MAX).into_string(), "");
+        assert_eq!(post_page(POSTS.len() + 1, 1).into_string(), "");
+
+        let all = post_page(1, usize::MAX).into_string();
+        assert!(!all.contains("load-more"));
+        let first = post_page(0, 1).into_string();
+        assert_eq!(first, post_page(1, 1).into_string());
+        assert_eq!(first.contains("/?page=2"), POSTS.len() > 1);
+    }
+
+    #[test]
+    fn file_names_without_a_name_use_the_whole_stem() {
+        assert_eq!(extract_slug_from_filename("2024-01-02"), "2024-01-02");
+        assert_eq!(extract_slug_from_filename("2024-01-02_Rust"), "rust");
+        assert_eq!(extract_slug_from_filename("notes"), "notes");
+    }
+
+    #[test]
+    fn series_urls_keep_the_language() {
+        let default = i18n::default_language();
+        assert_eq!(series_url("rust", default), "/series/rust");
+        assert_eq!(series_url("rust", "xx"), "/xx/series/rust");
+    }
+
+    #[test]
+    fn text_above_the_more_marker_is_the_excerpt() {
+        let post = parse(
+            "2024-01-02-more.md",
+            "# Title\nSubtitle\n---\n\nIntro with **bold**.\n\n<!-- more -->\n\nRest of the post.\n",
+        );
+        assert_eq!(post.excerpt, "Subtitle Intro with bold.");
+    }
+
+    #[test]
+    fn subtitle_before_the_rule_is_the_excerpt() {
+        let content = "# Title\nA *short* subtitle\n---\n\nBody text.\n";
+        assert_eq!(
+            extract_subtitle(content).as_deref(),
+            Some("A short subtitle")
+        );
+        assert_eq!(extract_excerpt(content), "A short subtitle");
+
+        assert_eq!(extract_subtitle("# Title\n\nSubtitle\n---\n"), None);
+        assert_eq!(extract_subtitle("# Title\nNo rule follows\n"), None);
+        assert_eq!(extract_subtitle("Not a title\n---\n"), None);
+    }
+
+    #[test]
+    fn excerpt_falls_back_to_the_first_words() {
+        let short = "# Title\n\nJust a few words.\n";
+        assert_eq!(extract_excerpt(short), "Just a few words.");
+
+        let words: Vec<String> = (1..=EXCERPT_WORDS + 10).map(|n| format!("w{n}")).collect();
+        let long = format!("# Title\n\n{}\n", words.join(" "));
+        assert_eq!(
+            extract_excerpt(&long),
+            format!("{}…", words[..EXCERPT_WORDS].join(" "))
+        );
+    }
+
+    #[test]
+    fn reading_time_rounds_up_and_skips_title_and_code() {
+        let post = parse(
+            "2024-01-02-short.md",
+            "# Many Title Words Here\n\nOne two three.\n\n```\nnot counted\n```\n",
+        );
+        assert_eq!(post.word_count, 3);
+        assert_eq!(post.reading_minutes(), 1);
+
+        let body = "word ".repeat(WORDS_PER_MINUTE + 1);
+        let post = parse("2024-01-02-long.md", &format!("# Long\n\n{body}\n"));
+        assert_eq!(post.word_count, WORDS_PER_MINUTE + 1);
+        assert_eq!(post.reading_minutes(), 2);
     }
 }
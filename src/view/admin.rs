@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use actix_session::config::CookieContentSecurity;
+use actix_session::storage::CookieSessionStore;
+use actix_session::{Session, SessionMiddleware};
+use actix_web::cookie::{Key, SameSite};
+use actix_web::http::{StatusCode, header};
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, error, get, post, web};
+use lazy_static::lazy_static;
+use maud::{Markup, html};
+use serde::Deserialize;
+
+use super::{about, comments, newsletter, reactions, webmentions};
+use crate::config::Server;
+use crate::rate_limit::{self, RateLimit};
+use crate::security::Nonce;
+
+const SESSION_COOKIE: &str = "focus_session";
+const SIGNED_IN: &str = "admin";
+
+lazy_static! {
+    /// Guessing the password takes long enough to be pointless.
+    static ref LOGINS: RateLimit = RateLimit::new(10, Duration::from_secs(15 * 60));
+}
+
+/// Key of the session cookie. Made once for the whole server: a key per
+/// worker would leave cookies from one worker unreadable on the others.
+pub fn session_key(server: &Server) -> Key {
+    match &server.admin().session_key {
+        Some(key) => Key::from(key.as_bytes()),
+        None => Key::generate(),
+    }
+}
+
+/// Encrypted cookie sessions, used for the admin login.
+pub fn sessions(key: Key) -> SessionMiddleware<CookieSessionStore> {
+    SessionMiddleware::builder(CookieSessionStore::default(), key)
+        .cookie_name(SESSION_COOKIE.to_string())
+        .cookie_content_security(CookieContentSecurity::Private)
+        .cookie_same_site(SameSite::Strict)
+        .build()
+}
+
+pub fn is_signed_in(session: &Session) -> bool {
+    session
+        .get::<bool>(SIGNED_IN)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// Fails requests to admin endpoints unless the admin area is enabled and
+/// the session is signed in. A disabled area looks like it does not exist.
+pub fn require(server: &Server, session: &Session) -> AwResult<()> {
+    if server.admin().password.is_none() {
+        return Err(error::ErrorNotFound("Not found"));
+    }
+    if !is_signed_in(session) {
+        return Err(error::ErrorUnauthorized("Sign in at /admin first"));
+    }
+    Ok(())
+}
+
+/// Compares in time independent of where the inputs differ.
+fn same(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn login_form(error: Option<&str>) -> Markup {
+    html! {
+        div class="max-w-sm mx-auto space-y-4" {
+            h1 class="text-2xl font-bold" { "Admin" }
+            @if let Some(error) = error {
+                div role="alert" class="alert alert-error" { (error) }
+            }
+            form method="post" action="/admin/login" class="space-y-4" {
+                label class="form-control w-full" {
+                    span class="label-text" { "Password" }
+                    input type="password" name="password" class="input input-bordered w-full"
+                        autocomplete="current-password" required autofocus;
+                }
+                button type="submit" class="btn btn-primary" { "Sign in" }
+            }
+        }
+    }
+}
+
+fn dashboard(server: &Server) -> Markup {
+    html! {
+        div class="space-y-8" {
+            div class="flex items-center justify-between" {
+                h1 class="text-2xl font-bold" { "Admin" }
+                div class="flex items-center gap-2" {
+                    @if server.analytics() {
+                        a href="/admin/analytics" class="btn btn-sm" { "Page views" }
+                    }
+                    form method="post" action="/admin/logout" {
+                        button type="submit" class="btn btn-ghost btn-sm" { "Sign out" }
+                    }
+                }
+            }
+            (comments::moderation_queue())
+            (webmentions::moderation())
+            (reactions::overview())
+            (newsletter::summary())
+        }
+    }
+}
+
+#[get("/admin")]
+pub async fn admin_route(
+    server: web::Data<Server>,
+    session: Session,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> HttpResponse {
+    if server.admin().password.is_none() {
+        return HttpResponse::NotFound().body("Not found");
+    }
+    let html = if is_signed_in(&session) {
+        dashboard(&server)
+    } else {
+        login_form(None)
+    };
+    about::page(html, &req, &nonce)
+}
+
+#[derive(Deserialize)]
+pub struct Login {
+    password: String,
+}
+
+#[post("/admin/login")]
+pub async fn login_route(
+    server: web::Data<Server>,
+    session: Session,
+    form: web::Form<Login>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
+    let Some(password) = &server.admin().password else {
+        return Ok(HttpResponse::NotFound().body("Not found"));
+    };
+
+    let refused = |status: StatusCode, message: &str| {
+        let form = login_form(Some(message));
+        let html = if super::is_fragment_request(&req) {
+            form
+        } else {
+            super::index(Some(form), &nonce)
+        };
+        HttpResponse::build(status)
+            .content_type("text/html")
+            .body(html.into_string())
+    };
+
+    if !LOGINS.allow(&rate_limit::client(&req)) {
+        return Ok(refused(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many attempts, try again later.",
+        ));
+    }
+    if !same(form.password.as_bytes(), password.as_bytes()) {
+        log::warn!("Failed admin login from {}", rate_limit::client(&req));
+        return Ok(refused(StatusCode::UNAUTHORIZED, "Wrong password."));
+    }
+
+    session.renew();
+    session
+        .insert(SIGNED_IN, true)
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, "/admin"))
+        .finish())
+}
+
+#[post("/admin/logout")]
+pub async fn logout_route(session: Session) -> HttpResponse {
+    session.purge();
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, "/"))
+        .finish()
+}
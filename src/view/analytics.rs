@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Mutex;
+
+use actix_session::Session;
+use actix_web::http::header;
+use actix_web::{HttpRequest, Result as AwResult, get, web};
+use chrono::{Days, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use maud::{Markup, html};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{about, admin};
+use crate::config::{self, Server};
+use crate::dates;
+use crate::rate_limit;
+use crate::security::Nonce;
+use crate::store::{Record, Store};
+
+/// Ranges the dashboard offers, in days.
+const RANGES: [u64; 4] = [7, 30, 90, 365];
+const TOP_REFERRERS: usize = 10;
+
+lazy_static! {
+    static ref DAYS: Store<Day> = {
+        let path = config::get().data_dir().join("analytics.jsonl");
+        Store::open(&path)
+            .unwrap_or_else(|err| panic!("could not open analytics at {}: {err}", path.display()))
+    };
+    static ref COUNTS: Mutex<Counts> = Mutex::new(Counts::load());
+    /// Crawlers, feed readers, link previews, monitoring and HTTP libraries.
+    static ref BOT: Regex = Regex::new(
+        r"(?i)bot|crawl|spider|slurp|scrape|archiver|fetch|preview|monitor|uptime|lighthouse|headless|feed|rss|curl|wget|python|java/|go-http|okhttp|libwww|httpclient|axios|node-fetch|facebookexternalhit|embedly|whatsapp|telegram"
+    )
+    .unwrap();
+}
+
+fn enabled() -> bool {
+    config::get().analytics()
+}
+
+/// Page views of one post on one day in the site's time zone.
+#[derive(Clone, Serialize, Deserialize)]
+struct Day {
+    id: String,
+    date: NaiveDate,
+    post: String,
+    views: u64,
+    visitors: u64,
+    /// Views by the domain of the page that linked here.
+    referrers: BTreeMap<String, u64>,
+}
+
+impl Record for Day {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Counts kept in memory and written out by `flush`. Visitor ids only live
+/// here: they hash address and browser with a key that changes every day
+/// and is never stored, so they cannot be followed from one day to the next.
+struct Counts {
+    today: NaiveDate,
+    key: RandomState,
+    seen: HashSet<(String, u64)>,
+    days: HashMap<String, Day>,
+    changed: HashSet<String>,
+}
+
+impl Counts {
+    fn load() -> Self {
+        Counts::new(today(), DAYS.all())
+    }
+
+    fn new(today: NaiveDate, days: Vec<Day>) -> Self {
+        Counts {
+            today,
+            key: RandomState::new(),
+            seen: HashSet::new(),
+            days: days.into_iter().map(|day| (day.id.clone(), day)).collect(),
+            changed: HashSet::new(),
+        }
+    }
+
+    fn record(
+        &mut self,
+        date: NaiveDate,
+        post: &str,
+        visitor: (&str, &str),
+        referrer: Option<String>,
+    ) {
+        if date != self.today {
+            self.today = date;
+            self.key = RandomState::new();
+            self.seen.clear();
+        }
+
+        let id = format!("{date} {post}");
+        let visitor = self.key.hash_one(visitor);
+        let new_visitor = self.seen.insert((post.to_string(), visitor));
+        let day = self.days.entry(id.clone()).or_insert_with(|| Day {
+            id: id.clone(),
+            date,
+            post: post.to_string(),
+            views: 0,
+            visitors: 0,
+            referrers: BTreeMap::new(),
+        });
+        day.views += 1;
+        if new_visitor {
+            day.visitors += 1;
+        }
+        if let Some(referrer) = referrer {
+            *day.referrers.entry(referrer).or_default() += 1;
+        }
+        self.changed.insert(id);
+    }
+}
+
+fn today() -> NaiveDate {
+    dates::local(Utc::now()).date_naive()
+}
+
+/// Reads the stored counts at startup.
+pub fn prepare() {
+    if enabled() {
+        lazy_static::initialize(&COUNTS);
+        log::info!(
+            "Loaded page views of {} post days from {}",
+            DAYS.all().len(),
+            DAYS.path().display()
+        );
+    }
+}
+
+/// Writes the counts that changed since the last call.
+pub fn flush() {
+    if !enabled() {
+        return;
+    }
+    let changed: Vec<Day> = {
+        let mut counts = COUNTS.lock().unwrap_or_else(|err| err.into_inner());
+        let ids: Vec<String> = counts.changed.drain().collect();
+        ids.iter()
+            .filter_map(|id| counts.days.get(id).cloned())
+            .collect()
+    };
+    for day in changed {
+        if let Err(err) = DAYS.put(day) {
+            log::error!("Could not store page views: {err}");
+        }
+    }
+}
+
+/// Domain of the page that linked here, without `www.`, unless it is
+/// this site at `site`.
+fn referrer(referrer: &str, site: Option<&str>) -> Option<String> {
+    fn domain(url: &str) -> Option<String> {
+        let host = Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+        Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+    }
+    let host = domain(referrer)?;
+    (site.and_then(domain).as_ref() != Some(&host)).then_some(host)
+}
+
+/// Crawlers and scripts, and clients that do not say what they are.
+fn is_bot(agent: &str) -> bool {
+    agent.is_empty() || BOT.is_match(agent)
+}
+
+/// Counts a view of the post at `slug`. Requests from bots, or without a
+/// user agent, are not views.
+pub fn record(req: &HttpRequest, slug: &str) {
+    if !enabled() {
+        return;
+    }
+    let agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|agent| agent.to_str().ok())
+        .unwrap_or_default();
+    if is_bot(agent) {
+        return;
+    }
+    let client = rate_limit::client(req);
+    let referrer = req
+        .headers()
+        .get(header::REFERER)
+        .and_then(|referrer| referrer.to_str().ok())
+        .and_then(|value| referrer(value, config::get().site_url()));
+    COUNTS.lock().unwrap_or_else(|err| err.into_inner()).record(
+        today(),
+        slug,
+        (&client, agent),
+        referrer,
+    );
+}
+
+/// Daily totals as an SVG bar chart, visitors drawn over views.
+fn daily_chart(daily: &[(NaiveDate, u64, u64)]) -> Markup {
+    const HEIGHT: u64 = 100;
+    let most = daily
+        .iter()
+        .map(|&(_, views, _)| views)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let bar = |value: u64| value * HEIGHT / most;
+    let width = daily.len() * 10;
+
+    html! {
+        svg viewBox={"0 0 " (width) " " (HEIGHT)} preserveAspectRatio="none"
+            class="w-full h-40 bg-base-200 rounded" role="img" aria-label="Page views per day" {
+            @for (at, (date, views, visitors)) in daily.iter().enumerate() {
+                g {
+                    title { (date) ": " (views) " views, " (visitors) " visitors" }
+                    rect x=(at * 10 + 1) y=(HEIGHT - bar(*views)) width="8" height=(bar(*views))
+                        class="fill-primary/40" {}
+                    rect x=(at * 10 + 3) y=(HEIGHT - bar(*visitors)) width="4" height=(bar(*visitors))
+                        class="fill-primary" {}
+                }
+            }
+        }
+        @if let (Some((first, ..)), Some((last, ..))) = (daily.first(), daily.last()) {
+            div class="flex justify-between text-xs text-base-content/60" {
+                span { (first) }
+                span { "views and " span class="text-primary" { "visitors" } " per day" }
+                span { (last) }
+            }
+        }
+    }
+}
+
+/// Rows of a label, a bar relative to the largest value, and the value.
+fn bars(rows: &[(Markup, u64, Option<u64>)]) -> Markup {
+    let most = rows
+        .iter()
+        .map(|(_, value, _)| *value)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    html! {
+        table class="table table-sm" {
+            tbody {
+                @for (label, value, secondary) in rows {
+                    tr {
+                        td class="w-1/2" { (label) }
+                        td class="w-1/3" {
+                            progress class="progress progress-primary" value=(value) max=(most) {}
+                        }
+                        td class="text-right tabular-nums" {
+                            (value)
+                            @if let Some(secondary) = secondary {
+                                span class="text-base-content/60" { " / " (secondary) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn dashboard(range: u64) -> Markup {
+    let last = today();
+    let first = last - Days::new(range - 1);
+    let days: Vec<Day> = {
+        let counts = COUNTS.lock().unwrap_or_else(|err| err.into_inner());
+        counts
+            .days
+            .values()
+            .filter(|day| day.date >= first && day.date <= last)
+            .cloned()
+            .collect()
+    };
+
+    let mut daily: BTreeMap<NaiveDate, (u64, u64)> = first
+        .iter_days()
+        .take_while(|date| *date <= last)
+        .map(|date| (date, (0, 0)))
+        .collect();
+    let mut posts: HashMap<&str, (u64, u64)> = HashMap::new();
+    let mut referrers: HashMap<&str, u64> = HashMap::new();
+    for day in &days {
+        let total = daily.entry(day.date).or_default();
+        total.0 += day.views;
+        total.1 += day.visitors;
+        let post = posts.entry(&day.post).or_default();
+        post.0 += day.views;
+        post.1 += day.visitors;
+        for (referrer, views) in &day.referrers {
+            *referrers.entry(referrer).or_default() += views;
+        }
+    }
+
+    let daily: Vec<(NaiveDate, u64, u64)> = daily
+        .into_iter()
+        .map(|(date, (views, visitors))| (date, views, visitors))
+        .collect();
+    let mut posts: Vec<(&str, (u64, u64))> = posts.into_iter().collect();
+    posts.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.0.cmp(b.0)));
+    let mut referrers: Vec<(&str, u64)> = referrers.into_iter().collect();
+    referrers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    referrers.truncate(TOP_REFERRERS);
+
+    let views: u64 = daily.iter().map(|&(_, views, _)| views).sum();
+    let visitors: u64 = daily.iter().map(|&(_, _, visitors)| visitors).sum();
+
+    html! {
+        div class="space-y-8" {
+            div class="flex flex-wrap items-center justify-between gap-4" {
+                h1 class="text-2xl font-bold" { "Page views" }
+                div class="join" {
+                    @for option in RANGES {
+                        a href={"/admin/analytics?days=" (option)}
+                            class={"join-item btn btn-sm" @if option == range { " btn-active" }} {
+                            (option) " days"
+                        }
+                    }
+                }
+            }
+            div class="stats bg-base-200" {
+                div class="stat" {
+                    div class="stat-title" { "Views" }
+                    div class="stat-value" { (views) }
+                }
+                div class="stat" {
+                    div class="stat-title" { "Visitors" }
+                    div class="stat-value" { (visitors) }
+                    div class="stat-desc" { "distinct per post and day" }
+                }
+            }
+            section class="space-y-2" {
+                h2 class="text-lg font-semibold" { "Per day" }
+                (daily_chart(&daily))
+            }
+            section class="space-y-2" {
+                h2 class="text-lg font-semibold" { "Per post" }
+                @if posts.is_empty() {
+                    p class="text-base-content/70" { "No views in this range." }
+                } @else {
+                    p class="text-sm text-base-content/60" { "views / visitors" }
+                    (bars(&posts.iter().map(|(slug, (views, visitors))| {
+                        let label = html! {
+                            a href={"/post/" (slug)} class="link link-hover" {
+                                (about::post_title(slug).unwrap_or(slug))
+                            }
+                        };
+                        (label, *views, Some(*visitors))
+                    }).collect::<Vec<_>>()))
+                }
+            }
+            section class="space-y-2" {
+                h2 class="text-lg font-semibold" { "Referrers" }
+                @if referrers.is_empty() {
+                    p class="text-base-content/70" { "No referred views in this range." }
+                } @else {
+                    (bars(&referrers.iter().map(|(domain, views)| {
+                        (html! { (domain) }, *views, None)
+                    }).collect::<Vec<_>>()))
+                }
+            }
+            p class="text-sm text-base-content/60" {
+                "Counted without cookies; bots are left out. "
+                a href="/admin" class="link" { "Back to moderation" }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RangeQuery {
+    days: Option<u64>,
+}
+
+#[get("/admin/analytics")]
+pub async fn analytics_route(
+    server: web::Data<Server>,
+    session: Session,
+    query: web::Query<RangeQuery>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<actix_web::HttpResponse> {
+    admin::require(&server, &session)?;
+    if !enabled() {
+        return Ok(actix_web::HttpResponse::NotFound().body("Analytics are disabled"));
+    }
+    let range = query
+        .days
+        .filter(|days| RANGES.contains(days))
+        .unwrap_or(30);
+    Ok(about::page(dashboard(range), &req, &nonce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn bots_and_anonymous_clients_are_not_counted() {
+        for agent in [
+            "",
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+            "curl/8.5.0",
+            "python-requests/2.31",
+            "Feedly/1.0 (+http://www.feedly.com/fetcher.html)",
+            "facebookexternalhit/1.1",
+        ] {
+            assert!(is_bot(agent), "{agent}");
+        }
+        assert!(!is_bot(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+        ));
+    }
+
+    #[test]
+    fn referrers_are_reduced_to_their_domain() {
+        let site = Some("https://blog.example");
+        assert_eq!(
+            referrer("https://www.news.example/item?id=1#top", site),
+            Some("news.example".to_string())
+        );
+        assert_eq!(referrer("https://blog.example/post/a", site), None);
+        assert_eq!(referrer("https://WWW.Blog.example/", site), None);
+        assert_eq!(referrer("not a url", site), None);
+        // Without a configured address every domain is someone else's
+        assert_eq!(
+            referrer("https://blog.example/post/a", None),
+            Some("blog.example".to_string())
+        );
+    }
+
+    #[test]
+    fn visitors_are_counted_once_per_post_and_day() {
+        let monday = date("2026-03-02");
+        let mut counts = Counts::new(monday, Vec::new());
+        let reader = ("203.0.113.7", "Firefox");
+        counts.record(monday, "a", reader, Some("news.example".to_string()));
+        counts.record(monday, "a", reader, None);
+        counts.record(monday, "b", reader, None);
+        counts.record(monday, "a", ("198.51.100.2", "Firefox"), None);
+
+        let a = &counts.days["2026-03-02 a"];
+        assert_eq!((a.views, a.visitors), (3, 2));
+        assert_eq!(a.referrers.get("news.example"), Some(&1));
+        assert_eq!(counts.days["2026-03-02 b"].visitors, 1);
+        assert_eq!(counts.changed.len(), 2);
+    }
+
+    #[test]
+    fn the_visitor_key_changes_every_day() {
+        let monday = date("2026-03-02");
+        let tuesday = date("2026-03-03");
+        let mut counts = Counts::new(monday, Vec::new());
+        let reader = ("203.0.113.7", "Firefox");
+        counts.record(monday, "a", reader, None);
+        let hashed = counts.key.hash_one(reader);
+
+        counts.record(tuesday, "a", reader, None);
+        assert_eq!(counts.today, tuesday);
+        assert_ne!(counts.key.hash_one(reader), hashed);
+        assert_eq!(counts.seen.len(), 1);
+        assert_eq!(counts.days["2026-03-03 a"].visitors, 1);
+        assert_eq!(counts.days["2026-03-02 a"].visitors, 1);
+    }
+}
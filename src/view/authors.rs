@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use include_dir::{Dir, include_dir};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+use crate::check::Diagnostic;
+
+/// One `{id}.yaml` file per author; the file name is the id posts refer to.
+static AUTHORS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/authors");
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Author {
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub bio: Option<String>,
+    /// Image URL, such as `/assets/avatars/jane.png`.
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Link {
+    pub name: String,
+    pub url: String,
+}
+
+lazy_static! {
+    /// Authors by id, sorted so listings are stable.
+    static ref AUTHORS: BTreeMap<String, Author> = load()
+        .into_iter()
+        .filter_map(|(_, author)| author.ok())
+        .map(|author| (author.id.clone(), author))
+        .collect();
+}
+
+fn load() -> Vec<(String, Result<Author, String>)> {
+    AUTHORS_DIR
+        .files()
+        .filter(|file| {
+            file.path()
+                .extension()
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        })
+        .map(|file| {
+            let path = format!("authors/{}", file.path().display());
+            let author = file
+                .path()
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| "file name is not valid UTF-8".to_string())
+                .and_then(|id| {
+                    let mut author: Author = serde_yaml::from_slice(file.contents())
+                        .map_err(|err| format!("invalid author: {err}"))?;
+                    author.id = id.to_string();
+                    Ok(author)
+                });
+            (path, author)
+        })
+        .collect()
+}
+
+pub fn get(id: &str) -> Option<&'static Author> {
+    AUTHORS.get(id)
+}
+
+pub fn all() -> impl Iterator<Item = &'static Author> {
+    AUTHORS.values()
+}
+
+/// Author files that do not parse or leave the name empty.
+pub fn check() -> Vec<Diagnostic> {
+    load()
+        .into_iter()
+        .filter_map(|(path, author)| match author {
+            Err(err) => Some(Diagnostic::error(path, None, err)),
+            Ok(author) if author.name.trim().is_empty() => {
+                Some(Diagnostic::error(path, None, "author has no name"))
+            }
+            Ok(_) => None,
+        })
+        .collect()
+}
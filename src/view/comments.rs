@@ -0,0 +1,517 @@
+use std::io;
+use std::time::Duration;
+
+use actix_session::Session;
+use actix_web::http::{StatusCode, header};
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, error, get, post, web};
+use chrono::{DateTime, SubsecRound, Utc};
+use lazy_static::lazy_static;
+use maud::{Markup, PreEscaped, html};
+use serde::{Deserialize, Serialize};
+
+use super::{about, admin, i18n};
+use crate::config::{self, Server};
+use crate::rate_limit::{self, RateLimit};
+use crate::sanitize;
+use crate::store::{Record, Store};
+
+const MAX_NAME_LENGTH: usize = 80;
+/// Approved comments listed in the admin area for removal.
+const RECENTLY_APPROVED: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Comment {
+    id: String,
+    /// Slug of the post; translations share the comments of the original.
+    post: String,
+    author: String,
+    /// Markdown as written, rendered with `sanitize::reader_markdown`.
+    body: String,
+    created: DateTime<Utc>,
+    status: Status,
+}
+
+impl Record for Comment {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+lazy_static! {
+    static ref COMMENTS: Store<Comment> = {
+        let path = config::get().data_dir().join("comments.jsonl");
+        Store::open(&path)
+            .unwrap_or_else(|err| panic!("could not open comments at {}: {err}", path.display()))
+    };
+    static ref SUBMISSIONS: RateLimit =
+        RateLimit::new(settings().rate_limit, Duration::from_secs(60 * 60));
+}
+
+fn settings() -> &'static config::Comments {
+    config::get().comments()
+}
+
+/// Opens the comment store at startup, so a data directory that cannot be
+/// written to stops the server rather than the first reader.
+pub fn prepare() {
+    if settings().enabled {
+        log::info!(
+            "Loaded {} comments from {}",
+            COMMENTS.all().len(),
+            COMMENTS.path().display()
+        );
+    }
+}
+
+/// Where the comments of a post go. They load once the post is shown, so
+/// post pages stay the same for every reader.
+pub fn placeholder(slug: &str, lang: &str) -> Option<Markup> {
+    settings().enabled.then(|| {
+        html! {
+            div id="comments"
+                hx-get={"/comments/" (slug) "?lang=" (lang)}
+                hx-trigger="load"
+                hx-swap="outerHTML" {}
+        }
+    })
+}
+
+/// Feedback shown above the form after a submission.
+enum Notice {
+    Pending,
+    Invalid,
+    RateLimited,
+}
+
+fn comment(comment: &Comment, lang: &str) -> Markup {
+    html! {
+        article class="card bg-base-200" {
+            div class="card-body gap-2 py-4" {
+                p class="text-sm text-base-content/60" {
+                    span class="font-semibold text-base-content" { (comment.author) }
+                    " · " (about::time(comment.created, lang))
+                }
+                div class="prose prose-sm max-w-none" {
+                    (PreEscaped(sanitize::reader_markdown(&comment.body)))
+                }
+            }
+        }
+    }
+}
+
+/// Approved comments on the post `slug`, in the order they came in.
+fn approved(store: &Store<Comment>, slug: &str) -> Vec<Comment> {
+    store
+        .all()
+        .into_iter()
+        .filter(|comment| comment.post == slug && comment.status == Status::Approved)
+        .collect()
+}
+
+fn section(slug: &str, lang: &str, notice: Option<Notice>, draft: Option<&Submission>) -> Markup {
+    let strings = i18n::strings(lang);
+    let approved = approved(&COMMENTS, slug);
+    let action = format!("/comments/{slug}");
+
+    html! {
+        section id="comments" class="space-y-4" {
+            h2 class="text-lg font-semibold" { (strings.comments) " (" (approved.len()) ")" }
+            @for approved in &approved {
+                (comment(approved, lang))
+            }
+            @if approved.is_empty() {
+                p class="text-base-content/70" { (strings.no_comments) }
+            }
+
+            form method="post" action=(action) hx-post=(action) hx-target="#comments" hx-swap="outerHTML"
+                class="space-y-3" {
+                h3 class="font-semibold" { (strings.leave_comment) }
+                @match notice {
+                    Some(Notice::Pending) => div role="status" class="alert alert-success" { (strings.awaiting_moderation) },
+                    Some(Notice::Invalid) => div role="alert" class="alert alert-error" { (strings.comment_invalid) },
+                    Some(Notice::RateLimited) => div role="alert" class="alert alert-warning" { (strings.comment_rate_limited) },
+                    None => {},
+                }
+                input type="hidden" name="lang" value=(lang);
+                // Honeypot: hidden from people, filled in by form-spamming bots
+                div class="hidden" aria-hidden="true" {
+                    label { "Website" input type="text" name="website" tabindex="-1" autocomplete="off"; }
+                }
+                label class="form-control w-full" {
+                    span class="label-text" { (strings.your_name) }
+                    input type="text" name="name" class="input input-bordered w-full" required
+                        maxlength=(MAX_NAME_LENGTH) autocomplete="name" value=[draft.map(|draft| &draft.name)];
+                }
+                label class="form-control w-full" {
+                    span class="label-text" { (strings.your_comment) }
+                    textarea name="body" class="textarea textarea-bordered w-full" rows="5" required
+                        maxlength=(settings().max_length) {
+                        @if let Some(draft) = draft { (draft.body) }
+                    }
+                }
+                p class="text-xs text-base-content/60" { (strings.markdown_hint) }
+                button type="submit" class="btn btn-primary" { (strings.post_comment) }
+            }
+        }
+    }
+}
+
+fn respond(status: StatusCode, html: Markup) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type("text/html")
+        .body(html.into_string())
+}
+
+#[derive(Deserialize)]
+pub struct CommentsQuery {
+    lang: Option<String>,
+}
+
+#[get("/comments/{slug}")]
+pub async fn comments_route(
+    path: web::Path<String>,
+    query: web::Query<CommentsQuery>,
+) -> HttpResponse {
+    let slug = path.into_inner();
+    if !settings().enabled || !about::has_post(&slug) {
+        return HttpResponse::NotFound().body("Post not found");
+    }
+    let lang = i18n::known(query.lang.as_deref());
+    respond(StatusCode::OK, section(&slug, lang, None, None))
+}
+
+#[derive(Deserialize)]
+pub struct Submission {
+    name: String,
+    body: String,
+    #[serde(default)]
+    website: String,
+    lang: Option<String>,
+}
+
+/// What a submitted form turns into.
+enum Received {
+    /// The honeypot field was filled in.
+    Spam,
+    /// An empty or too long name or comment.
+    Invalid,
+    Pending(Comment),
+}
+
+fn receive(slug: &str, form: &Submission, max_length: usize) -> Received {
+    if !form.website.is_empty() {
+        return Received::Spam;
+    }
+    let author = form.name.trim();
+    let body = form.body.trim();
+    if author.is_empty()
+        || author.chars().count() > MAX_NAME_LENGTH
+        || body.is_empty()
+        || body.chars().count() > max_length
+    {
+        return Received::Invalid;
+    }
+    Received::Pending(Comment {
+        id: uuid::Uuid::new_v4().to_string(),
+        post: slug.to_string(),
+        author: author.to_string(),
+        body: body.to_string(),
+        created: Utc::now().trunc_subsecs(0),
+        status: Status::Pending,
+    })
+}
+
+#[post("/comments/{slug}")]
+pub async fn submit_comment_route(
+    path: web::Path<String>,
+    form: web::Form<Submission>,
+    req: HttpRequest,
+) -> AwResult<HttpResponse> {
+    let slug = path.into_inner();
+    if !settings().enabled || !about::has_post(&slug) {
+        return Ok(HttpResponse::NotFound().body("Post not found"));
+    }
+    let lang = i18n::known(form.lang.as_deref());
+    let client = rate_limit::client(&req);
+
+    // Bots get the same answer as people, so they do not learn to skip the field
+    match receive(&slug, &form, settings().max_length) {
+        Received::Spam => {
+            log::info!("Dropped comment on {slug} from {client}: honeypot filled in");
+        }
+        _ if !SUBMISSIONS.allow(&client) => {
+            return Ok(respond(
+                StatusCode::TOO_MANY_REQUESTS,
+                section(&slug, lang, Some(Notice::RateLimited), Some(&form)),
+            ));
+        }
+        Received::Invalid => {
+            return Ok(respond(
+                StatusCode::BAD_REQUEST,
+                section(&slug, lang, Some(Notice::Invalid), Some(&form)),
+            ));
+        }
+        Received::Pending(comment) => {
+            // Writing waits for the disk, which must not hold up this worker
+            web::block(move || COMMENTS.put(comment))
+                .await?
+                .map_err(|err| {
+                    log::error!("Could not store comment: {err}");
+                    error::ErrorInternalServerError("Could not save comment")
+                })?;
+        }
+    }
+
+    if !super::is_fragment_request(&req) {
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((
+                header::LOCATION,
+                format!("{}#comments", about::post_url(&slug, lang)),
+            ))
+            .finish());
+    }
+    Ok(respond(
+        StatusCode::OK,
+        section(&slug, lang, Some(Notice::Pending), None),
+    ))
+}
+
+fn moderated(comment: &Comment, actions: &[(&str, &str, &str)]) -> Markup {
+    let lang = i18n::default_language();
+    html! {
+        article class="card bg-base-200" {
+            div class="card-body gap-2 py-4" {
+                p class="text-sm text-base-content/60" {
+                    span class="font-semibold text-base-content" { (comment.author) }
+                    " on "
+                    a href=(about::post_url(&comment.post, lang)) class="link" { (comment.post) }
+                    " · " (about::time(comment.created, lang))
+                }
+                div class="prose prose-sm max-w-none" {
+                    (PreEscaped(sanitize::reader_markdown(&comment.body)))
+                }
+                div class="card-actions justify-end" {
+                    @for (action, label, class) in actions {
+                        @let url = format!("/admin/comments/{}/{action}", comment.id);
+                        form method="post" action=(url) hx-post=(url) hx-target="closest article" hx-swap="outerHTML" {
+                            button type="submit" class={"btn btn-sm " (class)} { (label) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Comments waiting for a decision, oldest first, and the latest approved
+/// ones in case one has to go after all.
+pub fn moderation_queue() -> Markup {
+    if !settings().enabled {
+        return html! {};
+    }
+    let comments = COMMENTS.all();
+    let pending: Vec<&Comment> = comments
+        .iter()
+        .filter(|comment| comment.status == Status::Pending)
+        .collect();
+    let mut approved: Vec<&Comment> = comments
+        .iter()
+        .filter(|comment| comment.status == Status::Approved)
+        .collect();
+    approved.sort_by_key(|comment| std::cmp::Reverse(comment.created));
+    approved.truncate(RECENTLY_APPROVED);
+
+    html! {
+        section class="space-y-3" {
+            h2 class="text-lg font-semibold" { "Comments awaiting moderation (" (pending.len()) ")" }
+            @for comment in &pending {
+                (moderated(comment, &[("approve", "Approve", "btn-primary"), ("reject", "Reject", "btn-ghost")]))
+            }
+            @if pending.is_empty() {
+                p class="text-base-content/70" { "Nothing to moderate." }
+            }
+        }
+        @if !approved.is_empty() {
+            section class="space-y-3" {
+                h2 class="text-lg font-semibold" { "Recently approved comments" }
+                @for comment in &approved {
+                    (moderated(comment, &[("reject", "Remove", "btn-ghost")]))
+                }
+            }
+        }
+    }
+}
+
+/// Gives comment `id` in `store` a new status, `None` if there is no such
+/// comment. Approved comments can still be rejected later.
+fn moderate(store: &Store<Comment>, id: &str, status: Status) -> io::Result<Option<Comment>> {
+    let Some(mut comment) = store.get(id) else {
+        return Ok(None);
+    };
+    comment.status = status;
+    store.put(comment.clone())?;
+    Ok(Some(comment))
+}
+
+/// Approves or rejects a comment. htmx removes it from the queue; without
+/// htmx the browser returns to the admin page.
+#[post("/admin/comments/{id}/{action}")]
+pub async fn moderate_route(
+    path: web::Path<(String, String)>,
+    server: web::Data<Server>,
+    session: Session,
+    req: HttpRequest,
+) -> AwResult<HttpResponse> {
+    admin::require(&server, &session)?;
+    let (id, action) = path.into_inner();
+    let status = match action.as_str() {
+        "approve" => Status::Approved,
+        "reject" => Status::Rejected,
+        _ => return Ok(HttpResponse::NotFound().body("Unknown action")),
+    };
+    let moderated = web::block(move || moderate(&COMMENTS, &id, status))
+        .await?
+        .map_err(|err| {
+            log::error!("Could not store comment: {err}");
+            error::ErrorInternalServerError("Could not save comment")
+        })?;
+    if moderated.is_none() {
+        return Ok(HttpResponse::NotFound().body("Comment not found"));
+    }
+
+    if super::is_fragment_request(&req) {
+        Ok(HttpResponse::Ok().content_type("text/html").finish())
+    } else {
+        Ok(HttpResponse::SeeOther()
+            .insert_header((header::LOCATION, "/admin"))
+            .finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A comment store in a fresh file, removed again when dropped.
+    struct TempStore(PathBuf);
+
+    impl TempStore {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("focus-{name}-{}.jsonl", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            TempStore(path)
+        }
+
+        fn open(&self) -> Store<Comment> {
+            Store::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn form(name: &str, body: &str, website: &str) -> Submission {
+        Submission {
+            name: name.to_string(),
+            body: body.to_string(),
+            website: website.to_string(),
+            lang: None,
+        }
+    }
+
+    #[test]
+    fn filled_in_honeypot_is_spam() {
+        assert!(matches!(
+            receive(
+                "post",
+                &form("Ann", "Nice post", "https://spam.example"),
+                100
+            ),
+            Received::Spam
+        ));
+        // Even when the rest of the form is invalid
+        assert!(matches!(
+            receive("post", &form("", "", "x"), 100),
+            Received::Spam
+        ));
+    }
+
+    #[test]
+    fn names_and_bodies_must_fit_the_bounds() {
+        let invalid = |name: &str, body: &str| {
+            matches!(
+                receive("post", &form(name, body, ""), 10),
+                Received::Invalid
+            )
+        };
+        assert!(invalid("", "Nice post"));
+        assert!(invalid("   ", "Nice post"));
+        assert!(invalid("Ann", " \n "));
+        assert!(invalid(&"a".repeat(MAX_NAME_LENGTH + 1), "Nice post"));
+        assert!(invalid("Ann", "ten chars!+"));
+        assert!(!invalid(&"ä".repeat(MAX_NAME_LENGTH), "ten chars!"));
+        assert!(!invalid("Ann", "  ten chars!  "));
+
+        let Received::Pending(comment) = receive("post", &form(" Ann ", " Hi \n", ""), 10) else {
+            panic!("comment was not accepted");
+        };
+        assert_eq!(comment.post, "post");
+        assert_eq!(comment.author, "Ann");
+        assert_eq!(comment.body, "Hi");
+        assert!(comment.status == Status::Pending);
+    }
+
+    #[test]
+    fn only_approved_comments_are_shown() {
+        let temp = TempStore::new("comments-moderation");
+        let store = temp.open();
+        for (post, author) in [("post", "Ann"), ("post", "Bob"), ("other", "Cy")] {
+            let Received::Pending(comment) = receive(post, &form(author, "Hi", ""), 100) else {
+                panic!("comment was not accepted");
+            };
+            store.put(comment).unwrap();
+        }
+        let ids: Vec<String> = store.all().into_iter().map(|comment| comment.id).collect();
+        assert!(approved(&store, "post").is_empty());
+
+        moderate(&store, &ids[0], Status::Approved)
+            .unwrap()
+            .unwrap();
+        moderate(&store, &ids[1], Status::Rejected)
+            .unwrap()
+            .unwrap();
+        moderate(&store, &ids[2], Status::Approved)
+            .unwrap()
+            .unwrap();
+        let shown: Vec<String> = approved(&store, "post")
+            .into_iter()
+            .map(|comment| comment.author)
+            .collect();
+        assert_eq!(shown, ["Ann"]);
+
+        // Removing an approved comment later, which survives a restart
+        moderate(&store, &ids[0], Status::Rejected)
+            .unwrap()
+            .unwrap();
+        assert!(approved(&temp.open(), "post").is_empty());
+        assert!(
+            moderate(&store, "no-such-id", Status::Approved)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use actix_web::Result;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::StatusCode;
+use actix_web::http::header::ContentType;
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers};
+use actix_web::{HttpRequest, HttpResponse};
+use maud::{Markup, html};
+
+use super::{about, i18n, index, is_fragment_request};
+use crate::security::Nonce;
+
+const SUGGESTIONS: usize = 3;
+
+pub fn handlers<B: 'static>() -> ErrorHandlers<B> {
+    ErrorHandlers::new()
+        .handler(StatusCode::NOT_FOUND, not_found)
+        .default_handler_server(server_error)
+}
+
+fn not_found<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
+    let path = res.request().path();
+    let last_segment =
+        urlencoding::decode(path.trim_end_matches('/').rsplit('/').next().unwrap_or(""))
+            .map(|segment| segment.into_owned())
+            .unwrap_or_default();
+    let suggestions = about::similar_posts(&last_segment, SUGGESTIONS);
+    let strings = i18n::strings(language(res.request()));
+
+    let content = html! {
+        (heading("404", strings.page_not_found))
+        p { (strings.page_missing) }
+        @if !suggestions.is_empty() {
+            div class="space-y-2" {
+                p class="text-base-content/70" { (strings.did_you_mean) }
+                ul class="space-y-1" {
+                    @for (slug, title) in &suggestions {
+                        li {
+                            a class="link link-primary" href={"/post/" (slug)} { (title) }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    render(res, content)
+}
+
+fn server_error<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
+    let status = res.status();
+    let strings = i18n::strings(language(res.request()));
+    let content = html! {
+        (heading(status.as_str(), strings.something_went_wrong))
+        p { (strings.page_failed) }
+    };
+
+    render(res, content)
+}
+
+/// Language of the page that failed: translations live under `/{lang}/`,
+/// everything else is in the default language.
+fn language(req: &HttpRequest) -> &'static str {
+    let first = req.path().trim_start_matches('/').split('/').next();
+    i18n::languages()
+        .iter()
+        .find(|lang| first == Some(lang.as_str()) && i18n::is_translation(lang))
+        .map_or(i18n::default_language(), String::as_str)
+}
+
+fn heading(code: &str, title: &str) -> Markup {
+    html! {
+        p class="text-6xl font-bold text-base-content/30" { (code) }
+        h2 class="text-2xl font-semibold" { (title) }
+    }
+}
+
+/// Full page for normal and boosted navigation. Partial htmx requests get a
+/// fragment carrying the id of the element they target, so `outerHTML` swaps
+/// leave it addressable.
+fn error_page(req: &HttpRequest, content: Markup) -> Markup {
+    let body = html! {
+        div class="max-w-xl mx-auto text-center space-y-6 py-12" {
+            (content)
+            a href="/" class="btn btn-primary" { (i18n::strings(language(req)).back_to_home) }
+        }
+    };
+
+    if is_fragment_request(req) {
+        let target = req
+            .headers()
+            .get("HX-Target")
+            .and_then(|target| target.to_str().ok());
+        html! {
+            div id=[target] { (body) }
+        }
+    } else {
+        index(Some(body), &Nonce::of(req))
+    }
+}
+
+fn render<B>(res: ServiceResponse<B>, content: Markup) -> Result<ErrorHandlerResponse<B>> {
+    let (req, res) = res.into_parts();
+    let body = error_page(&req, content);
+
+    let res = HttpResponse::build(res.status())
+        .content_type(ContentType::html())
+        .body(body.into_string());
+
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, res).map_into_right_body(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, error, test, web};
+
+    use super::*;
+
+    async fn get(path: &str, headers: &[(&str, &str)]) -> (StatusCode, String) {
+        let app = test::init_service(
+            App::new()
+                .wrap(handlers())
+                .route(
+                    "/boom",
+                    web::get().to(|| async {
+                        Err::<HttpResponse, _>(error::ErrorInternalServerError(
+                            "database password is hunter2",
+                        ))
+                    }),
+                )
+                .default_service(web::to(HttpResponse::NotFound)),
+        )
+        .await;
+        let mut request = test::TestRequest::get().uri(path);
+        for &header in headers {
+            request = request.insert_header(header);
+        }
+        let response = test::call_service(&app, request.to_request()).await;
+        let status = response.status();
+        let body = test::read_body(response).await;
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn mistyped_slugs_suggest_the_post() {
+        let (status, body) = get("/post/my-own-wrld", &[]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.contains("Page not found"));
+        assert!(body.contains("Did you mean:"));
+        assert!(body.contains(r#"href="/post/my-own-world""#));
+    }
+
+    #[actix_web::test]
+    async fn unrelated_paths_suggest_nothing() {
+        let (status, body) = get("/zzzzzzzzzzzzzzzz", &[]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!body.contains("Did you mean:"));
+    }
+
+    #[actix_web::test]
+    async fn server_errors_do_not_leak_details() {
+        let (status, body) = get("/boom", &[]).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body.contains("Something went wrong"));
+        assert!(!body.contains("hunter2"));
+    }
+
+    #[actix_web::test]
+    async fn htmx_requests_get_a_fragment_for_their_target() {
+        let (_, body) = get("/boom", &[("HX-Request", "true"), ("HX-Target", "post")]).await;
+        assert!(body.starts_with(r#"<div id="post">"#));
+        assert!(!body.contains("<html"));
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+
+use crate::dates;
+use crate::html::escape;
+
+pub const CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+/// A post in a feed. URLs are absolute, feed readers resolve nothing.
+pub struct Entry {
+    pub title: String,
+    pub url: String,
+    pub published: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub authors: Vec<String>,
+    pub summary: String,
+    /// Rendered HTML; relative links in it resolve against `url`.
+    pub content: Option<String>,
+}
+
+pub struct Feed {
+    pub title: String,
+    /// The HTML page the feed mirrors.
+    pub url: String,
+    pub self_url: String,
+    pub entries: Vec<Entry>,
+}
+
+/// Renders the feed as Atom 1.0. The feed is as recent as its most recently
+/// updated entry; times carry the site's offset.
+pub fn atom(feed: &Feed) -> String {
+    let updated = feed
+        .entries
+        .iter()
+        .map(|entry| entry.updated)
+        .max()
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape(&feed.title)));
+    xml.push_str(&format!("  <id>{}</id>\n", escape(&feed.url)));
+    xml.push_str(&format!(
+        "  <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+        escape(&feed.url)
+    ));
+    xml.push_str(&format!(
+        "  <link rel=\"self\" type=\"application/atom+xml\" href=\"{}\"/>\n",
+        escape(&feed.self_url)
+    ));
+    xml.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        dates::local(updated).to_rfc3339()
+    ));
+
+    for entry in &feed.entries {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+        xml.push_str(&format!("    <id>{}</id>\n", escape(&entry.url)));
+        xml.push_str(&format!(
+            "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+            escape(&entry.url)
+        ));
+        xml.push_str(&format!(
+            "    <published>{}</published>\n",
+            dates::local(entry.published).to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            dates::local(entry.updated).to_rfc3339()
+        ));
+        for author in &entry.authors {
+            xml.push_str(&format!(
+                "    <author><name>{}</name></author>\n",
+                escape(author)
+            ));
+        }
+        xml.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            escape(&entry.summary)
+        ));
+        if let Some(content) = &entry.content {
+            xml.push_str(&format!(
+                "    <content type=\"html\" xml:base=\"{}\">{}</content>\n",
+                escape(&entry.url),
+                escape(content)
+            ));
+        }
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
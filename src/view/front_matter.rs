@@ -0,0 +1,88 @@
+use serde::Deserialize;
+
+/// Optional YAML block at the very top of a post, fenced by `---` lines.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    /// Publication time, overriding the date in the file name. Written with
+    /// an offset or in the site's time zone.
+    pub date: Option<String>,
+    /// Time of the last significant change.
+    pub updated: Option<String>,
+    /// Old slugs (`old-name`) or full paths (`/2024/old.html`) that redirect here.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Ids of the post's authors, the names of files in `authors/`.
+    #[serde(default)]
+    pub authors: Vec<String>,
+    /// Topics used to find related posts.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Name of the multi-part series the post belongs to.
+    pub series: Option<String>,
+    /// Position within the series; parts without one follow by date.
+    pub series_order: Option<u32>,
+}
+
+/// Splits a post into its raw front matter and the markdown body.
+pub fn split(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+
+    (None, content)
+}
+
+pub fn parse(yaml: &str) -> Result<FrontMatter, serde_yaml::Error> {
+    if yaml.trim().is_empty() {
+        return Ok(FrontMatter::default());
+    }
+    serde_yaml::from_str(yaml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_separates_front_matter() {
+        let (yaml, body) = split("---\ntitle: Hi\n---\n# Body\n");
+        assert_eq!(yaml, Some("title: Hi\n"));
+        assert_eq!(body, "# Body\n");
+
+        let (yaml, body) = split("---\r\ntitle: Hi\r\n---\r\nBody");
+        assert_eq!(yaml, Some("title: Hi\r\n"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn split_without_front_matter() {
+        assert_eq!(split("# Title\n"), (None, "# Title\n"));
+        // An unclosed block is part of the body
+        assert_eq!(split("---\ntitle: Hi\n"), (None, "---\ntitle: Hi\n"));
+    }
+
+    #[test]
+    fn parse_reads_fields() {
+        let front_matter = parse("title: Hello\nslug: custom\ntags: [rust]\n").unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(front_matter.slug.as_deref(), Some("custom"));
+        assert_eq!(front_matter.tags, vec!["rust"]);
+
+        assert!(parse("").unwrap().title.is_none());
+        assert!(parse("unknown: field\n").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+
+use crate::config;
+
+/// Interface text in one language. Languages without a table use English.
+pub struct Strings {
+    /// Name of the language in itself, for the language switcher.
+    pub name: &'static str,
+    pub next_post: &'static str,
+    pub previous_post: &'static str,
+    pub no_posts: &'static str,
+    pub by: &'static str,
+    pub updated_on: &'static str,
+    pub and: &'static str,
+    pub min_read: &'static str,
+    pub words: &'static str,
+    pub related_posts: &'static str,
+    pub part: &'static str,
+    pub parts: &'static str,
+    pub of: &'static str,
+    pub in_series: &'static str,
+    pub comments: &'static str,
+    pub no_comments: &'static str,
+    pub leave_comment: &'static str,
+    pub your_name: &'static str,
+    pub your_comment: &'static str,
+    pub markdown_hint: &'static str,
+    pub post_comment: &'static str,
+    pub awaiting_moderation: &'static str,
+    pub comment_invalid: &'static str,
+    pub comment_rate_limited: &'static str,
+    pub webmentions: &'static str,
+    pub likes: &'static str,
+    pub reposts: &'static str,
+    pub view_original: &'static str,
+    pub reactions: &'static str,
+    pub newsletter: &'static str,
+    pub newsletter_pitch: &'static str,
+    pub your_email: &'static str,
+    pub subscribe: &'static str,
+    pub check_inbox: &'static str,
+    pub email_invalid: &'static str,
+    pub subscribe_rate_limited: &'static str,
+    pub confirm_subject: &'static str,
+    pub confirm_email: &'static str,
+    pub confirm_ignore: &'static str,
+    pub subscribed: &'static str,
+    pub unsubscribe: &'static str,
+    pub unsubscribe_question: &'static str,
+    pub unsubscribed: &'static str,
+    pub new_posts: &'static str,
+    pub read_more: &'static str,
+    pub digest_footer: &'static str,
+    pub authors: &'static str,
+    pub post: &'static str,
+    pub posts: &'static str,
+    pub load_more: &'static str,
+    pub page_not_found: &'static str,
+    pub page_missing: &'static str,
+    pub did_you_mean: &'static str,
+    pub something_went_wrong: &'static str,
+    pub page_failed: &'static str,
+    pub back_to_home: &'static str,
+    months: [&'static str; 12],
+    format_date: fn(&Strings, DateTime<FixedOffset>) -> String,
+}
+
+static EN: Strings = Strings {
+    name: "English",
+    next_post: "Next Post",
+    previous_post: "Previous Post",
+    no_posts: "No posts available yet.",
+    by: "By",
+    updated_on: "Updated on",
+    and: "and",
+    min_read: "min read",
+    words: "words",
+    related_posts: "Related posts",
+    part: "Part",
+    parts: "parts",
+    of: "of",
+    in_series: "in",
+    comments: "Comments",
+    no_comments: "No comments yet.",
+    leave_comment: "Leave a comment",
+    your_name: "Name",
+    your_comment: "Comment",
+    markdown_hint: "Markdown works: *emphasis*, **bold**, `code`, links and lists.",
+    post_comment: "Post comment",
+    awaiting_moderation: "Thanks! Your comment shows up once it has been approved.",
+    comment_invalid: "Please enter your name and a comment that is not too long.",
+    comment_rate_limited: "You have commented a lot recently, please try again later.",
+    webmentions: "Around the web",
+    likes: "likes",
+    reposts: "reposts",
+    view_original: "View original",
+    reactions: "Reactions",
+    newsletter: "Newsletter",
+    newsletter_pitch: "Get new posts by email. Unsubscribe any time.",
+    your_email: "Email address",
+    subscribe: "Subscribe",
+    check_inbox: "Almost done! Please confirm with the link we just emailed you.",
+    email_invalid: "Please enter a valid email address.",
+    subscribe_rate_limited: "Too many attempts, please try again later.",
+    confirm_subject: "Please confirm your subscription",
+    confirm_email: "Please confirm that you want to get new posts by email:",
+    confirm_ignore: "If you did not ask for this, ignore this email and nothing happens.",
+    subscribed: "You are subscribed. New posts will arrive by email.",
+    unsubscribe: "Unsubscribe",
+    unsubscribe_question: "Stop getting new posts by email?",
+    unsubscribed: "You are unsubscribed and will get no more emails.",
+    new_posts: "New posts",
+    read_more: "Read more",
+    digest_footer: "You get this email because you subscribed to new posts.",
+    authors: "Authors",
+    post: "post",
+    posts: "posts",
+    load_more: "Load more",
+    page_not_found: "Page not found",
+    page_missing: "The page you are looking for does not exist or has moved.",
+    did_you_mean: "Did you mean:",
+    something_went_wrong: "Something went wrong",
+    page_failed: "The page could not be rendered. Please try again later.",
+    back_to_home: "Back to home",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    // January 05, 2026
+    format_date: |strings, date| {
+        format!("{} {:02}, {}", strings.month(date), date.day(), date.year())
+    },
+};
+
+static DE: Strings = Strings {
+    name: "Deutsch",
+    next_post: "Nächster Beitrag",
+    previous_post: "Vorheriger Beitrag",
+    no_posts: "Noch keine Beiträge vorhanden.",
+    by: "Von",
+    updated_on: "Aktualisiert am",
+    and: "und",
+    min_read: "Min. Lesezeit",
+    words: "Wörter",
+    related_posts: "Ähnliche Beiträge",
+    part: "Teil",
+    parts: "Teile",
+    of: "von",
+    in_series: "in",
+    comments: "Kommentare",
+    no_comments: "Noch keine Kommentare.",
+    leave_comment: "Kommentar schreiben",
+    your_name: "Name",
+    your_comment: "Kommentar",
+    markdown_hint: "Markdown geht: *kursiv*, **fett**, `Code`, Links und Listen.",
+    post_comment: "Kommentar senden",
+    awaiting_moderation: "Danke! Dein Kommentar erscheint, sobald er freigegeben wurde.",
+    comment_invalid: "Bitte gib deinen Namen und einen nicht zu langen Kommentar ein.",
+    comment_rate_limited: "Du hast gerade viel kommentiert, bitte versuche es später noch einmal.",
+    webmentions: "Im Web",
+    likes: "Likes",
+    reposts: "Reposts",
+    view_original: "Original ansehen",
+    reactions: "Reaktionen",
+    newsletter: "Newsletter",
+    newsletter_pitch: "Neue Beiträge per E-Mail. Jederzeit abbestellbar.",
+    your_email: "E-Mail-Adresse",
+    subscribe: "Abonnieren",
+    check_inbox: "Fast geschafft! Bitte bestätige mit dem Link, den wir dir gerade geschickt haben.",
+    email_invalid: "Bitte gib eine gültige E-Mail-Adresse ein.",
+    subscribe_rate_limited: "Zu viele Versuche, bitte versuche es später noch einmal.",
+    confirm_subject: "Bitte bestätige dein Abonnement",
+    confirm_email: "Bitte bestätige, dass du neue Beiträge per E-Mail bekommen möchtest:",
+    confirm_ignore: "Wenn du das nicht angefordert hast, ignoriere diese E-Mail einfach.",
+    subscribed: "Du hast den Newsletter abonniert. Neue Beiträge kommen per E-Mail.",
+    unsubscribe: "Abbestellen",
+    unsubscribe_question: "Keine neuen Beiträge mehr per E-Mail bekommen?",
+    unsubscribed: "Du hast den Newsletter abbestellt und bekommst keine E-Mails mehr.",
+    new_posts: "Neue Beiträge",
+    read_more: "Weiterlesen",
+    digest_footer: "Du bekommst diese E-Mail, weil du neue Beiträge abonniert hast.",
+    authors: "Autoren",
+    post: "Beitrag",
+    posts: "Beiträge",
+    load_more: "Mehr laden",
+    page_not_found: "Seite nicht gefunden",
+    page_missing: "Die gesuchte Seite gibt es nicht oder sie ist umgezogen.",
+    did_you_mean: "Meintest du:",
+    something_went_wrong: "Etwas ist schiefgelaufen",
+    page_failed: "Die Seite konnte nicht angezeigt werden. Bitte versuche es später noch einmal.",
+    back_to_home: "Zur Startseite",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    // 5. Januar 2026
+    format_date: |strings, date| format!("{}. {} {}", date.day(), strings.month(date), date.year()),
+};
+
+impl Strings {
+    fn month(&self, date: DateTime<FixedOffset>) -> &'static str {
+        self.months[date.month0() as usize]
+    }
+
+    pub fn date(&self, date: DateTime<FixedOffset>) -> String {
+        (self.format_date)(self, date)
+    }
+}
+
+pub fn strings(lang: &str) -> &'static Strings {
+    match lang {
+        "de" => &DE,
+        _ => &EN,
+    }
+}
+
+/// Configured content languages, the default one first.
+pub fn languages() -> &'static [String] {
+    config::get().languages()
+}
+
+/// Language posts without a language suffix are written in.
+pub fn default_language() -> &'static str {
+    &languages()[0]
+}
+
+/// `lang` if it is one of the site's languages, the default otherwise.
+pub fn known(lang: Option<&str>) -> &str {
+    lang.filter(|lang| languages().iter().any(|known| known == lang))
+        .unwrap_or(default_language())
+}
+
+/// Languages posts can be translated into, served under `/{lang}/`.
+pub fn is_translation(lang: &str) -> bool {
+    lang != default_language() && languages().iter().any(|known| known == lang)
+}
+
+/// Name of a language for the switcher, its code if there is no table for it.
+pub fn name(lang: &str) -> String {
+    match lang {
+        "en" | "de" => strings(lang).name.to_string(),
+        _ => lang.to_uppercase(),
+    }
+}
@@ -1,21 +1,92 @@
 use actix_web::{HttpRequest, Result as AwResult};
 use actix_web::{get, web};
 use maud::{Markup, html};
+use serde::Deserialize;
 
 pub mod about;
+pub mod admin;
+pub mod analytics;
+pub mod authors;
+pub mod comments;
+pub mod errors;
+mod feed;
+mod front_matter;
+pub mod i18n;
 mod icons;
 mod navbar;
+pub mod newsletter;
+pub mod reactions;
+mod related;
+mod render;
+pub mod shortcodes;
+pub mod sitemap;
+pub mod webmentions;
 
-use crate::config::Server;
+use crate::config::{Home, Server};
+use crate::security::Nonce;
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    page: Option<usize>,
+}
 
 #[get("/")]
-pub async fn index_route(_server: web::Data<Server>, _req: HttpRequest) -> AwResult<Markup> {
-    Ok(index(Some(about::posts())))
+pub async fn index_route(
+    server: web::Data<Server>,
+    req: HttpRequest,
+    query: web::Query<PageQuery>,
+    nonce: Nonce,
+) -> AwResult<Markup> {
+    let content = match server.home() {
+        Home::Latest => about::posts()?,
+        Home::List => {
+            let page = query.page.unwrap_or(1);
+            if is_fragment_request(&req) {
+                return Ok(about::post_page(page, server.page_size()));
+            }
+            about::post_list(page, server.page_size())?
+        }
+    };
+    Ok(index(Some(content), &nonce))
 }
 
 #[get("/about")]
-pub async fn about_readme_endpoint() -> AwResult<Markup> {
-    Ok(index(Some(about::readme())))
+pub async fn about_readme_endpoint(nonce: Nonce) -> AwResult<Markup> {
+    Ok(index(Some(about::readme()?), &nonce))
+}
+
+/// True for htmx requests that swap part of the page. Boosted navigation
+/// replaces the whole body and needs the full layout.
+pub fn is_fragment_request(req: &HttpRequest) -> bool {
+    let headers = req.headers();
+    headers.contains_key("HX-Request") && !headers.contains_key("HX-Boosted")
+}
+
+/// Language of a page and the absolute URLs of its translations, default
+/// language first. Pages without translations have no alternates.
+pub struct Locale {
+    pub lang: String,
+    pub alternates: Vec<(String, String)>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            lang: i18n::default_language().to_string(),
+            alternates: Vec::new(),
+        }
+    }
+}
+
+/// Error responses are swapped like any other so themed error fragments show up.
+fn htmx_config(nonce: &Nonce) -> String {
+    let nonce = nonce
+        .value()
+        .map(|nonce| format!(r#""inlineScriptNonce":"{nonce}","#))
+        .unwrap_or_default();
+    format!(
+        r#"{{{nonce}"responseHandling":[{{"code":"204","swap":false}},{{"code":"...","swap":true}}]}}"#
+    )
 }
 
 pub fn css(path: impl Into<String>) -> Markup {
@@ -23,39 +94,55 @@ pub fn css(path: impl Into<String>) -> Markup {
     html! {link href=(path) rel="stylesheet" type="text/css";}
 }
 
-pub fn js(path: impl Into<String>) -> Markup {
+pub fn js(path: impl Into<String>, nonce: &Nonce) -> Markup {
     let path: String = path.into();
-    html! {script src=(path) {}}
+    html! {script src=(path) nonce=[nonce.value()] {}}
+}
+
+pub fn index(content: Option<Markup>, nonce: &Nonce) -> Markup {
+    localized(content, nonce, &Locale::default())
 }
 
-pub fn index(content: Option<Markup>) -> Markup {
-    let content = content.unwrap_or_else(about::readme);
+pub fn localized(content: Option<Markup>, nonce: &Nonce, locale: &Locale) -> Markup {
+    let content = content.unwrap_or_else(|| about::readme().unwrap_or_else(|_| html! {}));
     html! {
         (maud::DOCTYPE)
-        head {
-            meta charset="UTF-8";
-            meta name="viewport" content="width=device-width, initial-scale=1.0";
-            title {
-                "Focus"
-            }
-            (js("/assets/tw.js"))
-            (js("/assets/theme-switcher.js"))
-            (js("/assets/htmx.js"))
-            (css("/assets/daisy.css"))
-            (css("/assets/themes.css"))
-            (css("/assets/app.css"))
-            link rel="icon" href="/assets/grocy.svg" sizes="any" type="image/svg+xml" {}
+        html lang=(locale.lang) {
+            head {
+                meta charset="UTF-8";
+                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                title {
+                    "Focus"
+                }
+                meta name="htmx-config" content=(htmx_config(nonce));
+                (js("/assets/tw.js", nonce))
+                (js("/assets/theme-switcher.js", nonce))
+                (js("/assets/htmx.js", nonce))
+                (css("/assets/daisy.css"))
+                (css("/assets/themes.css"))
+                (css("/assets/app.css"))
+                link rel="icon" href="/assets/grocy.svg" sizes="any" type="image/svg+xml" {}
+                @if let Some(endpoint) = webmentions::endpoint_link() {
+                    (endpoint)
+                }
+                @for (lang, url) in &locale.alternates {
+                    link rel="alternate" hreflang=(lang) href=(url);
+                }
+                @if let Some((_, url)) = locale.alternates.first() {
+                    link rel="alternate" hreflang="x-default" href=(url);
+                }
 
-        }
-        body hx-boost="true" class="overflow-hidden" {
-            (js("/assets/htmxListener.js"))
-            (js("/assets/htmx-reload.js"))
+            }
+            body hx-boost="true" class="overflow-hidden" {
+                (js("/assets/htmxListener.js", nonce))
+                (js("/assets/htmx-reload.js", nonce))
 
 
-            div class="h-screen bg-base-100 flex flex-col" {
-                (navbar::render())
-                main class="container mx-auto px-4 py-6 flex-1 overflow-y-auto" {
-                    (content)
+                div class="h-screen bg-base-100 flex flex-col" {
+                    (navbar::render(locale))
+                    main class="container mx-auto px-4 py-6 flex-1 overflow-y-auto" {
+                        (content)
+                    }
                 }
             }
         }
@@ -1,14 +1,36 @@
 use maud::{Markup, html};
 
+use crate::view::Locale;
+use crate::view::i18n;
 use crate::view::icons::{info_icon, moon_icon, read_icon, sun_icon};
 
-pub fn render() -> Markup {
+pub fn render(locale: &Locale) -> Markup {
     html! {
-       (navbar())
+       (navbar(locale))
     }
 }
 
-fn navbar() -> Markup {
+/// Links to the translations of the current page. Fragment responses send
+/// it out of band so the switcher follows htmx navigation between posts.
+pub fn language_switcher(locale: &Locale, out_of_band: bool) -> Markup {
+    html! {
+        div id="language-switcher" class="flex items-center gap-2 text-sm"
+            hx-swap-oob=[out_of_band.then_some("true")] {
+            @for (lang, url) in &locale.alternates {
+                @if *lang == locale.lang {
+                    span class="font-semibold text-base-content" aria-current="true" { (i18n::name(lang)) }
+                } @else {
+                    a href=(url) hreflang=(lang) lang=(lang)
+                        class="text-base-content/70 hover:text-base-content transition-colors" {
+                        (i18n::name(lang))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn navbar(locale: &Locale) -> Markup {
     html! {
         nav class="bg-base-100 border-b border-base-200 px-6 py-4" {
             div class="max-m-[80rem] mx-auto flex items-center justify-between" {
@@ -26,7 +48,7 @@ fn navbar() -> Markup {
                 div class="hidden lg:flex items-center gap-8" {
 
                     label class="swap swap-rotate flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2 cursor-pointer" {
-                        input type="checkbox" class="theme-controller hidden" value="swiss" onclick="toggleTheme()" {}
+                        input type="checkbox" class="theme-controller hidden" value="swiss" {}
                         span class="swap-on w-8 h-8 flex items-center justify-center opacity-60" {
                             (sun_icon())
                         }
@@ -37,6 +59,7 @@ fn navbar() -> Markup {
                 }
 
                 div class="flex items-center gap-4" {
+                    (language_switcher(locale, false))
 
                     div class="dropdown dropdown-end lg:hidden" {
                             div tabindex="0" role="button" class="p-2 text-base-content/70 hover:text-base-content transition-colors" {
@@ -63,7 +86,7 @@ fn navbar() -> Markup {
                                 }
                                 li {
                                     label class="swap swap-rotate flex items-center gap-3 px-4 py-2 rounded-lg hover:bg-base-200 transition-colors w-full cursor-pointer" {
-                                        input type="checkbox" class="theme-controller hidden" value="swiss" onclick="toggleTheme()" {}
+                                        input type="checkbox" class="theme-controller hidden" value="swiss" {}
                                         span class="swap-on w-4 h-4 flex items-center opacity-60" {
                                             (sun_icon())
                                         }
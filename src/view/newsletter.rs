@@ -0,0 +1,512 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use actix_web::http::{StatusCode, header};
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, error, get, post, web};
+use chrono::{DateTime, SubsecRound, Utc};
+use lazy_static::lazy_static;
+use maud::{Markup, html};
+use serde::{Deserialize, Serialize};
+
+use super::{about, i18n};
+use crate::config::{self, Server};
+use crate::newsletter::mail::{self, Email, Mailer, SmtpMailer};
+use crate::rate_limit::{self, RateLimit};
+use crate::security::Nonce;
+use crate::store::{Record, Store};
+
+const SUBSCRIBERS_FILE: &str = "subscribers.jsonl";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// Signed up, but has not followed the link in the confirmation yet.
+    Pending,
+    Confirmed,
+    Unsubscribed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Subscriber {
+    /// Lowercased, one subscription per address.
+    email: String,
+    /// Secret in the confirmation and unsubscribe links. Subscribing again
+    /// after unsubscribing gets a new one.
+    token: String,
+    lang: String,
+    created: DateTime<Utc>,
+    confirmed: Option<DateTime<Utc>>,
+    status: Status,
+}
+
+impl Record for Subscriber {
+    fn id(&self) -> &str {
+        &self.email
+    }
+}
+
+lazy_static! {
+    static ref SUBSCRIBERS: Store<Subscriber> = {
+        let path = config::get().data_dir().join(SUBSCRIBERS_FILE);
+        Store::open(&path)
+            .unwrap_or_else(|err| panic!("could not open subscribers at {}: {err}", path.display()))
+    };
+    static ref MAILER: SmtpMailer = SmtpMailer::new(settings())
+        .unwrap_or_else(|err| panic!("could not set up newsletter email: {err}"));
+    static ref SIGNUPS: RateLimit = RateLimit::new(5, Duration::from_secs(60 * 60));
+}
+
+fn settings() -> &'static config::Newsletter {
+    config::get().newsletter()
+}
+
+pub fn prepare() {
+    if settings().enabled {
+        lazy_static::initialize(&MAILER);
+        log::info!(
+            "Loaded {} newsletter subscribers from {}",
+            SUBSCRIBERS.all().len(),
+            SUBSCRIBERS.path().display()
+        );
+    }
+}
+
+/// A confirmed subscriber the digest goes to.
+pub struct Recipient {
+    pub email: String,
+    pub lang: String,
+    pub token: String,
+}
+
+/// Confirmed subscribers in `data_dir`. Reads the file without opening it
+/// for writing, as the server may be appending to it meanwhile.
+pub fn recipients(data_dir: &Path) -> io::Result<Vec<Recipient>> {
+    let subscribers = Store::<Subscriber>::read(data_dir.join(SUBSCRIBERS_FILE))?;
+    Ok(subscribers
+        .into_iter()
+        .filter(|subscriber| subscriber.status == Status::Confirmed)
+        .map(|subscriber| Recipient {
+            email: subscriber.email,
+            lang: subscriber.lang,
+            token: subscriber.token,
+        })
+        .collect())
+}
+
+pub fn unsubscribe_url(site: &str, token: &str) -> String {
+    format!("{site}/newsletter/unsubscribe/{token}")
+}
+
+/// Feedback shown in place of the form, or above it.
+enum Notice {
+    CheckInbox,
+    Invalid,
+    RateLimited,
+}
+
+fn section(slug: &str, lang: &str, notice: Option<Notice>, draft: Option<&str>) -> Markup {
+    let strings = i18n::strings(lang);
+    html! {
+        section id="newsletter" class="card bg-base-200" {
+            div class="card-body gap-3" {
+                h2 class="card-title" { (strings.newsletter) }
+                @match notice {
+                    Some(Notice::CheckInbox) => div role="status" class="alert alert-success" { (strings.check_inbox) },
+                    Some(Notice::Invalid) => div role="alert" class="alert alert-error" { (strings.email_invalid) },
+                    Some(Notice::RateLimited) => div role="alert" class="alert alert-warning" { (strings.subscribe_rate_limited) },
+                    None => p class="text-base-content/70" { (strings.newsletter_pitch) },
+                }
+                @if !matches!(notice, Some(Notice::CheckInbox)) {
+                    form method="post" action="/newsletter" hx-post="/newsletter" hx-target="#newsletter"
+                        hx-swap="outerHTML" class="flex flex-col gap-2 sm:flex-row" {
+                        input type="hidden" name="slug" value=(slug);
+                        input type="hidden" name="lang" value=(lang);
+                        // Honeypot: hidden from people, filled in by form-spamming bots
+                        div class="hidden" aria-hidden="true" {
+                            label { "Website" input type="text" name="website" tabindex="-1" autocomplete="off"; }
+                        }
+                        input type="email" name="email" class="input input-bordered w-full" required
+                            maxlength="254" autocomplete="email" placeholder=(strings.your_email)
+                            aria-label=(strings.your_email) value=[draft];
+                        button type="submit" class="btn btn-primary" { (strings.subscribe) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The signup form under a post.
+pub fn form(slug: &str, lang: &str) -> Option<Markup> {
+    settings().enabled.then(|| section(slug, lang, None, None))
+}
+
+fn confirmation(to: &str, url: &str, lang: &str) -> Email {
+    let strings = i18n::strings(lang);
+    Email {
+        to: to.to_string(),
+        subject: strings.confirm_subject.to_string(),
+        text: format!(
+            "{}\n\n{url}\n\n{}\n",
+            strings.confirm_email, strings.confirm_ignore
+        ),
+        html: html! {
+            p { (strings.confirm_email) }
+            p { a href=(url) { (url) } }
+            p { (strings.confirm_ignore) }
+        }
+        .into_string(),
+        unsubscribe: None,
+    }
+}
+
+/// Stores `email` as awaiting confirmation and returns the token to confirm
+/// with, or `None` when the address is confirmed already. Asking again
+/// before confirming keeps the token of the first email.
+fn sign_up(subscribers: &Store<Subscriber>, email: &str, lang: &str) -> io::Result<Option<String>> {
+    let existing = subscribers.get(email);
+    if existing
+        .as_ref()
+        .is_some_and(|subscriber| subscriber.status == Status::Confirmed)
+    {
+        return Ok(None);
+    }
+    let token = existing
+        .filter(|subscriber| subscriber.status == Status::Pending)
+        .map(|subscriber| subscriber.token)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    subscribers.put(Subscriber {
+        email: email.to_string(),
+        token: token.clone(),
+        lang: lang.to_string(),
+        created: Utc::now().trunc_subsecs(0),
+        confirmed: None,
+        status: Status::Pending,
+    })?;
+    Ok(Some(token))
+}
+
+#[derive(Deserialize)]
+pub struct Signup {
+    email: String,
+    slug: String,
+    #[serde(default)]
+    website: String,
+    lang: Option<String>,
+}
+
+/// Signs an address up and mails it a link to confirm with. Addresses that
+/// are subscribed already get the same answer, so the form does not tell
+/// who reads along.
+#[post("/newsletter")]
+pub async fn subscribe_route(
+    server: web::Data<Server>,
+    form: web::Form<Signup>,
+    req: HttpRequest,
+) -> AwResult<HttpResponse> {
+    // Links in emails always point at the configured address, never at the
+    // host a request names
+    let site = match server.site_url() {
+        Some(site) if settings().enabled && about::has_post(&form.slug) => site,
+        _ => return Ok(HttpResponse::NotFound().body("Not found")),
+    };
+    let lang = i18n::known(form.lang.as_deref());
+    let client = rate_limit::client(&req);
+    let respond = |status: StatusCode, notice: Notice, draft: Option<&str>| {
+        HttpResponse::build(status)
+            .content_type("text/html")
+            .body(section(&form.slug, lang, Some(notice), draft).into_string())
+    };
+
+    let email = form.email.trim().to_lowercase();
+    if !form.website.is_empty() {
+        log::info!("Dropped newsletter signup from {client}: honeypot filled in");
+    } else if !SIGNUPS.allow(&client) {
+        return Ok(respond(
+            StatusCode::TOO_MANY_REQUESTS,
+            Notice::RateLimited,
+            Some(&form.email),
+        ));
+    } else if !mail::valid_address(&email) {
+        return Ok(respond(
+            StatusCode::BAD_REQUEST,
+            Notice::Invalid,
+            Some(&form.email),
+        ));
+    } else {
+        let (address, language) = (email.clone(), lang.to_string());
+        let token = web::block(move || sign_up(&SUBSCRIBERS, &address, &language)).await?;
+        if let Some(token) = saved(token)? {
+            let url = format!("{site}/newsletter/confirm/{token}");
+            let email = confirmation(&email, &url, lang);
+            actix_web::rt::spawn(async move {
+                match web::block(move || MAILER.send(&email)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => log::error!("Could not send newsletter confirmation: {err}"),
+                    Err(err) => log::error!("Newsletter confirmation failed: {err}"),
+                }
+            });
+        }
+    }
+
+    if !super::is_fragment_request(&req) {
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((
+                header::LOCATION,
+                format!("{}#newsletter", about::post_url(&form.slug, lang)),
+            ))
+            .finish());
+    }
+    Ok(respond(StatusCode::OK, Notice::CheckInbox, None))
+}
+
+fn message(title: &str, text: &str) -> Markup {
+    html! {
+        div class="max-w-prose mx-auto space-y-4" {
+            h1 class="text-2xl font-bold" { (title) }
+            p { (text) }
+        }
+    }
+}
+
+fn by_token(subscribers: &Store<Subscriber>, token: &str) -> Option<Subscriber> {
+    subscribers
+        .all()
+        .into_iter()
+        .find(|subscriber| subscriber.token == token && subscriber.status != Status::Unsubscribed)
+}
+
+fn saved<T>(result: io::Result<T>) -> AwResult<T> {
+    result.map_err(|err| {
+        log::error!("Could not store subscriber: {err}");
+        error::ErrorInternalServerError("Could not save subscription")
+    })
+}
+
+/// Confirms the subscription `token` belongs to, if it is still active.
+fn confirm(subscribers: &Store<Subscriber>, token: &str) -> io::Result<Option<Subscriber>> {
+    let Some(mut subscriber) = by_token(subscribers, token) else {
+        return Ok(None);
+    };
+    if subscriber.status == Status::Pending {
+        subscriber.status = Status::Confirmed;
+        subscriber.confirmed = Some(Utc::now().trunc_subsecs(0));
+        subscribers.put(subscriber.clone())?;
+    }
+    Ok(Some(subscriber))
+}
+
+/// Ends the subscription `token` belongs to, if it is still active.
+fn unsubscribe(subscribers: &Store<Subscriber>, token: &str) -> io::Result<Option<Subscriber>> {
+    let Some(subscriber) = by_token(subscribers, token) else {
+        return Ok(None);
+    };
+    let subscriber = Subscriber {
+        status: Status::Unsubscribed,
+        ..subscriber
+    };
+    subscribers.put(subscriber.clone())?;
+    Ok(Some(subscriber))
+}
+
+#[get("/newsletter/confirm/{token}")]
+pub async fn confirm_route(
+    path: web::Path<String>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
+    if !settings().enabled {
+        return Ok(HttpResponse::NotFound().body("Link not found"));
+    }
+    let token = path.into_inner();
+    let confirmed = web::block(move || confirm(&SUBSCRIBERS, &token)).await?;
+    let Some(subscriber) = saved(confirmed)? else {
+        return Ok(HttpResponse::NotFound().body("Link not found"));
+    };
+    let strings = i18n::strings(&subscriber.lang);
+    Ok(about::page(
+        message(strings.newsletter, strings.subscribed),
+        &req,
+        &nonce,
+    ))
+}
+
+/// Asks before unsubscribing, as link scanners in mail filters open every
+/// link they see.
+#[get("/newsletter/unsubscribe/{token}")]
+pub async fn unsubscribe_page_route(
+    path: web::Path<String>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> HttpResponse {
+    let Some(subscriber) = by_token(&SUBSCRIBERS, &path) else {
+        return HttpResponse::NotFound().body("Link not found");
+    };
+    let strings = i18n::strings(&subscriber.lang);
+    let action = format!("/newsletter/unsubscribe/{}", subscriber.token);
+    about::page(
+        html! {
+            div class="max-w-prose mx-auto space-y-4" {
+                h1 class="text-2xl font-bold" { (strings.newsletter) }
+                p { (strings.unsubscribe_question) }
+                form method="post" action=(action) {
+                    button type="submit" class="btn btn-primary" { (strings.unsubscribe) }
+                }
+            }
+        },
+        &req,
+        &nonce,
+    )
+}
+
+/// Unsubscribes from the page above and from the one-click button of mail
+/// clients, which posts `List-Unsubscribe=One-Click` here.
+#[post("/newsletter/unsubscribe/{token}")]
+pub async fn unsubscribe_route(
+    path: web::Path<String>,
+    req: HttpRequest,
+    nonce: Nonce,
+) -> AwResult<HttpResponse> {
+    let token = path.into_inner();
+    let unsubscribed = web::block(move || unsubscribe(&SUBSCRIBERS, &token)).await?;
+    let Some(subscriber) = saved(unsubscribed)? else {
+        return Ok(HttpResponse::NotFound().body("Link not found"));
+    };
+    let strings = i18n::strings(&subscriber.lang);
+    Ok(about::page(
+        message(strings.newsletter, strings.unsubscribed),
+        &req,
+        &nonce,
+    ))
+}
+
+/// Subscriber counts for the admin area.
+pub fn summary() -> Markup {
+    if !settings().enabled {
+        return html! {};
+    }
+    let subscribers = SUBSCRIBERS.all();
+    let count = |status: Status| {
+        subscribers
+            .iter()
+            .filter(|subscriber| subscriber.status == status)
+            .count()
+    };
+    html! {
+        section class="space-y-3" {
+            h2 class="text-lg font-semibold" { "Newsletter" }
+            p class="text-base-content/70" {
+                (count(Status::Confirmed)) " confirmed, "
+                (count(Status::Pending)) " awaiting confirmation, "
+                (count(Status::Unsubscribed)) " unsubscribed. "
+                "Send new posts with " code { "focus newsletter send" } "."
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::newsletter::mail::Outbox;
+
+    /// A fresh data directory, removed again when dropped.
+    struct DataDir(PathBuf);
+
+    impl DataDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("focus-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            DataDir(dir)
+        }
+
+        fn store(&self) -> Store<Subscriber> {
+            Store::open(self.0.join(SUBSCRIBERS_FILE)).unwrap()
+        }
+    }
+
+    impl Drop for DataDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn confirm_and_unsubscribe_by_token() {
+        let dir = DataDir::new("newsletter-flow");
+        let subscribers = dir.store();
+        let email = "reader@example.com";
+
+        let token = sign_up(&subscribers, email, "de").unwrap().unwrap();
+        // Asking again before confirming sends the same link
+        assert_eq!(
+            sign_up(&subscribers, email, "de").unwrap(),
+            Some(token.clone())
+        );
+        assert!(recipients(&dir.0).unwrap().is_empty());
+
+        let outbox = Outbox::default();
+        let url = format!("https://blog.example/newsletter/confirm/{token}");
+        outbox.send(&confirmation(email, &url, "de")).unwrap();
+        let sent = outbox.sent.lock().unwrap();
+        assert_eq!(sent[0].to, email);
+        assert!(sent[0].text.contains(&url) && sent[0].html.contains(&url));
+        assert!(sent[0].unsubscribe.is_none());
+
+        assert!(confirm(&subscribers, "no-such-token").unwrap().is_none());
+        let confirmed = confirm(&subscribers, &token).unwrap().unwrap();
+        assert_eq!(confirmed.status, Status::Confirmed);
+        assert!(confirmed.confirmed.is_some());
+        // Following the link twice changes nothing
+        assert_eq!(
+            confirm(&subscribers, &token).unwrap().unwrap().confirmed,
+            confirmed.confirmed
+        );
+        // Confirmed addresses get no new email
+        assert_eq!(sign_up(&subscribers, email, "de").unwrap(), None);
+
+        let recipients = recipients(&dir.0).unwrap();
+        assert_eq!(recipients.len(), 1);
+        assert_eq!(recipients[0].email, email);
+        assert_eq!(recipients[0].lang, "de");
+        assert_eq!(recipients[0].token, token);
+
+        let gone = unsubscribe(&subscribers, &token).unwrap().unwrap();
+        assert_eq!(gone.status, Status::Unsubscribed);
+        assert!(unsubscribe(&subscribers, &token).unwrap().is_none());
+        assert!(confirm(&subscribers, &token).unwrap().is_none());
+        assert!(super::recipients(&dir.0).unwrap().is_empty());
+
+        // Subscribing again takes a new link
+        let again = sign_up(&subscribers, email, "en").unwrap().unwrap();
+        assert_ne!(again, token);
+        assert!(by_token(&subscribers, &token).is_none());
+    }
+
+    #[test]
+    fn recipients_leave_the_file_alone() {
+        let dir = DataDir::new("newsletter-read");
+        assert!(recipients(&dir.0).unwrap().is_empty());
+        assert!(!dir.0.exists());
+
+        let subscribers = dir.store();
+        for _ in 0..1500 {
+            sign_up(&subscribers, "reader@example.com", "en").unwrap();
+        }
+        let path = dir.0.join(SUBSCRIBERS_FILE);
+        let before = std::fs::read_to_string(&path).unwrap();
+        assert!(recipients(&dir.0).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn unsubscribe_links_point_at_the_site() {
+        assert_eq!(
+            unsubscribe_url("https://blog.example", "abc"),
+            "https://blog.example/newsletter/unsubscribe/abc"
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_session::Session;
+use actix_web::http::{StatusCode, header};
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, error, get, post, web};
+use lazy_static::lazy_static;
+use maud::{Markup, html};
+use serde::{Deserialize, Serialize};
+
+use super::{about, i18n};
+use crate::config;
+use crate::rate_limit::{self, RateLimit};
+use crate::store::{Record, Store};
+
+/// What readers can react with: the name forms send, and the emoji.
+const REACTIONS: [(&str, &str); 4] = [
+    ("like", "👍"),
+    ("love", "❤️"),
+    ("insightful", "💡"),
+    ("celebrate", "🎉"),
+];
+/// Session key of the reactions a reader gave, as `{slug} {name}`.
+const GIVEN: &str = "reactions";
+/// Reactions a session remembers, so the cookie stays under its size limit.
+/// Older ones can be given again.
+const REMEMBERED: usize = 100;
+/// Posts listed in the admin area.
+const TOP_POSTS: usize = 20;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Tally {
+    post: String,
+    /// Readers per reaction name.
+    counts: BTreeMap<String, u64>,
+}
+
+impl Record for Tally {
+    fn id(&self) -> &str {
+        &self.post
+    }
+}
+
+lazy_static! {
+    static ref TALLIES: Store<Tally> = {
+        let path = config::get().data_dir().join("reactions.jsonl");
+        Store::open(&path)
+            .unwrap_or_else(|err| panic!("could not open reactions at {}: {err}", path.display()))
+    };
+    /// Held from reading a tally to writing it back, so no reaction is lost.
+    static ref UPDATE: Mutex<()> = Mutex::new(());
+    static ref REACTED: RateLimit = RateLimit::new(60, Duration::from_secs(60 * 60));
+}
+
+fn enabled() -> bool {
+    config::get().reactions()
+}
+
+pub fn prepare() {
+    if enabled() {
+        log::info!(
+            "Loaded reactions to {} posts from {}",
+            TALLIES.all().len(),
+            TALLIES.path().display()
+        );
+    }
+}
+
+/// Where the reactions of a post go. They load once the post is shown, as
+/// the buttons show what this reader gave.
+pub fn placeholder(slug: &str, lang: &str) -> Option<Markup> {
+    enabled().then(|| {
+        html! {
+            div id="reactions"
+                hx-get={"/reactions/" (slug) "?lang=" (lang)}
+                hx-trigger="load"
+                hx-swap="outerHTML" {}
+        }
+    })
+}
+
+fn counts(slug: &str) -> BTreeMap<String, u64> {
+    TALLIES
+        .get(slug)
+        .map(|tally| tally.counts)
+        .unwrap_or_default()
+}
+
+fn given(session: &Session) -> Vec<String> {
+    session
+        .get::<Vec<String>>(GIVEN)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+fn bar(slug: &str, lang: &str, given: &[String]) -> Markup {
+    let counts = counts(slug);
+    let action = format!("/post/{slug}/react");
+    html! {
+        div id="reactions" role="group" aria-label=(i18n::strings(lang).reactions)
+            class="flex flex-wrap gap-2" {
+            @for (name, emoji) in REACTIONS {
+                @let pressed = given.contains(&format!("{slug} {name}"));
+                form method="post" action=(action) hx-post=(action) hx-target="#reactions" hx-swap="outerHTML" {
+                    input type="hidden" name="reaction" value=(name);
+                    input type="hidden" name="lang" value=(lang);
+                    button type="submit" title=(name) aria-pressed=(pressed)
+                        class={"btn btn-sm " @if pressed { "btn-primary" } @else { "btn-ghost border-base-300" }} {
+                        (emoji) " " (counts.get(name).copied().unwrap_or(0))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Counts for post cards, when the post has any.
+pub fn summary(slug: &str) -> Option<Markup> {
+    if !enabled() {
+        return None;
+    }
+    let counts = counts(slug);
+    let given: Vec<(&str, u64)> = REACTIONS
+        .iter()
+        .filter_map(|(name, emoji)| Some((*emoji, *counts.get(*name).filter(|&&n| n > 0)?)))
+        .collect();
+    (!given.is_empty()).then(|| {
+        html! {
+            p class="text-sm text-base-content/70" {
+                @for (emoji, count) in given {
+                    span class="mr-3" { (emoji) " " (count) }
+                }
+            }
+        }
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ReactionsQuery {
+    lang: Option<String>,
+}
+
+#[get("/reactions/{slug}")]
+pub async fn reactions_route(
+    path: web::Path<String>,
+    query: web::Query<ReactionsQuery>,
+    session: Session,
+) -> HttpResponse {
+    let slug = path.into_inner();
+    if !enabled() || !about::has_post(&slug) {
+        return HttpResponse::NotFound().body("Post not found");
+    }
+    let lang = i18n::known(query.lang.as_deref());
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(bar(&slug, lang, &given(&session)).into_string())
+}
+
+#[derive(Deserialize)]
+pub struct Reaction {
+    reaction: String,
+    lang: Option<String>,
+}
+
+/// Adds `key` to the reactions a session gave, or removes it when it is
+/// there already. Returns whether it was taken back.
+fn toggle(given: &mut Vec<String>, key: String) -> bool {
+    if given.contains(&key) {
+        given.retain(|other| *other != key);
+        return true;
+    }
+    given.push(key);
+    let overflow = given.len().saturating_sub(REMEMBERED);
+    given.drain(..overflow);
+    false
+}
+
+/// Counts one more reader for `reaction` to `slug`, or one less.
+fn count(store: &Store<Tally>, slug: &str, reaction: &str, taken_back: bool) -> io::Result<()> {
+    let _update = UPDATE.lock().unwrap_or_else(|err| err.into_inner());
+    let mut tally = store.get(slug).unwrap_or_else(|| Tally {
+        post: slug.to_string(),
+        counts: BTreeMap::new(),
+    });
+    let count = tally.counts.entry(reaction.to_string()).or_default();
+    *count = if taken_back {
+        count.saturating_sub(1)
+    } else {
+        *count + 1
+    };
+    store.put(tally)
+}
+
+/// Gives a reaction, or takes it back when this session gave it already.
+#[post("/post/{slug}/react")]
+pub async fn react_route(
+    path: web::Path<String>,
+    form: web::Form<Reaction>,
+    session: Session,
+    req: HttpRequest,
+) -> AwResult<HttpResponse> {
+    let slug = path.into_inner();
+    let known = REACTIONS.iter().any(|(name, _)| *name == form.reaction);
+    if !enabled() || !about::has_post(&slug) || !known {
+        return Ok(HttpResponse::NotFound().body("Not found"));
+    }
+    let lang = i18n::known(form.lang.as_deref());
+    let mut given = given(&session);
+
+    let status = if REACTED.allow(&rate_limit::client(&req)) {
+        let taken_back = toggle(&mut given, format!("{slug} {}", form.reaction));
+        let (post, reaction) = (slug.clone(), form.reaction.clone());
+        web::block(move || count(&TALLIES, &post, &reaction, taken_back))
+            .await?
+            .map_err(|err| {
+                log::error!("Could not store reaction: {err}");
+                error::ErrorInternalServerError("Could not save reaction")
+            })?;
+        session
+            .insert(GIVEN, &given)
+            .map_err(error::ErrorInternalServerError)?;
+        StatusCode::OK
+    } else {
+        StatusCode::TOO_MANY_REQUESTS
+    };
+
+    if !super::is_fragment_request(&req) {
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((
+                header::LOCATION,
+                format!("{}#reactions", about::post_url(&slug, lang)),
+            ))
+            .finish());
+    }
+    Ok(HttpResponse::build(status)
+        .content_type("text/html")
+        .body(bar(&slug, lang, &given).into_string()))
+}
+
+/// The posts readers reacted to most, for the admin area.
+pub fn overview() -> Markup {
+    if !enabled() {
+        return html! {};
+    }
+    let mut tallies: Vec<(u64, Tally)> = TALLIES
+        .all()
+        .into_iter()
+        .map(|tally| (tally.counts.values().sum(), tally))
+        .filter(|(total, _)| *total > 0)
+        .collect();
+    tallies.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.post.cmp(&b.1.post)));
+    tallies.truncate(TOP_POSTS);
+
+    html! {
+        section class="space-y-3" {
+            h2 class="text-lg font-semibold" { "Reactions" }
+            @if tallies.is_empty() {
+                p class="text-base-content/70" { "No reactions yet." }
+            } @else {
+                table class="table table-sm" {
+                    thead {
+                        tr {
+                            th { "Post" }
+                            @for (name, emoji) in REACTIONS {
+                                th class="text-right" title=(name) { (emoji) }
+                            }
+                            th class="text-right" { "Total" }
+                        }
+                    }
+                    tbody {
+                        @for (total, tally) in &tallies {
+                            tr {
+                                td {
+                                    a href={"/post/" (tally.post)} class="link link-hover" {
+                                        (about::post_title(&tally.post).unwrap_or(&tally.post))
+                                    }
+                                }
+                                @for (name, _) in REACTIONS {
+                                    td class="text-right tabular-nums" {
+                                        (tally.counts.get(name).copied().unwrap_or(0))
+                                    }
+                                }
+                                td class="text-right tabular-nums font-semibold" { (total) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A tally store in a fresh file, removed again when dropped.
+    struct TempStore(PathBuf);
+
+    impl TempStore {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("focus-{name}-{}.jsonl", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            TempStore(path)
+        }
+
+        fn open(&self) -> Store<Tally> {
+            Store::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn giving_a_reaction_twice_takes_it_back() {
+        let mut given = Vec::new();
+        assert!(!toggle(&mut given, "post like".to_string()));
+        assert!(!toggle(&mut given, "post love".to_string()));
+        assert_eq!(given, ["post like", "post love"]);
+        assert!(toggle(&mut given, "post like".to_string()));
+        assert_eq!(given, ["post love"]);
+    }
+
+    #[test]
+    fn sessions_forget_their_oldest_reactions() {
+        let mut given = Vec::new();
+        for index in 0..=REMEMBERED {
+            toggle(&mut given, format!("post-{index} like"));
+        }
+        assert_eq!(given.len(), REMEMBERED);
+        assert_eq!(given[0], "post-1 like");
+        // The forgotten one counts as new again
+        assert!(!toggle(&mut given, "post-0 like".to_string()));
+    }
+
+    #[test]
+    fn tallies_count_readers_per_reaction() {
+        let temp = TempStore::new("reactions-tally");
+        let store = temp.open();
+        count(&store, "post", "like", false).unwrap();
+        count(&store, "post", "like", false).unwrap();
+        count(&store, "post", "love", false).unwrap();
+        count(&store, "post", "love", true).unwrap();
+        // Taking back more than was given stops at zero
+        count(&store, "other", "like", true).unwrap();
+
+        let reopened = temp.open();
+        let counts = reopened.get("post").unwrap().counts;
+        assert_eq!(counts.get("like"), Some(&2));
+        assert_eq!(counts.get("love"), Some(&0));
+        assert_eq!(reopened.get("other").unwrap().counts.get("like"), Some(&0));
+    }
+}
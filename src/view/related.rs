@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Weight of shared tags against content similarity, both in `0..=1`.
+const TAG_WEIGHT: f64 = 0.5;
+/// Shorter words are mostly stop words and carry little meaning.
+const MIN_WORD_LEN: usize = 3;
+
+/// What a post is compared by: its tags and its plain text.
+pub struct Document<'a> {
+    pub tags: &'a [String],
+    pub text: &'a str,
+}
+
+/// Unit-length TF-IDF vector. Terms live in a `BTreeMap` so sums run in the
+/// same order every time and ties between equally similar posts resolve the
+/// same way on every start.
+type Vector = BTreeMap<String, f64>;
+
+fn terms(text: &str) -> BTreeMap<String, usize> {
+    let mut terms = BTreeMap::new();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_WORD_LEN)
+    {
+        *terms.entry(word.to_lowercase()).or_insert(0) += 1;
+    }
+    terms
+}
+
+fn vectors(documents: &[Document]) -> Vec<Vector> {
+    let terms: Vec<_> = documents.iter().map(|doc| terms(doc.text)).collect();
+
+    let mut frequency: BTreeMap<&str, usize> = BTreeMap::new();
+    for term in terms.iter().flat_map(BTreeMap::keys) {
+        *frequency.entry(term).or_insert(0) += 1;
+    }
+
+    let count = documents.len() as f64;
+    terms
+        .iter()
+        .map(|terms| {
+            let total = terms.values().sum::<usize>().max(1) as f64;
+            let mut vector: Vector = terms
+                .iter()
+                .map(|(term, &n)| {
+                    let idf = (count / frequency[term.as_str()] as f64).ln();
+                    (term.clone(), n as f64 / total * idf)
+                })
+                .filter(|(_, weight)| *weight > 0.0)
+                .collect();
+
+            let norm = vector.values().map(|w| w * w).sum::<f64>().sqrt();
+            if norm > 0.0 {
+                vector.values_mut().for_each(|w| *w /= norm);
+            }
+            vector
+        })
+        .collect()
+}
+
+fn cosine(a: &Vector, b: &Vector) -> f64 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(term, w)| large.get(term).map(|v| w * v))
+        .sum()
+}
+
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// For every document, the indices of at most `limit` others ranked by
+/// shared tags and TF-IDF similarity. Unrelated documents are left out and
+/// equal scores keep the input order.
+pub fn rank(documents: &[Document], limit: usize) -> Vec<Vec<usize>> {
+    let vectors = vectors(documents);
+    let tags: Vec<BTreeSet<String>> = documents
+        .iter()
+        .map(|doc| doc.tags.iter().cloned().collect())
+        .collect();
+
+    (0..documents.len())
+        .map(|index| {
+            let mut scores: Vec<(f64, usize)> = (0..documents.len())
+                .filter(|&other| other != index)
+                .map(|other| {
+                    let score = TAG_WEIGHT * jaccard(&tags[index], &tags[other])
+                        + (1.0 - TAG_WEIGHT) * cosine(&vectors[index], &vectors[other]);
+                    (score, other)
+                })
+                .filter(|(score, _)| *score > 0.0)
+                .collect();
+            scores.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            scores
+                .into_iter()
+                .take(limit)
+                .map(|(_, other)| other)
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn ranking_is_deterministic_with_ties() {
+        let rust = tags(&["rust"]);
+        let web = tags(&["web"]);
+        let none = tags(&[]);
+        let documents = [
+            Document {
+                tags: &rust,
+                text: "ownership borrowing lifetimes",
+            },
+            // Two identical documents tie for every other one
+            Document {
+                tags: &rust,
+                text: "ownership borrowing traits",
+            },
+            Document {
+                tags: &rust,
+                text: "ownership borrowing traits",
+            },
+            Document {
+                tags: &web,
+                text: "htmx requests templates",
+            },
+            Document {
+                tags: &none,
+                text: "gardening tomatoes",
+            },
+        ];
+
+        let first = rank(&documents, 3);
+        for _ in 0..10 {
+            assert_eq!(rank(&documents, 3), first);
+        }
+        // Tied scores keep the input order
+        assert_eq!(first[0], vec![1, 2]);
+        assert_eq!(first[1][0], 2);
+        assert_eq!(first[2][0], 1);
+        // Unrelated documents are left out
+        assert!(first[3].is_empty());
+        assert!(first[4].is_empty());
+    }
+
+    #[test]
+    fn limit_cuts_the_ranking() {
+        let shared = tags(&["notes"]);
+        let documents: Vec<Document> = (0..5)
+            .map(|_| Document {
+                tags: &shared,
+                text: "same words everywhere",
+            })
+            .collect();
+        let ranked = rank(&documents, 2);
+        assert_eq!(ranked[0], vec![1, 2]);
+        assert_eq!(ranked[4], vec![0, 1]);
+    }
+}
@@ -0,0 +1,859 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use markdown::ParseOptions;
+use markdown::mdast::{
+    AlignKind, AttributeContent, AttributeValue, FootnoteDefinition, Html, List, MdxJsxAttribute,
+    MdxJsxFlowElement, Node, Paragraph, Strong, Text,
+};
+use maud::PreEscaped;
+
+use super::shortcodes;
+use crate::html::escape;
+use crate::math;
+
+/// Protocols allowed in links and image sources, as in the markdown crate's
+/// own HTML compiler without `allow_dangerous_protocol`.
+pub const LINK_PROTOCOLS: [&str; 6] = ["http", "https", "mailto", "xmpp", "irc", "ircs"];
+pub const IMAGE_PROTOCOLS: [&str; 2] = ["http", "https"];
+
+/// Renders post markdown to HTML through its syntax tree, after the
+/// transforms for shortcodes, admonitions, collapsibles and definition lists.
+pub fn to_html(content: &str) -> Result<String, String> {
+    let options = ParseOptions {
+        constructs: math::constructs(),
+        ..ParseOptions::gfm()
+    };
+    let mut root = markdown::to_mdast(content, &options).map_err(|message| message.to_string())?;
+
+    transform(&mut root, content);
+
+    let mut renderer = Renderer::new(&root);
+    renderer.node(&root, false);
+    renderer.footnotes();
+    Ok(renderer.out)
+}
+
+fn transform(node: &mut Node, source: &str) {
+    if let Some(children) = node.children_mut() {
+        block_shortcodes(children, source);
+        for child in children.iter_mut() {
+            transform(child, source);
+            if let Some(element) = blockquote_element(child) {
+                *child = element;
+            }
+        }
+        definition_lists(children);
+        inline_shortcodes(children);
+    }
+}
+
+fn element(name: &str, attributes: &[(&str, &str)], children: Vec<Node>) -> Node {
+    Node::MdxJsxFlowElement(MdxJsxFlowElement {
+        children,
+        position: None,
+        name: Some(name.to_string()),
+        attributes: attributes
+            .iter()
+            .map(|(name, value)| {
+                AttributeContent::Property(MdxJsxAttribute {
+                    name: name.to_string(),
+                    value: Some(AttributeValue::Literal(value.to_string())),
+                })
+            })
+            .collect(),
+    })
+}
+
+fn text(value: &str) -> Node {
+    Node::Text(Text {
+        value: value.to_string(),
+        position: None,
+    })
+}
+
+fn html(value: String) -> Node {
+    Node::Html(Html {
+        value,
+        position: None,
+    })
+}
+
+/// Byte range of a paragraph in the source, which keeps shortcode
+/// arguments intact where the parsed text may have turned them into links
+/// or emphasis.
+fn paragraph_range(node: &Node) -> Option<Range<usize>> {
+    let Node::Paragraph(paragraph) = node else {
+        return None;
+    };
+    let position = paragraph.position.as_ref()?;
+    Some(position.start.offset..position.end.offset)
+}
+
+fn expand_or_source(tag: &shortcodes::Tag, inner: Option<&str>, written: &str) -> String {
+    let inner = match inner.map(to_html).transpose() {
+        Ok(inner) => inner.map(PreEscaped),
+        Err(err) => {
+            log::warn!(
+                "Could not render the content of shortcode `{}`: {err}",
+                tag.name
+            );
+            None
+        }
+    };
+    match shortcodes::expand(&tag.name, &tag.args, inner) {
+        Ok(markup) => markup.into_string(),
+        Err(err) => {
+            log::warn!("{err}");
+            format!("<p>{}</p>", escape(written))
+        }
+    }
+}
+
+/// A paragraph that is a single shortcode becomes its markup. A paired
+/// shortcode takes the markdown up to its closing tag, which is either
+/// the end of the same paragraph or a later paragraph of its own.
+fn block_shortcodes(children: &mut Vec<Node>, source: &str) {
+    let mut index = 0;
+    while index < children.len() {
+        let Some(range) = paragraph_range(&children[index]) else {
+            index += 1;
+            continue;
+        };
+        let written = &source[range.clone()];
+        let mut tags = shortcodes::tags(written);
+        let Some((opening, tag)) = tags
+            .next()
+            .filter(|(opening, tag)| opening.start == 0 && !tag.closing)
+        else {
+            index += 1;
+            continue;
+        };
+        let closing = tags.last().filter(|(closing, closer)| {
+            closer.closing && closer.name == tag.name && closing.end == written.len()
+        });
+
+        let (last, inner) = if opening.end == written.len() {
+            // A closing paragraph further down pairs with this one
+            let closer = children
+                .iter()
+                .enumerate()
+                .skip(index + 1)
+                .find_map(|(at, node)| {
+                    let closing = paragraph_range(node)?;
+                    let closer = shortcodes::tag(&source[closing.clone()])?;
+                    (closer.closing && closer.name == tag.name).then_some((at, closing))
+                });
+            match closer {
+                Some((at, closing)) => (at, Some(range.start + opening.end..closing.start)),
+                None => (index, None),
+            }
+        } else if let Some((closing, _)) = closing {
+            (
+                index,
+                Some(range.start + opening.end..range.start + closing.start),
+            )
+        } else {
+            // Text follows the shortcode, so it is expanded inline
+            index += 1;
+            continue;
+        };
+
+        let end = paragraph_range(&children[last]).map_or(range.end, |range| range.end);
+        let markup = expand_or_source(
+            &tag,
+            inner.map(|inner| &source[inner]),
+            &source[range.start..end],
+        );
+        children.splice(index..=last, [html(markup)]);
+        index += 1;
+    }
+}
+
+/// Expands shortcodes within text, which cannot take markdown content.
+fn inline_shortcodes(children: &mut Vec<Node>) {
+    if !children
+        .iter()
+        .any(|child| matches!(child, Node::Text(text) if text.value.contains("{{<")))
+    {
+        return;
+    }
+
+    let mut expanded = Vec::with_capacity(children.len());
+    for child in children.drain(..) {
+        let Node::Text(node) = &child else {
+            expanded.push(child);
+            continue;
+        };
+
+        let mut last = 0;
+        for (range, tag) in shortcodes::tags(&node.value).filter(|(_, tag)| !tag.closing) {
+            let markup = match shortcodes::expand(&tag.name, &tag.args, None) {
+                Ok(markup) => markup.into_string(),
+                Err(err) => {
+                    log::warn!("{err}");
+                    continue;
+                }
+            };
+            if last < range.start {
+                expanded.push(text(&node.value[last..range.start]));
+            }
+            expanded.push(html(markup));
+            last = range.end;
+        }
+        if last == 0 {
+            expanded.push(child);
+        } else if last < node.value.len() {
+            expanded.push(text(&node.value[last..]));
+        }
+    }
+    *children = expanded;
+}
+
+/// Alert class and default title of an admonition or callout kind.
+pub(super) fn alert_kind(kind: &str) -> Option<(&'static str, &'static str)> {
+    Some(match kind.to_ascii_uppercase().as_str() {
+        "NOTE" => ("alert-info", "Note"),
+        "TIP" => ("alert-success", "Tip"),
+        "IMPORTANT" => ("alert-info", "Important"),
+        "WARNING" => ("alert-warning", "Warning"),
+        "CAUTION" => ("alert-error", "Caution"),
+        _ => return None,
+    })
+}
+
+/// `> [!NOTE]` and friends become daisy alerts, `> [!DETAILS] Summary` a
+/// collapsible. Text after the marker replaces the default title.
+fn blockquote_element(node: &mut Node) -> Option<Node> {
+    let Node::Blockquote(blockquote) = node else {
+        return None;
+    };
+    let Some(Node::Paragraph(paragraph)) = blockquote.children.first_mut() else {
+        return None;
+    };
+    let Some(Node::Text(first)) = paragraph.children.first_mut() else {
+        return None;
+    };
+
+    let (marker, rest) = first.value.strip_prefix("[!")?.split_once(']')?;
+    let (kind, default_title) = if marker.eq_ignore_ascii_case("DETAILS") {
+        ("details", "Details")
+    } else {
+        alert_kind(marker)?
+    };
+
+    // The title is the rest of the marker's line, the body starts after it
+    let (title, body) = match rest.split_once('\n') {
+        Some((title, body)) => (title.trim().to_string(), body.to_string()),
+        None => (rest.trim().to_string(), String::new()),
+    };
+    let title = if title.is_empty() {
+        default_title.to_string()
+    } else {
+        title
+    };
+
+    first.value = body;
+    if first.value.is_empty() {
+        paragraph.children.remove(0);
+    }
+    if paragraph.children.is_empty() {
+        blockquote.children.remove(0);
+    }
+    let children = std::mem::take(&mut blockquote.children);
+
+    Some(if kind == "details" {
+        element(
+            "details",
+            &[("class", "collapse collapse-arrow bg-base-200")],
+            vec![
+                element(
+                    "summary",
+                    &[("class", "collapse-title")],
+                    vec![text(&title)],
+                ),
+                element("div", &[("class", "collapse-content")], children),
+            ],
+        )
+    } else {
+        let heading = Node::Paragraph(Paragraph {
+            children: vec![Node::Strong(Strong {
+                children: vec![text(&title)],
+                position: None,
+            })],
+            position: None,
+        });
+        let content = std::iter::once(heading).chain(children).collect();
+        element(
+            "div",
+            &[
+                ("role", "alert"),
+                ("class", &format!("alert alert-soft {kind}")),
+            ],
+            vec![element("div", &[], content)],
+        )
+    })
+}
+
+/// Splits the inline content of a paragraph at its line endings.
+fn lines(children: &[Node]) -> Vec<Vec<Node>> {
+    let mut lines = vec![Vec::new()];
+    for child in children {
+        match child {
+            Node::Text(node) => {
+                for (index, part) in node.value.split('\n').enumerate() {
+                    if index > 0 {
+                        lines.push(Vec::new());
+                    }
+                    if !part.is_empty() {
+                        lines.last_mut().unwrap().push(text(part));
+                    }
+                }
+            }
+            other => lines.last_mut().unwrap().push(other.clone()),
+        }
+    }
+    lines
+}
+
+/// Definition line content without its leading `: `, or `None` for a term.
+fn definition(line: &[Node]) -> Option<Vec<Node>> {
+    let Some(Node::Text(first)) = line.first() else {
+        return None;
+    };
+    let rest = first.value.strip_prefix(':')?;
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let mut line = line.to_vec();
+    let rest = rest.trim_start();
+    if rest.is_empty() {
+        line.remove(0);
+    } else {
+        line[0] = text(rest);
+    }
+    Some(line)
+}
+
+/// Paragraphs of `Term` lines each followed by `: Definition` lines become
+/// definition lists; consecutive ones merge into a single `<dl>`.
+fn definition_lists(children: &mut Vec<Node>) {
+    for child in children.iter_mut() {
+        let Node::Paragraph(paragraph) = child else {
+            continue;
+        };
+        let lines = lines(&paragraph.children);
+        let items: Vec<(bool, Vec<Node>)> = lines
+            .into_iter()
+            .map(|line| match definition(&line) {
+                Some(definition) => (true, definition),
+                None => (false, line),
+            })
+            .collect();
+
+        let valid = items
+            .first()
+            .is_some_and(|(is_definition, _)| !is_definition)
+            && items
+                .last()
+                .is_some_and(|(is_definition, _)| *is_definition);
+        if !valid {
+            continue;
+        }
+
+        let items = items
+            .into_iter()
+            .map(|(is_definition, line)| {
+                element(if is_definition { "dd" } else { "dt" }, &[], line)
+            })
+            .collect();
+        *child = element("dl", &[], items);
+    }
+
+    let mut merged: Vec<Node> = Vec::with_capacity(children.len());
+    for child in children.drain(..) {
+        match (merged.last_mut(), child) {
+            (Some(Node::MdxJsxFlowElement(previous)), Node::MdxJsxFlowElement(next))
+                if previous.name.as_deref() == Some("dl") && next.name.as_deref() == Some("dl") =>
+            {
+                previous.children.extend(next.children);
+            }
+            (_, child) => merged.push(child),
+        }
+    }
+    *children = merged;
+}
+
+/// Percent-encodes what is not allowed in a URL and drops URLs with a
+/// protocol outside `protocols`. The result is escaped for attributes.
+pub fn url(url: &str, protocols: &[&str]) -> String {
+    let scheme_end = url.find(':');
+    let path_start = url.find(['/', '?', '#']);
+    let scheme = scheme_end
+        .filter(|&end| path_start.is_none_or(|start| end < start))
+        .map(|end| &url[..end]);
+    if let Some(scheme) = scheme
+        && !protocols
+            .iter()
+            .any(|protocol| protocol.eq_ignore_ascii_case(scheme))
+    {
+        return String::new();
+    }
+
+    let mut encoded = String::with_capacity(url.len());
+    for c in url.chars() {
+        if c.is_ascii_alphanumeric() || "!#$%&'()*+,-./:;=?@[]_~".contains(c) {
+            encoded.push(c);
+        } else {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                encoded.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+    escape(&encoded)
+}
+
+fn footnote_id(identifier: &str) -> String {
+    urlencoding::encode(&identifier.to_lowercase()).into_owned()
+}
+
+struct Renderer<'a> {
+    definitions: HashMap<String, (&'a str, Option<&'a str>)>,
+    footnotes: HashMap<String, &'a FootnoteDefinition>,
+    /// Footnotes in the order they are first referenced, which numbers them.
+    order: Vec<String>,
+    references: HashMap<String, usize>,
+    out: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(root: &'a Node) -> Self {
+        fn collect<'a>(node: &'a Node, renderer: &mut Renderer<'a>) {
+            match node {
+                Node::Definition(definition) => {
+                    renderer
+                        .definitions
+                        .entry(definition.identifier.to_lowercase())
+                        .or_insert((&definition.url, definition.title.as_deref()));
+                }
+                Node::FootnoteDefinition(definition) => {
+                    renderer
+                        .footnotes
+                        .entry(definition.identifier.to_lowercase())
+                        .or_insert(definition);
+                }
+                _ => {}
+            }
+            for child in node.children().into_iter().flatten() {
+                collect(child, renderer);
+            }
+        }
+
+        let mut renderer = Renderer {
+            definitions: HashMap::new(),
+            footnotes: HashMap::new(),
+            order: Vec::new(),
+            references: HashMap::new(),
+            out: String::new(),
+        };
+        collect(root, &mut renderer);
+        renderer
+    }
+
+    fn push(&mut self, html: &str) {
+        self.out.push_str(html);
+    }
+
+    fn children(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.node(node, false);
+        }
+    }
+
+    /// Block children, where raw HTML ends its own line.
+    fn blocks(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.node(node, false);
+            if matches!(node, Node::Html(_)) {
+                self.push("\n");
+            }
+        }
+    }
+
+    fn wrap(&mut self, tag: &str, nodes: &[Node]) {
+        self.push(&format!("<{tag}>"));
+        self.children(nodes);
+        self.push(&format!("</{tag}>"));
+    }
+
+    /// `tight` unwraps paragraphs, as in list items without blank lines.
+    fn node(&mut self, node: &Node, tight: bool) {
+        match node {
+            Node::Root(root) => self.blocks(&root.children),
+            Node::Paragraph(paragraph) if tight => self.children(&paragraph.children),
+            Node::Paragraph(paragraph) => {
+                self.wrap("p", &paragraph.children);
+                self.push("\n");
+            }
+            Node::Heading(heading) => {
+                self.wrap(&format!("h{}", heading.depth), &heading.children);
+                self.push("\n");
+            }
+            Node::Blockquote(blockquote) => {
+                self.push("<blockquote>\n");
+                self.blocks(&blockquote.children);
+                self.push("</blockquote>\n");
+            }
+            Node::List(list) => self.list(list),
+            Node::Code(code) => {
+                let class = code
+                    .lang
+                    .as_ref()
+                    .map(|lang| format!(" class=\"language-{}\"", escape(lang)))
+                    .unwrap_or_default();
+                let value = escape(&code.value);
+                let newline = if value.is_empty() { "" } else { "\n" };
+                self.push(&format!(
+                    "<pre><code{class}>{value}{newline}</code></pre>\n"
+                ));
+            }
+            Node::Math(math) => self.push(&format!(
+                "<pre><code class=\"language-math math-display\">{}\n</code></pre>\n",
+                escape(&math.value)
+            )),
+            Node::Html(html) => self.push(&html.value),
+            Node::ThematicBreak(_) => self.push("<hr />\n"),
+            Node::Table(table) => self.table(&table.children, &table.align),
+            Node::Text(text) => self.push(&escape(&text.value)),
+            Node::Emphasis(emphasis) => self.wrap("em", &emphasis.children),
+            Node::Strong(strong) => self.wrap("strong", &strong.children),
+            Node::Delete(delete) => self.wrap("del", &delete.children),
+            Node::InlineCode(code) => self.push(&format!("<code>{}</code>", escape(&code.value))),
+            Node::InlineMath(math) => self.push(&format!(
+                "<code class=\"language-math math-inline\">{}</code>",
+                escape(&math.value)
+            )),
+            Node::Break(_) => self.push("<br />\n"),
+            Node::Link(link) => self.link(&link.url, link.title.as_deref(), &link.children),
+            Node::LinkReference(reference) => {
+                let (href, title) = self
+                    .definitions
+                    .get(&reference.identifier.to_lowercase())
+                    .copied()
+                    .unwrap_or_default();
+                self.link(href, title, &reference.children);
+            }
+            Node::Image(image) => self.image(&image.url, &image.alt, image.title.as_deref()),
+            Node::ImageReference(reference) => {
+                let (src, title) = self
+                    .definitions
+                    .get(&reference.identifier.to_lowercase())
+                    .copied()
+                    .unwrap_or_default();
+                self.image(src, &reference.alt, title);
+            }
+            Node::FootnoteReference(reference) => self.footnote_reference(&reference.identifier),
+            Node::MdxJsxFlowElement(element) => self.element(element),
+            _ => {}
+        }
+    }
+
+    fn link(&mut self, href: &str, title: Option<&str>, children: &[Node]) {
+        let title = title
+            .map(|title| format!(" title=\"{}\"", escape(title)))
+            .unwrap_or_default();
+        self.push(&format!(
+            "<a href=\"{}\"{title}>",
+            url(href, &LINK_PROTOCOLS)
+        ));
+        self.children(children);
+        self.push("</a>");
+    }
+
+    fn image(&mut self, src: &str, alt: &str, title: Option<&str>) {
+        let title = title
+            .map(|title| format!(" title=\"{}\"", escape(title)))
+            .unwrap_or_default();
+        self.push(&format!(
+            "<img src=\"{}\" alt=\"{}\"{title} />",
+            url(src, &IMAGE_PROTOCOLS),
+            escape(alt)
+        ));
+    }
+
+    fn list(&mut self, list: &List) {
+        let tag = if list.ordered { "ol" } else { "ul" };
+        let start = match list.start {
+            Some(start) if list.ordered && start != 1 => format!(" start=\"{start}\""),
+            _ => String::new(),
+        };
+        let tight = !list.spread
+            && !list
+                .children
+                .iter()
+                .any(|item| matches!(item, Node::ListItem(item) if item.spread));
+
+        self.push(&format!("<{tag}{start}>\n"));
+        for item in &list.children {
+            let Node::ListItem(item) = item else {
+                continue;
+            };
+            self.push("<li>");
+            match item.checked {
+                Some(true) => self.push("<input type=\"checkbox\" disabled=\"\" checked=\"\" /> "),
+                Some(false) => self.push("<input type=\"checkbox\" disabled=\"\" /> "),
+                None => {}
+            }
+            for (index, child) in item.children.iter().enumerate() {
+                if index > 0 || !tight {
+                    self.push("\n");
+                }
+                self.node(child, tight);
+            }
+            if !tight || item.children.len() > 1 {
+                self.push("\n");
+            }
+            self.push("</li>\n");
+        }
+        self.push(&format!("</{tag}>\n"));
+    }
+
+    fn table(&mut self, rows: &[Node], align: &[AlignKind]) {
+        self.push("<table>\n");
+        for (index, row) in rows.iter().enumerate() {
+            let Node::TableRow(row) = row else {
+                continue;
+            };
+            let cell_tag = if index == 0 { "th" } else { "td" };
+            match index {
+                0 => self.push("<thead>\n"),
+                1 => self.push("<tbody>\n"),
+                _ => {}
+            }
+
+            self.push("<tr>\n");
+            for (column, cell) in row.children.iter().enumerate() {
+                let align = match align.get(column) {
+                    Some(AlignKind::Left) => " align=\"left\"",
+                    Some(AlignKind::Right) => " align=\"right\"",
+                    Some(AlignKind::Center) => " align=\"center\"",
+                    _ => "",
+                };
+                self.push(&format!("<{cell_tag}{align}>"));
+                self.children(cell.children().map_or(&[], |children| children));
+                self.push(&format!("</{cell_tag}>\n"));
+            }
+            self.push("</tr>\n");
+
+            if index == 0 {
+                self.push("</thead>\n");
+            }
+        }
+        if rows.len() > 1 {
+            self.push("</tbody>\n");
+        }
+        self.push("</table>\n");
+    }
+
+    fn element(&mut self, element: &MdxJsxFlowElement) {
+        let name = element.name.as_deref().unwrap_or("div");
+        let attributes: String = element
+            .attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                AttributeContent::Property(MdxJsxAttribute {
+                    name,
+                    value: Some(AttributeValue::Literal(value)),
+                }) => Some(format!(" {name}=\"{}\"", escape(value))),
+                _ => None,
+            })
+            .collect();
+
+        self.push(&format!("<{name}{attributes}>"));
+        self.blocks(&element.children);
+        self.push(&format!("</{name}>\n"));
+    }
+
+    fn footnote_reference(&mut self, identifier: &str) {
+        let identifier = identifier.to_lowercase();
+        if !self.footnotes.contains_key(&identifier) {
+            return;
+        }
+        if !self.order.contains(&identifier) {
+            self.order.push(identifier.clone());
+        }
+        let number = self.order.iter().position(|id| *id == identifier).unwrap() + 1;
+        let count = self.references.entry(identifier.clone()).or_insert(0);
+        *count += 1;
+
+        let id = footnote_id(&identifier);
+        let suffix = if *count > 1 {
+            format!("-{count}")
+        } else {
+            String::new()
+        };
+        self.push(&format!(
+            "<sup><a href=\"#user-content-fn-{id}\" id=\"user-content-fnref-{id}{suffix}\" data-footnote-ref=\"\" aria-describedby=\"footnote-label\">{number}</a></sup>"
+        ));
+    }
+
+    /// Numbered list of the referenced footnotes, each with links back to
+    /// where it is referenced.
+    fn footnotes(&mut self) {
+        if self.order.is_empty() {
+            return;
+        }
+        self.push("<section data-footnotes=\"\" class=\"footnotes\"><h2 id=\"footnote-label\" class=\"sr-only\">Footnotes</h2>\n<ol>\n");
+
+        // Footnotes may reference footnotes that come later in the list
+        let mut index = 0;
+        while let Some(identifier) = self.order.get(index).cloned() {
+            index += 1;
+            let definition = self.footnotes[&identifier];
+            let id = footnote_id(&identifier);
+            self.push(&format!("<li id=\"user-content-fn-{id}\">\n"));
+
+            let (last, rest) = match definition.children.split_last() {
+                Some((Node::Paragraph(last), rest)) => (Some(last), rest),
+                _ => (None, definition.children.as_slice()),
+            };
+            self.blocks(rest);
+            self.push("<p>");
+            if let Some(last) = last {
+                self.children(&last.children);
+                self.push(" ");
+            }
+
+            let count = self.references.get(&identifier).copied().unwrap_or(1);
+            let backrefs: Vec<String> = (1..=count)
+                .map(|n| {
+                    let (suffix, mark) = if n > 1 {
+                        (format!("-{n}"), format!("<sup>{n}</sup>"))
+                    } else {
+                        (String::new(), String::new())
+                    };
+                    format!(
+                        "<a href=\"#user-content-fnref-{id}{suffix}\" data-footnote-backref=\"\" aria-label=\"Back to content\" class=\"data-footnote-backref\">↩{mark}</a>"
+                    )
+                })
+                .collect();
+            self.push(&backrefs.join(" "));
+            self.push("</p>\n</li>\n");
+        }
+
+        self.push("</ol>\n</section>\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(markdown: &str) -> String {
+        to_html(markdown).unwrap()
+    }
+
+    #[test]
+    fn footnotes_are_numbered_by_first_reference() {
+        let html = render("B[^b] then A[^a] and B[^b] again.\n\n[^a]: First.\n[^b]: Second.\n");
+        assert!(html.contains(
+            r##"<sup><a href="#user-content-fn-b" id="user-content-fnref-b" data-footnote-ref="" aria-describedby="footnote-label">1</a></sup>"##
+        ));
+        assert!(html.contains(r#"id="user-content-fnref-a" data-footnote-ref="" aria-describedby="footnote-label">2</a>"#));
+        assert!(html.contains(r#"id="user-content-fnref-b-2""#));
+        let list = &html[html.find("<section").unwrap()..];
+        assert!(list.find("Second.").unwrap() < list.find("First.").unwrap());
+        assert!(list.contains(r##"<a href="#user-content-fnref-b-2" data-footnote-backref="" aria-label="Back to content" class="data-footnote-backref">↩<sup>2</sup></a>"##));
+    }
+
+    #[test]
+    fn unreferenced_footnotes_are_left_out() {
+        let html = render("No references.\n\n[^a]: Unused.\n");
+        assert!(!html.contains("footnotes"));
+        assert!(!html.contains("Unused"));
+    }
+
+    #[test]
+    fn task_lists_have_disabled_checkboxes() {
+        let html = render("- [x] done\n- [ ] todo\n");
+        assert_eq!(
+            html,
+            "<ul>\n<li><input type=\"checkbox\" disabled=\"\" checked=\"\" /> done</li>\n<li><input type=\"checkbox\" disabled=\"\" /> todo</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn admonitions_become_alerts() {
+        let html = render("> [!WARNING]\n> Mind the gap.\n");
+        assert!(html.starts_with(r#"<div role="alert" class="alert alert-soft alert-warning"><div><p><strong>Warning</strong></p>"#));
+        assert!(html.contains("<p>Mind the gap.</p>"));
+        assert!(!html.contains("blockquote"));
+
+        let titled = render("> [!tip] Shortcut\n> Use the keyboard.\n");
+        assert!(titled.contains("alert-success"));
+        assert!(titled.contains("<strong>Shortcut</strong>"));
+
+        let unknown = render("> [!OTHER]\n> Stays a quote.\n");
+        assert!(unknown.starts_with("<blockquote>"));
+    }
+
+    #[test]
+    fn details_become_collapsibles() {
+        let html = render("> [!DETAILS] Spoiler\n> The butler did it.\n");
+        assert!(html.starts_with(r#"<details class="collapse collapse-arrow bg-base-200"><summary class="collapse-title">Spoiler</summary>"#));
+        assert!(html.contains(r#"<div class="collapse-content"><p>The butler did it.</p>"#));
+        assert!(html.trim_end().ends_with("</details>"));
+    }
+
+    #[test]
+    fn definition_lists_merge_consecutive_paragraphs() {
+        let html = render("Rust\n: A language\n\nCargo\n: Its build tool\n: And package manager\n");
+        assert_eq!(
+            html,
+            "<dl><dt>Rust</dt>\n<dd>A language</dd>\n<dt>Cargo</dt>\n<dd>Its build tool</dd>\n<dd>And package manager</dd>\n</dl>\n"
+        );
+        // A colon without a following space is text
+        assert_eq!(render("Time\n:30\n"), "<p>Time\n:30</p>\n");
+    }
+
+    #[test]
+    fn dangerous_links_and_images_lose_their_url() {
+        let html = render(
+            "[click](javascript:alert(1)) ![x](javascript:alert(1)) ![y](data:image/png;base64,AAAA)",
+        );
+        assert!(!html.contains("javascript"));
+        assert!(!html.contains("data:"));
+        assert!(html.contains(r#"<a href="">click</a>"#));
+        assert!(html.contains(r#"<img src="" alt="x" />"#));
+
+        let html = render("[mail](mailto:me@example.com) ![ok](/images/a.png \"A \\\"title\\\"\")");
+        assert!(html.contains(r#"<a href="mailto:me@example.com">mail</a>"#));
+        assert!(
+            html.contains(r#"<img src="/images/a.png" alt="ok" title="A &quot;title&quot;" />"#)
+        );
+    }
+
+    #[test]
+    fn urls_keep_allowed_protocols_and_encode_the_rest() {
+        assert_eq!(url("JavaScript:alert(1)", &LINK_PROTOCOLS), "");
+        assert_eq!(url("mailto:me@example.com", &IMAGE_PROTOCOLS), "");
+        assert_eq!(
+            url("HTTPS://example.com/a b?q=\"x\"&y", &LINK_PROTOCOLS),
+            "HTTPS://example.com/a%20b?q=%22x%22&amp;y"
+        );
+        // A colon after the path starts is not a protocol
+        assert_eq!(url("/posts/a:b", &IMAGE_PROTOCOLS), "/posts/a:b");
+        assert_eq!(url("ü", &LINK_PROTOCOLS), "%C3%BC");
+    }
+
+    #[test]
+    fn text_and_code_are_escaped() {
+        let html = render("<b>raw</b> & `a<b>`\n\n```rust\nfn x() -> &str {}\n```\n");
+        assert!(html.contains("<b>raw</b> &amp; <code>a&lt;b&gt;</code>"));
+        assert!(html.contains(
+            "<pre><code class=\"language-rust\">fn x() -&gt; &amp;str {}\n</code></pre>"
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use maud::{Markup, PreEscaped, html};
+use regex::Regex;
+
+use super::render;
+
+lazy_static! {
+    /// `{{< name args >}}` or the closing `{{< /name >}}` of a paired shortcode.
+    static ref SHORTCODE: Regex =
+        Regex::new(r#"\{\{<\s*(/)?\s*([A-Za-z][\w-]*)((?:\s+(?:[\w-]+=)?(?:"[^"]*"|'[^']*'|[^\s"'>]+))*)\s*>\}\}"#)
+            .unwrap();
+    static ref ARGUMENT: Regex =
+        Regex::new(r#"(?:([\w-]+)=)?(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+}
+
+/// A shortcode renders its arguments and, for paired shortcodes, the
+/// rendered markdown between the opening and closing tag.
+type Shortcode = fn(&Args, Option<Markup>) -> Result<Markup, String>;
+
+static SHORTCODES: [(&str, Shortcode); 4] = [
+    ("youtube", youtube),
+    ("vimeo", vimeo),
+    ("figure", figure),
+    ("callout", callout),
+];
+
+/// Positional and `key=value` arguments, values optionally quoted.
+#[derive(Default)]
+pub struct Args {
+    positional: Vec<String>,
+    named: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(text: &str) -> Self {
+        let mut args = Args::default();
+        for caps in ARGUMENT.captures_iter(text) {
+            let value = caps
+                .get(2)
+                .or(caps.get(3))
+                .or(caps.get(4))
+                .map_or(String::new(), |value| value.as_str().to_string());
+            match caps.get(1) {
+                Some(key) => {
+                    args.named.insert(key.as_str().to_string(), value);
+                }
+                None => args.positional.push(value),
+            }
+        }
+        args
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.named.get(name).map(String::as_str)
+    }
+
+    /// A named argument, or the first positional one.
+    fn first(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .or(self.positional.first().map(String::as_str))
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.first(name)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("missing `{name}` argument"))
+    }
+}
+
+/// A shortcode tag as written in a post.
+pub struct Tag {
+    /// `{{< /name >}}`, which ends a paired shortcode.
+    pub closing: bool,
+    pub name: String,
+    pub args: Args,
+}
+
+/// Shortcode tags in `text` with their byte ranges.
+pub fn tags(text: &str) -> impl Iterator<Item = (Range<usize>, Tag)> + '_ {
+    SHORTCODE.captures_iter(text).map(|caps| {
+        let tag = Tag {
+            closing: caps.get(1).is_some(),
+            name: caps[2].to_string(),
+            args: Args::parse(&caps[3]),
+        };
+        (caps.get(0).unwrap().range(), tag)
+    })
+}
+
+/// The tag `text` consists of, ignoring surrounding whitespace.
+pub fn tag(text: &str) -> Option<Tag> {
+    let text = text.trim();
+    tags(text)
+        .next()
+        .filter(|(range, _)| *range == (0..text.len()))
+        .map(|(_, tag)| tag)
+}
+
+pub fn expand(name: &str, args: &Args, inner: Option<Markup>) -> Result<Markup, String> {
+    let (_, shortcode) = SHORTCODES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .ok_or_else(|| format!("unknown shortcode `{name}`"))?;
+    shortcode(args, inner).map_err(|err| format!("shortcode `{name}`: {err}"))
+}
+
+fn video_id(args: &Args) -> Result<&str, String> {
+    let id = args.required("id")?;
+    if id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(id)
+    } else {
+        Err(format!("invalid video id `{id}`"))
+    }
+}
+
+fn embed(src: String, title: &str) -> Markup {
+    html! {
+        div class="aspect-video [&>iframe]:size-full" {
+            iframe src=(src) title=(title) loading="lazy"
+                referrerpolicy="strict-origin-when-cross-origin" allowfullscreen {}
+        }
+    }
+}
+
+/// `{{< youtube id [title="..."] >}}`
+fn youtube(args: &Args, _: Option<Markup>) -> Result<Markup, String> {
+    let id = video_id(args)?;
+    Ok(embed(
+        format!("https://www.youtube-nocookie.com/embed/{id}"),
+        args.get("title").unwrap_or("YouTube video"),
+    ))
+}
+
+/// `{{< vimeo id [title="..."] >}}`
+fn vimeo(args: &Args, _: Option<Markup>) -> Result<Markup, String> {
+    let id = video_id(args)?;
+    Ok(embed(
+        format!("https://player.vimeo.com/video/{id}"),
+        args.get("title").unwrap_or("Vimeo video"),
+    ))
+}
+
+/// `{{< figure src="..." [alt="..."] [caption="..."] [link="..."] >}}`
+fn figure(args: &Args, _: Option<Markup>) -> Result<Markup, String> {
+    let src = checked_url(args.required("src")?, &render::IMAGE_PROTOCOLS)?;
+    let link = args
+        .get("link")
+        .map(|link| checked_url(link, &render::LINK_PROTOCOLS))
+        .transpose()?;
+    let caption = args.get("caption");
+    let alt = args.get("alt").or(caption).unwrap_or_default();
+
+    let image = html! { img src=(src) alt=(alt) title=[args.get("title")]; };
+    Ok(html! {
+        figure {
+            @if let Some(link) = link {
+                a href=(link) { (image) }
+            } @else {
+                (image)
+            }
+            @if let Some(caption) = caption {
+                figcaption { (caption) }
+            }
+        }
+    })
+}
+
+/// `url` encoded for an attribute, or an error if its protocol is not one
+/// of `protocols`.
+fn checked_url(url: &str, protocols: &[&str]) -> Result<PreEscaped<String>, String> {
+    let encoded = render::url(url, protocols);
+    if encoded.is_empty() {
+        return Err(format!("unsupported URL `{url}`"));
+    }
+    Ok(PreEscaped(encoded))
+}
+
+/// `{{< callout [type=note|tip|important|warning|caution] [title="..."] >}} markdown {{< /callout >}}`
+fn callout(args: &Args, inner: Option<Markup>) -> Result<Markup, String> {
+    let kind = args.first("type").unwrap_or("note");
+    let (class, default_title) =
+        render::alert_kind(kind).ok_or_else(|| format!("unknown callout type `{kind}`"))?;
+
+    Ok(html! {
+        div role="alert" class={ "alert alert-soft " (class) } {
+            div {
+                p { strong { (args.get("title").unwrap_or(default_title)) } }
+                @if let Some(inner) = inner {
+                    (inner)
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(tag: &str) -> Result<String, String> {
+        let tag = super::tag(tag).expect("a shortcode tag");
+        expand(&tag.name, &tag.args, None).map(Markup::into_string)
+    }
+
+    #[test]
+    fn figure_renders_image_link_and_caption() {
+        let html =
+            render(r#"{{< figure src="/img/a b.png" link="https://example.com/?a=1&b=2" caption="A <cat>" >}}"#)
+                .unwrap();
+        assert_eq!(
+            html,
+            "<figure><a href=\"https://example.com/?a=1&amp;b=2\"><img src=\"/img/a%20b.png\" alt=\"A &lt;cat&gt;\"></a>\
+             <figcaption>A &lt;cat&gt;</figcaption></figure>"
+        );
+    }
+
+    #[test]
+    fn figure_refuses_script_urls() {
+        for tag in [
+            r#"{{< figure src="javascript:alert(1)" >}}"#,
+            r#"{{< figure src="data:image/svg+xml,<svg onload=alert(1)>" >}}"#,
+            r#"{{< figure src="x.png" link="javascript:alert(1)" >}}"#,
+            r#"{{< figure src="x.png" link=" JavaScript:alert(1)" >}}"#,
+        ] {
+            let err = render(tag).unwrap_err();
+            assert!(err.contains("unsupported URL"), "{tag}: {err}");
+        }
+        assert!(render(r#"{{< figure src="x.png" link="mailto:a@example.com" >}}"#).is_ok());
+    }
+
+    #[test]
+    fn videos_need_a_plain_id() {
+        assert!(
+            render(r#"{{< youtube abc-123_X >}}"#)
+                .unwrap()
+                .contains("https://www.youtube-nocookie.com/embed/abc-123_X")
+        );
+        assert!(render(r#"{{< vimeo "1 onload=x" >}}"#).is_err());
+        assert!(render("{{< nosuch >}}").is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use actix_web::{HttpResponse, get, web};
+
+use super::about;
+use crate::config::Server;
+use crate::dates;
+use crate::html::escape;
+
+/// `sitemap.xml` with the last change of every post, translation, series
+/// and author page. Its URLs are absolute, so there is none without
+/// `g_site_url`.
+#[get("/sitemap.xml")]
+pub async fn sitemap_route(server: web::Data<Server>) -> HttpResponse {
+    let Some(site) = server.site_url() else {
+        return HttpResponse::NotFound().body("Sitemap not found");
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (path, changed) in about::sitemap_entries() {
+        xml.push_str("  <url>\n");
+        xml.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            escape(&format!("{site}{path}"))
+        ));
+        if let Some(changed) = changed {
+            xml.push_str(&format!(
+                "    <lastmod>{}</lastmod>\n",
+                dates::local(changed).to_rfc3339()
+            ));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(xml)
+}
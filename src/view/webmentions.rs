@@ -0,0 +1,412 @@
+use std::time::Duration;
+
+use actix_session::Session;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, get, post, web};
+use chrono::{DateTime, SubsecRound, Utc};
+use lazy_static::lazy_static;
+use maud::{Markup, html};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{about, admin, i18n};
+use crate::config::{self, Server};
+use crate::rate_limit::{self, RateLimit};
+use crate::store::{Record, Store};
+use crate::webmention::fetch::HttpFetcher;
+use crate::webmention::{self, Kind, Verification};
+
+/// Mentions listed in the admin area for removal.
+const RECENT_MENTIONS: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Verified,
+    /// The source dropped the link or is gone.
+    Gone,
+    /// Taken down in the admin area; it stays down when sent again.
+    Removed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Mention {
+    source: String,
+    target: String,
+    /// Slug of the post `target` points at, in any language.
+    post: String,
+    kind: Kind,
+    author: Option<String>,
+    author_url: Option<String>,
+    title: Option<String>,
+    content: Option<String>,
+    published: Option<DateTime<Utc>>,
+    received: DateTime<Utc>,
+    status: Status,
+    /// `source` and `target`, one mention per pair.
+    id: String,
+}
+
+impl Record for Mention {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+lazy_static! {
+    static ref MENTIONS: Store<Mention> = {
+        let path = config::get().data_dir().join("webmentions.jsonl");
+        Store::open(&path)
+            .unwrap_or_else(|err| panic!("could not open webmentions at {}: {err}", path.display()))
+    };
+    static ref RECEIVED: RateLimit = RateLimit::new(30, Duration::from_secs(60 * 60));
+}
+
+fn enabled() -> bool {
+    config::get().webmentions()
+}
+
+/// Whether `/webmention` takes mentions. Targets are checked against
+/// `g_site_url`, so without it there is nothing to accept.
+fn receiving() -> bool {
+    enabled() && config::get().site_url().is_some()
+}
+
+pub fn prepare() {
+    if enabled() {
+        log::info!(
+            "Loaded {} webmentions from {}",
+            MENTIONS.all().len(),
+            MENTIONS.path().display()
+        );
+    }
+}
+
+/// Tells other sites where to send webmentions, for the page head.
+pub fn endpoint_link() -> Option<Markup> {
+    receiving().then(|| html! { link rel="webmention" href="/webmention"; })
+}
+
+/// Where the webmentions of a post go, loaded once the post is shown.
+pub fn placeholder(slug: &str, lang: &str) -> Option<Markup> {
+    enabled().then(|| {
+        html! {
+            div id="webmentions"
+                hx-get={"/webmentions/" (slug) "?lang=" (lang)}
+                hx-trigger="load"
+                hx-swap="outerHTML" {}
+        }
+    })
+}
+
+fn mention_id(source: &str, target: &str) -> String {
+    format!("{source} {target}")
+}
+
+/// The author's name, or the host of the page when it names nobody.
+fn by(mention: &Mention) -> String {
+    mention.author.clone().unwrap_or_else(|| {
+        Url::parse(&mention.source)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| mention.source.clone())
+    })
+}
+
+fn people(mentions: &[&Mention], label: &str) -> Markup {
+    html! {
+        p class="text-sm" {
+            span class="font-semibold" { (mentions.len()) " " (label) } ": "
+            @for (at, mention) in mentions.iter().enumerate() {
+                @if at > 0 { ", " }
+                a href=(mention.author_url.as_deref().unwrap_or(&mention.source))
+                    class="link link-hover" rel="nofollow ugc noopener" { (by(mention)) }
+            }
+        }
+    }
+}
+
+fn section(slug: &str, lang: &str) -> Markup {
+    let strings = i18n::strings(lang);
+    let mentions: Vec<Mention> = MENTIONS
+        .all()
+        .into_iter()
+        .filter(|mention| mention.post == slug && mention.status == Status::Verified)
+        .collect();
+    if mentions.is_empty() {
+        return html! { div id="webmentions" {} };
+    }
+    let of_kind = |kinds: &[Kind]| -> Vec<&Mention> {
+        mentions
+            .iter()
+            .filter(|mention| kinds.contains(&mention.kind))
+            .collect()
+    };
+    let likes = of_kind(&[Kind::Like]);
+    let reposts = of_kind(&[Kind::Repost]);
+    let replies = of_kind(&[Kind::Reply, Kind::Mention, Kind::Bookmark]);
+
+    html! {
+        section id="webmentions" class="space-y-3" {
+            h2 class="text-lg font-semibold" { (strings.webmentions) }
+            @if !likes.is_empty() {
+                (people(&likes, strings.likes))
+            }
+            @if !reposts.is_empty() {
+                (people(&reposts, strings.reposts))
+            }
+            @for reply in &replies {
+                article class="card bg-base-200" {
+                    div class="card-body gap-2 py-4" {
+                        p class="text-sm text-base-content/60" {
+                            @match &reply.author_url {
+                                Some(url) => a href=(url) class="font-semibold text-base-content link link-hover"
+                                    rel="nofollow ugc noopener" { (by(reply)) },
+                                None => span class="font-semibold text-base-content" { (by(reply)) },
+                            }
+                            " · " (about::time(reply.published.unwrap_or(reply.received), lang))
+                        }
+                        @if let Some(text) = reply.content.as_ref().or(reply.title.as_ref()) {
+                            p { (text) }
+                        }
+                        a href=(reply.source) class="link text-sm" rel="nofollow ugc noopener" {
+                            (strings.view_original)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MentionsQuery {
+    lang: Option<String>,
+}
+
+#[get("/webmentions/{slug}")]
+pub async fn webmentions_route(
+    path: web::Path<String>,
+    query: web::Query<MentionsQuery>,
+) -> HttpResponse {
+    let slug = path.into_inner();
+    if !enabled() || !about::has_post(&slug) {
+        return HttpResponse::NotFound().body("Post not found");
+    }
+    let lang = i18n::known(query.lang.as_deref());
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(section(&slug, lang).into_string())
+}
+
+/// Fetches the source and records, updates or drops the mention.
+fn process(source: String, target: String, post: String) {
+    let id = mention_id(&source, &target);
+    let existing = MENTIONS.get(&id);
+    if existing
+        .as_ref()
+        .is_some_and(|mention| mention.status == Status::Removed)
+    {
+        return;
+    }
+    let verification = match webmention::verify(&HttpFetcher::new().public_only(), &source, &target)
+    {
+        Ok(verification) => verification,
+        Err(err) => {
+            log::warn!("Could not verify webmention from {source}: {err}");
+            return;
+        }
+    };
+
+    let mention = match verification {
+        Verification::Removed => match existing {
+            Some(mention) => Mention {
+                status: Status::Gone,
+                ..mention
+            },
+            None => {
+                log::info!("Ignored webmention from {source}: it does not link to {target}");
+                return;
+            }
+        },
+        Verification::Links(kind, page) => Mention {
+            source,
+            target,
+            post,
+            kind,
+            author: page.author,
+            author_url: page.author_url,
+            title: page.title,
+            content: page.content,
+            published: page.published,
+            received: existing
+                .map(|mention| mention.received)
+                .unwrap_or_else(|| Utc::now().trunc_subsecs(0)),
+            status: Status::Verified,
+            id,
+        },
+    };
+
+    if let Err(err) = MENTIONS.put(mention) {
+        log::error!("Could not store webmention: {err}");
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Notification {
+    source: String,
+    target: String,
+}
+
+/// Receives a webmention. The source is fetched after answering, as the
+/// specification suggests, so slow sites do not hold the sender up.
+#[post("/webmention")]
+pub async fn receive_route(
+    server: web::Data<Server>,
+    form: web::Form<Notification>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let Some(site) = server.site_url().filter(|_| enabled()) else {
+        return HttpResponse::NotFound().body("Not found");
+    };
+    let bad_request = |message: &str| HttpResponse::BadRequest().body(message.to_string());
+
+    let (Ok(source), Ok(target)) = (Url::parse(&form.source), Url::parse(&form.target)) else {
+        return bad_request("source and target must be absolute URLs");
+    };
+    if !matches!(source.scheme(), "http" | "https") || !matches!(target.scheme(), "http" | "https")
+    {
+        return bad_request("source and target must be http(s) URLs");
+    }
+    if same_page(&source, &target) {
+        return bad_request("source and target must differ");
+    }
+
+    let Some(post) = target_post(&target, site) else {
+        return bad_request("target is not a post on this site");
+    };
+
+    if !RECEIVED.allow(&rate_limit::client(&req)) {
+        return HttpResponse::TooManyRequests().body("Too many webmentions, try again later");
+    }
+
+    let (source, target) = (source.to_string(), target.to_string());
+    actix_web::rt::spawn(async move {
+        if let Err(err) = web::block(move || process(source, target, post)).await {
+            log::error!("Webmention processing failed: {err}");
+        }
+    });
+    HttpResponse::Accepted().body("Webmention accepted, it is verified shortly")
+}
+
+/// Slug of the post `target` points at, if it is on the site at `site`.
+fn target_post(target: &Url, site: &str) -> Option<String> {
+    let site = Url::parse(site).ok()?;
+    if site.host_str() != target.host_str() {
+        return None;
+    }
+    about::slug_for_path(target.path())
+}
+
+fn same_page(a: &Url, b: &Url) -> bool {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    a.set_fragment(None);
+    b.set_fragment(None);
+    a == b
+}
+
+/// The latest mentions shown under posts, to take down spam.
+pub fn moderation() -> Markup {
+    if !enabled() {
+        return html! {};
+    }
+    let mut mentions: Vec<Mention> = MENTIONS
+        .all()
+        .into_iter()
+        .filter(|mention| mention.status == Status::Verified)
+        .collect();
+    mentions.sort_by_key(|mention| std::cmp::Reverse(mention.received));
+    mentions.truncate(RECENT_MENTIONS);
+
+    html! {
+        section class="space-y-3" {
+            h2 class="text-lg font-semibold" { "Recent webmentions" }
+            @for mention in &mentions {
+                article class="card bg-base-200" {
+                    div class="card-body gap-2 py-4 sm:flex-row sm:items-center sm:justify-between" {
+                        p class="text-sm" {
+                            span class="badge badge-ghost mr-2" { (mention.kind.name()) }
+                            a href=(mention.source) class="link" rel="nofollow noopener" { (by(mention)) }
+                            " → "
+                            a href=(mention.target) class="link" { (mention.post) }
+                        }
+                        form method="post" action="/admin/webmentions/remove"
+                            hx-post="/admin/webmentions/remove" hx-target="closest article" hx-swap="outerHTML" {
+                            input type="hidden" name="id" value=(mention.id);
+                            button type="submit" class="btn btn-sm btn-ghost" { "Remove" }
+                        }
+                    }
+                }
+            }
+            @if mentions.is_empty() {
+                p class="text-base-content/70" { "No webmentions yet." }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Removal {
+    id: String,
+}
+
+#[post("/admin/webmentions/remove")]
+pub async fn remove_route(
+    server: web::Data<Server>,
+    session: Session,
+    form: web::Form<Removal>,
+    req: HttpRequest,
+) -> AwResult<HttpResponse> {
+    admin::require(&server, &session)?;
+    let Some(mention) = MENTIONS.get(&form.id) else {
+        return Ok(HttpResponse::NotFound().body("Webmention not found"));
+    };
+    let removed = Mention {
+        status: Status::Removed,
+        ..mention
+    };
+    web::block(move || MENTIONS.put(removed))
+        .await?
+        .map_err(|err| {
+            log::error!("Could not store webmention: {err}");
+            actix_web::error::ErrorInternalServerError("Could not save webmention")
+        })?;
+
+    if super::is_fragment_request(&req) {
+        Ok(HttpResponse::Ok().content_type("text/html").finish())
+    } else {
+        Ok(HttpResponse::SeeOther()
+            .insert_header((header::LOCATION, "/admin"))
+            .finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(url: &str) -> Option<String> {
+        target_post(&Url::parse(url).unwrap(), "https://blog.example")
+    }
+
+    #[test]
+    fn targets_must_be_posts_on_the_configured_site() {
+        assert_eq!(
+            target("https://blog.example/post/my-own-world#comments"),
+            Some("my-own-world".to_string())
+        );
+        assert_eq!(target("https://other.example/post/my-own-world"), None);
+        assert_eq!(target("https://blog.example/post/no-such-post"), None);
+        assert_eq!(target("https://blog.example/about"), None);
+    }
+}
@@ -0,0 +1,400 @@
+use std::io::{self, Read};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use ureq::config::Config;
+use ureq::http::Uri;
+use ureq::unversioned::resolver::{DefaultResolver, ResolvedSocketAddrs, Resolver};
+use ureq::unversioned::transport::{DefaultConnector, NextTimeout};
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+/// Pages larger than this are cut off; links are near the top anyway.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// A fetched page after following redirects.
+pub struct Page {
+    pub status: u16,
+    /// Where the page ended up, which relative links resolve against.
+    pub url: Url,
+    /// Values of every `Link` header.
+    pub links: Vec<String>,
+    pub content_type: String,
+    pub body: String,
+}
+
+/// Talks HTTP for webmentions. The network is the default; tests can stand
+/// in a local server or answer without one.
+pub trait Fetcher {
+    fn get(&self, url: &str) -> Result<Page, String>;
+
+    /// Posts a form and returns the status it was answered with.
+    fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<u16, String>;
+}
+
+pub struct HttpFetcher {
+    agent: ureq::Agent,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        HttpFetcher {
+            agent: ureq::Agent::new_with_config(config(false)),
+        }
+    }
+
+    /// Refuses addresses on the loopback, private or link-local networks,
+    /// so senders cannot make the server probe what sits next to it.
+    pub fn public_only(self) -> Self {
+        HttpFetcher {
+            agent: ureq::Agent::with_parts(
+                config(true),
+                DefaultConnector::default(),
+                PublicResolver::default(),
+            ),
+        }
+    }
+
+    fn allowed(&self, url: &Url) -> Result<(), String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("{url} is not an http(s) URL"));
+        }
+        Ok(())
+    }
+}
+
+fn config(public_only: bool) -> Config {
+    let config = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .http_status_as_error(false)
+        // Followed by hand so every hop can be checked
+        .max_redirects(0)
+        .user_agent("focus-webmention");
+    if public_only {
+        // A proxy would resolve the host itself, past the check
+        config.proxy(None).build()
+    } else {
+        config.build()
+    }
+}
+
+/// Resolves like the default resolver but refuses hosts with any address
+/// that is not public. The connection goes to the addresses checked here,
+/// so a host cannot pass the check with one address and be fetched from
+/// another.
+#[derive(Debug, Default)]
+struct PublicResolver(DefaultResolver);
+
+impl Resolver for PublicResolver {
+    fn resolve(
+        &self,
+        uri: &Uri,
+        config: &Config,
+        timeout: NextTimeout,
+    ) -> Result<ResolvedSocketAddrs, ureq::Error> {
+        let addresses = self.0.resolve(uri, config, timeout)?;
+        if addresses.iter().any(|address| !is_public(address.ip())) {
+            return Err(ureq::Error::Io(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is not a public address", uri.host().unwrap_or_default()),
+            )));
+        }
+        Ok(addresses)
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    fn get(&self, url: &str) -> Result<Page, String> {
+        let mut url = Url::parse(url).map_err(|err| format!("invalid URL {url}: {err}"))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            self.allowed(&url)?;
+            let mut response = self
+                .agent
+                .get(url.as_str())
+                .call()
+                .map_err(|err| err.to_string())?;
+            let status = response.status();
+            let header = |name: &str| {
+                response
+                    .headers()
+                    .get_all(name)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            };
+
+            if status.is_redirection() {
+                let location = header("location")
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| format!("{url} redirects without a location"))?;
+                url = url
+                    .join(&location)
+                    .map_err(|err| format!("invalid redirect from {url}: {err}"))?;
+                continue;
+            }
+
+            let links = header("link");
+            let content_type = header("content-type")
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            let mut body = Vec::new();
+            response
+                .body_mut()
+                .as_reader()
+                .take(MAX_BODY)
+                .read_to_end(&mut body)
+                .map_err(|err| format!("could not read {url}: {err}"))?;
+
+            return Ok(Page {
+                status: status.as_u16(),
+                url,
+                links,
+                content_type,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            });
+        }
+
+        Err(format!("too many redirects from {url}"))
+    }
+
+    fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<u16, String> {
+        let parsed = Url::parse(url).map_err(|err| format!("invalid URL {url}: {err}"))?;
+        self.allowed(&parsed)?;
+        self.agent
+            .post(url)
+            .send_form(form.iter().copied())
+            .map(|response| response.status().as_u16())
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Answers requests on a local port with `respond(method, path, body)`,
+    /// one request per connection. Returns the address to fetch from.
+    fn serve(respond: fn(&str, &str, &str) -> String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                let mut parts = request.split_whitespace();
+                let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+                let response = respond(method, path, &String::from_utf8_lossy(&body));
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        address
+    }
+
+    fn response(status: &str, headers: &[&str], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+        for header in headers {
+            response.push_str(&format!("{header}\r\n"));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+        response
+    }
+
+    fn stand_in(method: &str, path: &str, body: &str) -> String {
+        match (method, path) {
+            ("GET", "/page") => response(
+                "200 OK",
+                &[
+                    "Content-Type: text/html; charset=utf-8",
+                    "Link: <https://hub.example/>; rel=\"hub\"",
+                    "Link: </webmention>; rel=\"webmention\"",
+                ],
+                "<p>Hello</p>",
+            ),
+            ("GET", "/moved") => response("301 Moved Permanently", &["Location: /hop/2"], ""),
+            ("GET", "/hop/0") => response("302 Found", &["Location: /page"], ""),
+            ("GET", hop) if hop.starts_with("/hop/") => {
+                let left: usize = hop["/hop/".len()..].parse().unwrap();
+                response("302 Found", &[&format!("Location: /hop/{}", left - 1)], "")
+            }
+            ("GET", "/loop") => response("302 Found", &["Location: /loop"], ""),
+            ("GET", "/nowhere") => response("302 Found", &[], ""),
+            ("GET", "/big") => response(
+                "200 OK",
+                &["Content-Type: text/plain"],
+                &"x".repeat(MAX_BODY as usize + 4096),
+            ),
+            ("POST", "/webmention") if body.contains("source=https%3A%2F%2Fa.example%2F") => {
+                response("202 Accepted", &[], "")
+            }
+            _ => response("404 Not Found", &[], "missing"),
+        }
+    }
+
+    #[test]
+    fn reads_status_headers_and_body() {
+        let server = serve(stand_in);
+        let page = HttpFetcher::new().get(&format!("{server}/page")).unwrap();
+        assert_eq!(page.status, 200);
+        assert_eq!(page.url.as_str(), format!("{server}/page"));
+        assert_eq!(page.content_type, "text/html; charset=utf-8");
+        assert_eq!(
+            page.links,
+            [
+                "<https://hub.example/>; rel=\"hub\"",
+                "</webmention>; rel=\"webmention\""
+            ]
+        );
+        assert_eq!(page.body, "<p>Hello</p>");
+
+        let missing = HttpFetcher::new().get(&format!("{server}/gone")).unwrap();
+        assert_eq!((missing.status, missing.body.as_str()), (404, "missing"));
+    }
+
+    #[test]
+    fn follows_redirects_up_to_the_limit() {
+        let server = serve(stand_in);
+        let fetcher = HttpFetcher::new();
+        let page = fetcher.get(&format!("{server}/moved")).unwrap();
+        assert_eq!(page.url.as_str(), format!("{server}/page"));
+        assert_eq!(page.body, "<p>Hello</p>");
+
+        // The limit itself still arrives, one more hop does not
+        let last = fetcher
+            .get(&format!("{server}/hop/{}", MAX_REDIRECTS - 1))
+            .unwrap();
+        assert_eq!(last.status, 200);
+        let err = fetcher
+            .get(&format!("{server}/hop/{MAX_REDIRECTS}"))
+            .err()
+            .unwrap();
+        assert!(err.contains("too many redirects"), "{err}");
+        let err = fetcher.get(&format!("{server}/loop")).err().unwrap();
+        assert!(err.contains("too many redirects"), "{err}");
+        let err = fetcher.get(&format!("{server}/nowhere")).err().unwrap();
+        assert!(err.contains("without a location"), "{err}");
+    }
+
+    #[test]
+    fn cuts_off_large_bodies() {
+        let server = serve(stand_in);
+        let page = HttpFetcher::new().get(&format!("{server}/big")).unwrap();
+        assert_eq!(page.body.len() as u64, MAX_BODY);
+    }
+
+    #[test]
+    fn posts_forms() {
+        let server = serve(stand_in);
+        let fetcher = HttpFetcher::new();
+        let endpoint = format!("{server}/webmention");
+        let form = [
+            ("source", "https://a.example/"),
+            ("target", "https://b.example/"),
+        ];
+        assert_eq!(fetcher.post_form(&endpoint, &form), Ok(202));
+        assert_eq!(
+            fetcher.post_form(&format!("{server}/other"), &form),
+            Ok(404)
+        );
+    }
+
+    #[test]
+    fn public_only_refuses_local_addresses() {
+        let fetcher = HttpFetcher::new().public_only();
+        for url in [
+            "http://127.0.0.1:9/",
+            "http://localhost:9/",
+            "http://[::1]:9/",
+            "http://10.0.0.1:9/",
+        ] {
+            let err = fetcher.get(url).err().expect(url);
+            assert!(err.contains("not a public address"), "{url}: {err}");
+        }
+        let err = fetcher
+            .post_form("http://127.0.0.1:9/webmention", &[("source", "x")])
+            .unwrap_err();
+        assert!(err.contains("not a public address"), "{err}");
+    }
+
+    #[test]
+    fn refuses_other_schemes() {
+        let err = HttpFetcher::new().get("file:///etc/passwd").err().unwrap();
+        assert!(err.contains("not an http(s) URL"), "{err}");
+    }
+
+    #[test]
+    fn public_addresses() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
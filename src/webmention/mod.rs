@@ -0,0 +1,571 @@
+use std::collections::BTreeSet;
+use std::io;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::view::about;
+use crate::{check, config, sanitize};
+
+pub mod fetch;
+
+use fetch::{Fetcher, HttpFetcher, Page};
+
+/// Longest excerpt of a reply kept, in characters.
+const MAX_CONTENT: usize = 500;
+
+lazy_static! {
+    static ref TAG: Regex =
+        Regex::new(r#"(?is)<([a-z][a-z0-9]*)\b((?:[^>"']|"[^"]*"|'[^']*')*)>"#).unwrap();
+    static ref MARKUP: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+    static ref TITLE: Regex = Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").unwrap();
+    static ref LINK_HEADER: Regex = Regex::new(r#"<([^>]*)>\s*((?:;\s*[^;,]+)*)"#).unwrap();
+    static ref REL: Regex = Regex::new(r#"(?i)rel\s*=\s*(?:"([^"]*)"|([^\s;,"]+))"#).unwrap();
+}
+
+/// What a page does with the post it links to, from its microformats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Reply,
+    Like,
+    Repost,
+    Bookmark,
+    Mention,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Reply => "reply",
+            Kind::Like => "like",
+            Kind::Repost => "repost",
+            Kind::Bookmark => "bookmark",
+            Kind::Mention => "mention",
+        }
+    }
+}
+
+/// What is shown of a page that links to a post.
+#[derive(Clone, Debug, Default)]
+pub struct Source {
+    pub author: Option<String>,
+    pub author_url: Option<String>,
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+}
+
+pub enum Verification {
+    Links(Kind, Source),
+    /// The source no longer links to the target, or is gone.
+    Removed,
+}
+
+/// Same URL for our purposes: fragments and a trailing slash do not matter.
+fn same_url(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| {
+        let url = url.split('#').next().unwrap_or(url);
+        url.trim_end_matches('/').to_string()
+    };
+    normalize(a) == normalize(b)
+}
+
+fn classes(attributes: &str) -> Vec<String> {
+    sanitize::attribute(attributes, "class")
+        .map(|class| class.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn text(html: &str) -> String {
+    let text = MARKUP.replace_all(html, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: String, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text,
+    }
+}
+
+/// The first element with `class`: its attributes and the HTML inside it,
+/// up to the next closing tag of the same name.
+fn element<'a>(html: &'a str, class: &str) -> Option<(&'a str, &'a str)> {
+    let caps = TAG
+        .captures_iter(html)
+        .find(|caps| classes(&caps[2]).iter().any(|found| found == class))?;
+    let name = caps[1].to_ascii_lowercase();
+    let attributes = caps.get(2).map_or("", |attributes| attributes.as_str());
+    let rest = &html[caps.get(0)?.end()..];
+    let end = rest
+        .to_ascii_lowercase()
+        .find(&format!("</{name}"))
+        .unwrap_or(rest.len());
+    Some((attributes, &rest[..end]))
+}
+
+fn element_text(html: &str, class: &str) -> Option<String> {
+    element(html, class)
+        .map(|(_, inner)| text(inner))
+        .filter(|text| !text.is_empty())
+}
+
+/// Reads the author, content and date from the microformats2 classes most
+/// sites use. Pages without them still count as a plain mention.
+fn source(html: &str) -> Source {
+    let author = element(html, "p-author").or_else(|| element(html, "h-card"));
+    let author_name = author.and_then(|(_, inner)| {
+        element_text(inner, "p-name").or_else(|| Some(text(inner)).filter(|name| !name.is_empty()))
+    });
+    let author_url = author.and_then(|(attributes, inner)| {
+        sanitize::attribute(attributes, "href").or_else(|| {
+            element(inner, "u-url")
+                .and_then(|(attributes, _)| sanitize::attribute(attributes, "href"))
+        })
+    });
+
+    let content = element_text(html, "e-content")
+        .or_else(|| element_text(html, "p-content"))
+        .or_else(|| element_text(html, "p-summary"))
+        .map(|content| truncate(content, MAX_CONTENT));
+    let published = element(html, "dt-published")
+        .and_then(|(attributes, inner)| {
+            sanitize::attribute(attributes, "datetime").or_else(|| Some(text(inner)))
+        })
+        .and_then(|date| DateTime::parse_from_rfc3339(date.trim()).ok())
+        .map(|date| date.to_utc());
+    let title = TITLE
+        .captures(html)
+        .map(|caps| text(&caps[1]))
+        .filter(|title| !title.is_empty());
+
+    Source {
+        author: author_name.map(|name| truncate(name, 100)),
+        author_url: author_url.filter(|url| url.starts_with("http")),
+        title,
+        content,
+        published,
+    }
+}
+
+/// Whether `source` links to `target`, and how. Sources answering 410 Gone
+/// and ones that dropped the link are removals.
+pub fn verify(
+    fetcher: &impl Fetcher,
+    source_url: &str,
+    target: &str,
+) -> Result<Verification, String> {
+    let page = fetcher.get(source_url)?;
+    if page.status == 410 {
+        return Ok(Verification::Removed);
+    }
+    if page.status >= 400 {
+        return Err(format!("source answered {}", page.status));
+    }
+
+    if !page.content_type.contains("html") {
+        return Ok(if page.body.contains(target) {
+            Verification::Links(Kind::Mention, Source::default())
+        } else {
+            Verification::Removed
+        });
+    }
+
+    let mut found = false;
+    let mut kind = Kind::Mention;
+    for caps in TAG.captures_iter(&page.body) {
+        let links_here = ["href", "src"].iter().any(|name| {
+            sanitize::attribute(&caps[2], name)
+                .and_then(|url| page.url.join(url.trim()).ok())
+                .is_some_and(|url| same_url(url.as_str(), target))
+        });
+        if !links_here {
+            continue;
+        }
+        found = true;
+        for class in classes(&caps[2]) {
+            kind = match class.as_str() {
+                "u-in-reply-to" => Kind::Reply,
+                "u-like-of" => Kind::Like,
+                "u-repost-of" => Kind::Repost,
+                "u-bookmark-of" => Kind::Bookmark,
+                _ => continue,
+            };
+        }
+    }
+
+    Ok(if found {
+        Verification::Links(kind, source(&page.body))
+    } else {
+        Verification::Removed
+    })
+}
+
+/// The webmention endpoint of `page`: a `Link` header first, then the first
+/// `<link>` or `<a>` in the document, as the specification orders them.
+fn endpoint(page: &Page) -> Option<Url> {
+    let is_webmention = |rel: &str| {
+        rel.split_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+    };
+
+    let from_header = page.links.iter().find_map(|header| {
+        LINK_HEADER.captures_iter(header).find_map(|caps| {
+            REL.captures(&caps[2])
+                .and_then(|rel| rel.get(1).or(rel.get(2)))
+                .filter(|rel| is_webmention(rel.as_str()))
+                .map(|_| caps[1].to_string())
+        })
+    });
+    let from_html = || {
+        page.content_type.contains("html").then_some(())?;
+        TAG.captures_iter(&page.body)
+            .filter(|caps| matches!(caps[1].to_ascii_lowercase().as_str(), "link" | "a"))
+            .filter(|caps| {
+                sanitize::attribute(&caps[2], "rel").is_some_and(|rel| is_webmention(&rel))
+            })
+            .find_map(|caps| sanitize::attribute(&caps[2], "href"))
+    };
+
+    let href = from_header.or_else(from_html)?;
+    page.url.join(href.trim()).ok()
+}
+
+/// Where a mention of `target` has to go, if it takes webmentions at all.
+pub fn discover(fetcher: &impl Fetcher, target: &str) -> Result<Option<Url>, String> {
+    let page = fetcher.get(target)?;
+    if page.status >= 400 {
+        return Err(format!("answered {}", page.status));
+    }
+    Ok(endpoint(&page))
+}
+
+/// Outcome of notifying one linked page.
+pub enum Sent {
+    Accepted(u16),
+    Refused(u16),
+    NoEndpoint,
+    Failed(String),
+}
+
+/// Notifies every page `source` links to that takes webmentions.
+pub fn send(fetcher: &impl Fetcher, source: &str, targets: &[String]) -> Vec<(String, Sent)> {
+    targets
+        .iter()
+        .map(|target| {
+            let sent = match discover(fetcher, target) {
+                Ok(Some(endpoint)) => {
+                    match fetcher
+                        .post_form(endpoint.as_str(), &[("source", source), ("target", target)])
+                    {
+                        Ok(status) if (200..300).contains(&status) => Sent::Accepted(status),
+                        Ok(status) => Sent::Refused(status),
+                        Err(err) => Sent::Failed(err),
+                    }
+                }
+                Ok(None) => Sent::NoEndpoint,
+                Err(err) => Sent::Failed(err),
+            };
+            (target.clone(), sent)
+        })
+        .collect()
+}
+
+/// External pages a post links to, each once.
+fn outbound_links(content: &str) -> Vec<String> {
+    check::links(content)
+        .into_iter()
+        .filter(|link| !link.image)
+        .filter(|link| {
+            Url::parse(&link.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+        })
+        .map(|link| link.url)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// `focus webmention send <slug> [--site <url>] [--dry-run]`. The post's
+/// public address comes from `--site` or `g_site_url`.
+pub fn run(args: &[String]) -> io::Result<()> {
+    let slug = match args {
+        [command, slug, ..] if command == "send" && !slug.starts_with("--") => slug,
+        _ => {
+            eprintln!("Usage: focus webmention send <slug> [--site <url>] [--dry-run]");
+            std::process::exit(1);
+        }
+    };
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let site = args
+        .iter()
+        .position(|arg| arg == "--site")
+        .and_then(|index| args.get(index + 1).cloned())
+        .or_else(|| config::get().site_url().map(str::to_string));
+
+    let Some(content) = about::post_content(slug) else {
+        eprintln!("Error: no post with slug {slug}");
+        std::process::exit(1);
+    };
+    let targets = outbound_links(content);
+
+    let fetcher = HttpFetcher::new();
+    if dry_run {
+        for target in &targets {
+            match discover(&fetcher, target) {
+                Ok(Some(endpoint)) => println!("would send  {target} via {endpoint}"),
+                Ok(None) => println!("no endpoint {target}"),
+                Err(err) => println!("failed      {target} ({err})"),
+            }
+        }
+        return Ok(());
+    }
+
+    let Some(site) = site else {
+        eprintln!("Error: set g_site_url or pass --site with the public address of the blog");
+        std::process::exit(1);
+    };
+    let source = format!("{}/post/{slug}", site.trim_end_matches('/'));
+    let results = send(&fetcher, &source, &targets);
+    let mut failed = 0;
+    for (target, sent) in &results {
+        match sent {
+            Sent::Accepted(status) => println!("sent    {status}  {target}"),
+            Sent::NoEndpoint => println!("skipped      {target} (no endpoint)"),
+            Sent::Refused(status) => {
+                failed += 1;
+                println!("refused {status}  {target}");
+            }
+            Sent::Failed(err) => {
+                failed += 1;
+                println!("failed       {target} ({err})");
+            }
+        }
+    }
+    println!(
+        "Sent webmentions for {} link(s) from {source}: {failed} failed",
+        results.len()
+    );
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const TARGET: &str = "https://blog.example/post/hello";
+
+    /// Endpoint and fields of a posted form.
+    type Posted = (String, Vec<(String, String)>);
+
+    /// Answers from fixed pages and records the forms posted to it.
+    #[derive(Default)]
+    struct Stub {
+        pages: HashMap<String, (u16, Vec<String>, &'static str)>,
+        posted: RefCell<Vec<Posted>>,
+    }
+
+    impl Stub {
+        fn page(mut self, url: &str, status: u16, body: &'static str) -> Self {
+            self.pages
+                .insert(url.to_string(), (status, Vec::new(), body));
+            self
+        }
+
+        fn header(mut self, url: &str, link: &str) -> Self {
+            self.pages
+                .insert(url.to_string(), (200, vec![link.to_string()], ""));
+            self
+        }
+    }
+
+    impl Fetcher for Stub {
+        fn get(&self, url: &str) -> Result<Page, String> {
+            let (status, links, body) = self
+                .pages
+                .get(url)
+                .ok_or_else(|| format!("could not resolve {url}"))?;
+            Ok(Page {
+                status: *status,
+                url: Url::parse(url).unwrap(),
+                links: links.clone(),
+                content_type: "text/html; charset=utf-8".to_string(),
+                body: body.to_string(),
+            })
+        }
+
+        fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<u16, String> {
+            let form = form
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            self.posted.borrow_mut().push((url.to_string(), form));
+            Ok(202)
+        }
+    }
+
+    #[test]
+    fn verify_classifies_replies() {
+        let stub = Stub::default().page(
+            "https://other.example/reply",
+            200,
+            r#"<html><head><title>Re: Hello</title></head><body>
+            <article class="h-entry">
+              <a class="p-author h-card" href="https://other.example/"><span class="p-name">Ada</span></a>
+              <a class="u-in-reply-to" href="https://blog.example/post/hello/#comments">in reply to</a>
+              <div class="e-content">Great <b>post</b>!</div>
+              <time class="dt-published" datetime="2024-05-01T10:00:00Z">May 1</time>
+            </article></body></html>"#,
+        );
+
+        let Verification::Links(kind, source) =
+            verify(&stub, "https://other.example/reply", TARGET).unwrap()
+        else {
+            panic!("reply not found");
+        };
+        assert_eq!(kind, Kind::Reply);
+        assert_eq!(source.author.as_deref(), Some("Ada"));
+        assert_eq!(source.author_url.as_deref(), Some("https://other.example/"));
+        assert_eq!(source.title.as_deref(), Some("Re: Hello"));
+        assert_eq!(source.content.as_deref(), Some("Great post !"));
+        assert_eq!(
+            source.published.map(|date| date.to_rfc3339()).as_deref(),
+            Some("2024-05-01T10:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn verify_classifies_likes_and_mentions() {
+        let stub = Stub::default()
+            .page(
+                "https://other.example/like",
+                200,
+                r#"<a class="u-like-of" href="/post/hello">liked</a>"#,
+            )
+            .page(
+                "https://blog.example/notes",
+                200,
+                r#"<p>See <a href="https://blog.example/post/hello">this</a>.</p>"#,
+            );
+
+        // Relative links resolve against the source
+        let Verification::Links(kind, _) = verify(
+            &stub,
+            "https://other.example/like",
+            "https://other.example/post/hello",
+        )
+        .unwrap() else {
+            panic!("like not found");
+        };
+        assert_eq!(kind, Kind::Like);
+
+        let Verification::Links(kind, source) =
+            verify(&stub, "https://blog.example/notes", TARGET).unwrap()
+        else {
+            panic!("mention not found");
+        };
+        assert_eq!(kind, Kind::Mention);
+        assert!(source.author.is_none());
+    }
+
+    #[test]
+    fn verify_detects_removals() {
+        let stub = Stub::default()
+            .page(
+                "https://other.example/edited",
+                200,
+                r#"<a href="https://blog.example/post/other">elsewhere</a>"#,
+            )
+            .page("https://other.example/deleted", 410, "Gone")
+            .page("https://other.example/missing", 404, "Not found");
+
+        assert!(matches!(
+            verify(&stub, "https://other.example/edited", TARGET),
+            Ok(Verification::Removed)
+        ));
+        assert!(matches!(
+            verify(&stub, "https://other.example/deleted", TARGET),
+            Ok(Verification::Removed)
+        ));
+        assert!(verify(&stub, "https://other.example/missing", TARGET).is_err());
+    }
+
+    #[test]
+    fn discover_prefers_the_link_header() {
+        let stub = Stub::default()
+            .header(
+                "https://a.example/",
+                r#"<https://a.example/other>; rel="other", </webmention>; rel="webmention""#,
+            )
+            .page(
+                "https://b.example/post",
+                200,
+                r#"<a href="/nope">x</a><link rel="me webmention" href="endpoint?x=1"><a rel="webmention" href="/later">"#,
+            )
+            .page("https://c.example/", 200, "<p>No endpoint</p>")
+            .page("https://d.example/", 500, "");
+
+        assert_eq!(
+            discover(&stub, "https://a.example/")
+                .unwrap()
+                .unwrap()
+                .as_str(),
+            "https://a.example/webmention"
+        );
+        assert_eq!(
+            discover(&stub, "https://b.example/post")
+                .unwrap()
+                .unwrap()
+                .as_str(),
+            "https://b.example/endpoint?x=1"
+        );
+        assert!(discover(&stub, "https://c.example/").unwrap().is_none());
+        assert!(discover(&stub, "https://d.example/").is_err());
+    }
+
+    #[test]
+    fn send_posts_to_discovered_endpoints() {
+        let stub = Stub::default()
+            .header("https://a.example/", r#"</webmention>; rel="webmention""#)
+            .page("https://c.example/", 200, "<p>No endpoint</p>");
+        let targets = [
+            "https://a.example/".to_string(),
+            "https://c.example/".to_string(),
+            "https://gone.example/".to_string(),
+        ];
+
+        let results = send(&stub, TARGET, &targets);
+        assert!(matches!(results[0].1, Sent::Accepted(202)));
+        assert!(matches!(results[1].1, Sent::NoEndpoint));
+        assert!(matches!(results[2].1, Sent::Failed(_)));
+
+        let posted = stub.posted.borrow();
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].0, "https://a.example/webmention");
+        assert_eq!(
+            posted[0].1,
+            [
+                ("source".to_string(), TARGET.to_string()),
+                ("target".to_string(), "https://a.example/".to_string())
+            ]
+        );
+    }
+}